@@ -0,0 +1,152 @@
+use nalgebra::{Point2, Point3, Vector2};
+
+use glutin::event::{ElementState, MouseButton};
+
+use crate::camera::{Camera, CameraController, CameraInput, FirstPersonController, FlyController, OrbitController, Projection};
+use crate::picking::Hit;
+use crate::scene::SceneCamera;
+use crate::input::{self, Action, ActionMap, Axis, Bindings, InputEvent, MouseLook};
+
+// Everything that reacts to input and changes over time. Knows nothing about
+// the window or OpenGL, so it runs the same live, replayed or in tests.
+pub struct App {
+    pub camera: Camera,
+    pub previous_camera: Camera, // Before the last tick, for interpolating
+    pub alpha: f32,              // How far rendering is between previous_camera and camera
+    pub camera_controller: Box<dyn CameraController>,
+    pub actions: ActionMap,
+    pub mouse_look: MouseLook,
+    pub wireframe: bool,
+    pub show_stats: bool, // Frame time graph over the scene
+    pub quit: bool,
+    pub cursor: Point2<f32>,    // Last known cursor position in window pixels
+    pub viewport: Vector2<f32>, // Window size in pixels
+    pub pick: Option<Point2<f32>>, // Pixel to pick at the next frame, the renderer knows what is there
+    pub selected: Option<Hit>,
+    pub screenshot: bool, // Save the next frame once it is drawn
+}
+
+impl App {
+
+    // Camera starts where the scene says.
+    pub fn new(width: u32, height: u32, bindings: Bindings, start: &SceneCamera) -> Self {
+        let mut camera = Camera::new(Point3::from(start.position), start.projection);
+        camera.set_aspect(width as f32, height as f32);
+
+        let mut camera_controller = FlyController { yaw: start.yaw, pitch: start.pitch, ..FlyController::default() };
+        camera_controller.update(&mut camera, &CameraInput::default(), 0.0); // Face the way yaw and pitch say
+
+        let mut mouse_look = MouseLook::new(&bindings.mouse);
+        mouse_look.captured = true; // Cursor is grabbed for looking around, Tab releases it
+
+        App {
+            previous_camera: camera.clone(),
+            alpha: 1.0,
+            camera,
+            camera_controller: Box::new(camera_controller),
+            actions: ActionMap::new(bindings),
+            mouse_look,
+            wireframe: false,
+            show_stats: false,
+            quit: false,
+            cursor: Point2::origin(),
+            viewport: Vector2::new(width as f32, height as f32),
+            pick: None,
+            selected: None,
+            screenshot: false,
+        }
+    }
+
+    pub fn handle_input(&mut self, event: &InputEvent) {
+        self.actions.handle_event(event);
+
+        match *event {
+            InputEvent::Resized(width, height) => {
+                self.camera.set_aspect(width as f32, height as f32); // To remove distrotion
+                self.viewport = Vector2::new(width as f32, height as f32);
+            }
+
+            InputEvent::CursorMoved(x, y) => self.cursor = Point2::new(x, y),
+
+            // Clicking into the window takes the cursor back
+            InputEvent::MouseButton(MouseButton::Left, ElementState::Pressed) => self.mouse_look.captured = true,
+
+            // Don't keep the cursor locked when switching to other windows
+            InputEvent::Focused(false) => self.mouse_look.captured = false,
+
+            _ => (),
+        }
+    }
+
+    // Actions that don't wait for a tick, so they still work while paused.
+    pub fn frame_actions(&mut self) {
+        // While looking around the cursor is hidden, so pick what is in the middle
+        if self.actions.take_pressed(Action::Select) {
+            self.pick = Some(if self.mouse_look.captured { Point2::from(self.viewport / 2.0) } else { self.cursor });
+        }
+
+        self.screenshot |= self.actions.take_pressed(Action::Screenshot);
+    }
+
+    // One simulation tick, applies everything that happened since the last one.
+    pub fn update(&mut self, delta_time: f32) {
+        self.previous_camera = self.camera.clone();
+        self.frame_actions(); // A replay feeds its events right before the tick
+        let actions = &self.actions;
+
+        if actions.just_pressed(Action::Quit) {
+            self.quit = true;
+        }
+        if actions.just_pressed(Action::ToggleCursor) {
+            self.mouse_look.captured = !self.mouse_look.captured;
+        }
+        if actions.just_pressed(Action::ToggleWireframe) {
+            self.wireframe = !self.wireframe;
+        }
+        if actions.just_pressed(Action::ToggleStats) {
+            self.show_stats = !self.show_stats;
+        }
+
+        // Switching camera controllers
+        if actions.just_pressed(Action::CameraFly) {
+            self.camera_controller = Box::new(FlyController::default());
+        }
+        if actions.just_pressed(Action::CameraOrbit) {
+            let distance = self.camera.position.coords.norm();
+            self.camera_controller = Box::new(OrbitController::new(Point3::origin(), distance));
+        }
+        if actions.just_pressed(Action::CameraFirstPerson) {
+            self.camera_controller = Box::new(FirstPersonController::default());
+        }
+
+        // Perspective => orthographic => infinite reverse-Z => perspective
+        if actions.just_pressed(Action::CycleProjection) {
+            self.camera.projection = match self.camera.projection {
+                Projection::Perspective { .. } => Projection::Orthographic { height: 5.0, near: 0.1, far: 100.0 },
+                Projection::Orthographic { .. } => Projection::InfiniteReversed { fov_y_degrees: 45.0, near: 0.1 },
+                Projection::InfiniteReversed { .. } => Projection::Perspective { fov_y_degrees: 45.0, near: 0.1, far: 100.0 },
+            };
+        }
+
+        let input = CameraInput {
+            movement: input::movement(actions, 2.0), // Sprint doubles the speed
+            look: self.mouse_look.look(actions)
+                + Vector2::new(actions.axis(Axis::TurnX), actions.axis(Axis::TurnY)) * 120.0 * delta_time, // Sticks turn up to 120°/s
+            zoom: actions.axis(Axis::Zoom),
+        };
+        self.camera_controller.update(&mut self.camera, &input, delta_time);
+        self.actions.end_frame();
+    }
+
+    // Camera to draw with, between the last two ticks.
+    pub fn view_camera(&self) -> Camera {
+        self.previous_camera.interpolate(&self.camera, self.alpha)
+    }
+
+    // Pick and screenshot requests are answered once per drawn frame, however
+    // many ticks ran before it.
+    pub fn end_frame(&mut self) {
+        self.pick = None;
+        self.screenshot = false;
+    }
+}
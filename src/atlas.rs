@@ -0,0 +1,487 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::image::Image;
+
+//
+// ─── REGIONS ────────────────────────────────────────────────────────────────────
+//
+
+// Texture coordinates of a sprite inside its page. (0, 0) is the first pixel of uploaded data.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UvRect {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+}
+
+// Where a sprite ended up. Pixel rectangle excludes padding and extruded border.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Region {
+    pub page: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub uv: UvRect,
+}
+
+#[derive(Debug)]
+pub enum AtlasError {
+    Io(std::io::Error),
+    Decode(PathBuf, png::DecodingError),
+    Encode(png::EncodingError),
+    Metadata(String),
+    DuplicateName(String),
+    Empty(String),
+    TooLarge { name: String, width: u32, height: u32 },
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AtlasError::Io(e) => write!(f, "atlas I/O error: {}", e),
+            AtlasError::Decode(path, e) => write!(f, "could not decode {}: {}", path.display(), e),
+            AtlasError::Encode(e) => write!(f, "could not encode atlas page: {}", e),
+            AtlasError::Metadata(e) => write!(f, "invalid atlas metadata: {}", e),
+            AtlasError::DuplicateName(name) => write!(f, "sprite '{}' was added twice", name),
+            AtlasError::Empty(name) => write!(f, "sprite '{}' has no pixels", name),
+            AtlasError::TooLarge { name, width, height } => write!(
+                f, "sprite '{}' does not fit into a {}x{} page", name, width, height
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AtlasError {}
+
+impl From<std::io::Error> for AtlasError {
+    fn from(e: std::io::Error) -> Self {
+        AtlasError::Io(e)
+    }
+}
+
+
+//
+// ─── BUILDER ────────────────────────────────────────────────────────────────────
+//
+
+pub struct AtlasBuilder {
+    page_width: u32,
+    page_height: u32,
+    padding: u32, // Empty pixels between sprites and around page edges
+    extrude: u32, // How many times are sprite edge pixels repeated outwards
+    sprites: Vec<(String, Image)>,
+}
+
+impl AtlasBuilder {
+
+    pub fn new(page_width: u32, page_height: u32) -> Self {
+        AtlasBuilder {
+            page_width,
+            page_height,
+            padding: 2,
+            extrude: 1,
+            sprites: Vec::new(),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn padding(mut self, pixels: u32) -> Self {
+        self.padding = pixels;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn extrude(mut self, pixels: u32) -> Self {
+        self.extrude = pixels;
+        self
+    }
+
+    pub fn add(&mut self, name: &str, image: Image) -> Result<&mut Self, AtlasError> {
+        if self.sprites.iter().any(|(n, _)| n == name) {
+            return Err(AtlasError::DuplicateName(name.to_string()));
+        }
+        if image.width == 0 || image.height == 0 {
+            return Err(AtlasError::Empty(name.to_string())); // Edges to extrude don't exist
+        }
+        self.sprites.push((name.to_string(), image));
+        Ok(self)
+    }
+
+    // Adds PNG file under its file name without extension, "images/car.png" => "car".
+    pub fn add_png(&mut self, path: impl AsRef<Path>) -> Result<&mut Self, AtlasError> {
+        let path = path.as_ref();
+        let image = Image::open(path).map_err(|e| AtlasError::Decode(path.to_path_buf(), e))?;
+        let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        self.add(&name, image)
+    }
+
+    // Packs all sprites using shelves. Tallest sprites go first so shelves waste less space.
+    pub fn build(&self) -> Result<Atlas, AtlasError> {
+        let mut order: Vec<&(String, Image)> = self.sprites.iter().collect();
+        order.sort_by(|(a_name, a), (b_name, b)| {
+            b.height.cmp(&a.height).then(b.width.cmp(&a.width)).then(a_name.cmp(b_name))
+        });
+
+        let mut pages: Vec<PageLayout> = Vec::new();
+        let mut atlas = Atlas {
+            pages: Vec::new(),
+            regions: BTreeMap::new(),
+        };
+
+        for (name, image) in order {
+            let cell_width = image.width + 2 * self.extrude;
+            let cell_height = image.height + 2 * self.extrude;
+
+            if cell_width + 2 * self.padding > self.page_width
+                || cell_height + 2 * self.padding > self.page_height
+            {
+                return Err(AtlasError::TooLarge {
+                    name: name.clone(),
+                    width: self.page_width,
+                    height: self.page_height,
+                });
+            }
+
+            let placed = pages.iter_mut().enumerate().find_map(|(page, layout)| {
+                layout.place(cell_width, cell_height).map(|(x, y)| (page, x, y))
+            });
+            let (page, x, y) = match placed {
+                Some(place) => place,
+                None => {
+                    let mut layout = PageLayout::new(self.page_width, self.page_height, self.padding);
+                    let (x, y) = layout.place(cell_width, cell_height).unwrap(); // Size checked above
+                    pages.push(layout);
+                    atlas.pages.push(Image::new(self.page_width, self.page_height));
+                    (pages.len() - 1, x, y)
+                }
+            };
+
+            let x = x + self.extrude;
+            let y = y + self.extrude;
+            let target = &mut atlas.pages[page];
+            target.blit(image, x, y);
+            extrude_edges(target, x, y, image.width, image.height, self.extrude);
+
+            atlas.regions.insert(name.clone(), Region {
+                page,
+                x,
+                y,
+                width: image.width,
+                height: image.height,
+                uv: UvRect {
+                    min: [
+                        x as f32 / self.page_width as f32,
+                        y as f32 / self.page_height as f32,
+                    ],
+                    max: [
+                        (x + image.width) as f32 / self.page_width as f32,
+                        (y + image.height) as f32 / self.page_height as f32,
+                    ],
+                },
+            });
+        }
+
+        Ok(atlas)
+    }
+}
+
+// Free space bookkeeping of one page: rows ("shelves") filled from left to right.
+struct PageLayout {
+    width: u32,
+    height: u32,
+    padding: u32,
+    shelves: Vec<Shelf>,
+}
+
+struct Shelf {
+    y: u32,
+    height: u32,
+    cursor_x: u32,
+}
+
+impl PageLayout {
+
+    fn new(width: u32, height: u32, padding: u32) -> Self {
+        PageLayout {
+            width,
+            height,
+            padding,
+            shelves: Vec::new(),
+        }
+    }
+
+    // Returns top-left corner for a cell of given size or None if the page is full.
+    fn place(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let right_edge = self.width - self.padding;
+        for shelf in self.shelves.iter_mut() {
+            if height <= shelf.height && shelf.cursor_x + width <= right_edge {
+                let x = shelf.cursor_x;
+                shelf.cursor_x += width + self.padding;
+                return Some((x, shelf.y));
+            }
+        }
+
+        // Open a new shelf under the last one
+        let y = match self.shelves.last() {
+            Some(shelf) => shelf.y + shelf.height + self.padding,
+            None => self.padding,
+        };
+        if y + height > self.height - self.padding {
+            return None;
+        }
+        self.shelves.push(Shelf {
+            y,
+            height,
+            cursor_x: self.padding + width + self.padding,
+        });
+        Some((self.padding, y))
+    }
+}
+
+// Repeats border pixels of the sprite at (x, y) outwards so linear filtering
+// and mipmaps don't bleed neighbouring sprites into it.
+fn extrude_edges(page: &mut Image, x: u32, y: u32, width: u32, height: u32, extrude: u32) {
+    if extrude == 0 {
+        return;
+    }
+    for row in y - extrude..y + height + extrude {
+        let source_y = row.clamp(y, y + height - 1);
+        for column in x - extrude..x + width + extrude {
+            let source_x = column.clamp(x, x + width - 1);
+            if source_x != column || source_y != row {
+                let pixel = page.pixel(source_x, source_y);
+                page.set_pixel(column, row, pixel);
+            }
+        }
+    }
+}
+
+
+//
+// ─── ATLAS ──────────────────────────────────────────────────────────────────────
+//
+
+pub struct Atlas {
+    pub pages: Vec<Image>,
+    pub regions: BTreeMap<String, Region>,
+}
+
+// What gets written next to the page PNGs.
+#[derive(Serialize, Deserialize)]
+struct AtlasMetadata {
+    pages: Vec<String>, // Page file names relative to the metadata file
+    regions: BTreeMap<String, Region>,
+}
+
+impl Atlas {
+
+    #[allow(dead_code)]
+    pub fn region(&self, name: &str) -> Option<&Region> {
+        self.regions.get(name)
+    }
+
+    #[allow(dead_code)]
+    pub fn uv(&self, name: &str) -> Option<UvRect> {
+        self.regions.get(name).map(|region| region.uv)
+    }
+
+    // Writes "<path>" as RON metadata and every page as "<stem>_<page>.png" next to it.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), AtlasError> {
+        let path = path.as_ref();
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+        let mut page_names = Vec::with_capacity(self.pages.len());
+        for (i, page) in self.pages.iter().enumerate() {
+            let name = format!("{}_{}.png", stem, i);
+            page.save(directory.join(&name)).map_err(AtlasError::Encode)?;
+            page_names.push(name);
+        }
+
+        let metadata = AtlasMetadata {
+            pages: page_names,
+            regions: self.regions.clone(),
+        };
+        let text = ron::ser::to_string_pretty(&metadata, ron::ser::PrettyConfig::default())
+            .map_err(|e| AtlasError::Metadata(e.to_string()))?;
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, AtlasError> {
+        let path = path.as_ref();
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+
+        let text = fs::read_to_string(path)?;
+        let metadata: AtlasMetadata = ron::de::from_str(&text)
+            .map_err(|e| AtlasError::Metadata(e.to_string()))?;
+
+        let mut pages = Vec::with_capacity(metadata.pages.len());
+        for name in metadata.pages.iter() {
+            let page_path = directory.join(name);
+            pages.push(Image::open(&page_path).map_err(|e| AtlasError::Decode(page_path, e))?);
+        }
+
+        if let Some((name, region)) = metadata.regions.iter().find(|(_, r)| r.page >= pages.len()) {
+            return Err(AtlasError::Metadata(format!(
+                "sprite '{}' refers to missing page {}", name, region.page
+            )));
+        }
+
+        Ok(Atlas {
+            pages,
+            regions: metadata.regions,
+        })
+    }
+}
+
+// Offline packing: `gl_end_my_life --pack-atlas <output.ron> <image.png>...`
+pub fn pack_files(output: &Path, inputs: &[PathBuf]) -> Result<Atlas, AtlasError> {
+    let mut builder = AtlasBuilder::new(2048, 2048);
+    for input in inputs {
+        builder.add_png(input)?;
+    }
+    let atlas = builder.build()?;
+    atlas.save(output)?;
+    Ok(atlas)
+}
+
+
+//
+// ─── TESTS ──────────────────────────────────────────────────────────────────────
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(width: u32, height: u32, rgba: [u8; 4]) -> Image {
+        let mut image = Image::new(width, height);
+        for pixel in image.data.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&rgba);
+        }
+        image
+    }
+
+    fn corner(region: &Region) -> (usize, u32, u32) {
+        (region.page, region.x, region.y)
+    }
+
+    #[test]
+    fn shelves_fill_left_to_right_then_down() {
+        let mut builder = AtlasBuilder::new(64, 64).padding(2).extrude(0);
+        builder.add("low", solid(20, 8, [255; 4])).unwrap();
+        builder.add("b", solid(20, 10, [255; 4])).unwrap();
+        builder.add("a", solid(20, 10, [255; 4])).unwrap();
+        let atlas = builder.build().unwrap();
+
+        // Tallest first, equal ones by name. The third doesn't fit the rest of the first shelf
+        assert_eq!(atlas.pages.len(), 1);
+        assert_eq!(corner(atlas.region("a").unwrap()), (0, 2, 2));
+        assert_eq!(corner(atlas.region("b").unwrap()), (0, 24, 2));
+        assert_eq!(corner(atlas.region("low").unwrap()), (0, 2, 14));
+        assert_eq!(atlas.uv("low").unwrap(), UvRect { min: [2.0 / 64.0, 14.0 / 64.0], max: [22.0 / 64.0, 22.0 / 64.0] });
+    }
+
+    #[test]
+    fn padding_stays_empty_and_edges_are_extruded() {
+        let mut sprite = Image::new(2, 2);
+        sprite.set_pixel(0, 0, [255, 0, 0, 255]);
+        sprite.set_pixel(1, 0, [0, 255, 0, 255]);
+        sprite.set_pixel(0, 1, [0, 0, 255, 255]);
+        sprite.set_pixel(1, 1, [255, 255, 255, 255]);
+
+        let mut builder = AtlasBuilder::new(16, 16).padding(1).extrude(2);
+        builder.add("sprite", sprite).unwrap();
+        let atlas = builder.build().unwrap();
+        let region = atlas.region("sprite").unwrap();
+        let page = &atlas.pages[0];
+
+        // Region excludes the extruded border, the cell starts after the padding
+        assert_eq!((region.x, region.y, region.width, region.height), (3, 3, 2, 2));
+        assert_eq!(page.pixel(0, 0), [0; 4]);
+        assert_eq!(page.pixel(7, 3), [0; 4]);
+        assert_eq!(page.pixel(1, 1), [255, 0, 0, 255]);
+        assert_eq!(page.pixel(6, 3), [0, 255, 0, 255]);
+        assert_eq!(page.pixel(3, 6), [0, 0, 255, 255]);
+        assert_eq!(page.pixel(6, 6), [255, 255, 255, 255]);
+        assert_eq!(page.pixel(4, 4), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn sprite_exactly_page_sized_fits() {
+        let mut builder = AtlasBuilder::new(16, 16).padding(0).extrude(0);
+        builder.add("full", solid(16, 16, [1, 2, 3, 4])).unwrap();
+        let atlas = builder.build().unwrap();
+        assert_eq!(atlas.pages, vec![solid(16, 16, [1, 2, 3, 4])]);
+        assert_eq!(atlas.uv("full").unwrap(), UvRect { min: [0.0, 0.0], max: [1.0, 1.0] });
+    }
+
+    #[test]
+    fn full_pages_spill_onto_a_second_page() {
+        let mut builder = AtlasBuilder::new(16, 16).padding(0).extrude(0);
+        builder.add("first", solid(16, 16, [255; 4])).unwrap();
+        builder.add("second", solid(8, 8, [255; 4])).unwrap();
+        let atlas = builder.build().unwrap();
+        assert_eq!(atlas.pages.len(), 2);
+        assert_eq!(corner(atlas.region("first").unwrap()), (0, 0, 0));
+        assert_eq!(corner(atlas.region("second").unwrap()), (1, 0, 0));
+    }
+
+    #[test]
+    fn oversized_and_duplicate_sprites_are_rejected() {
+        // 60 wide plus one extruded pixel and two of padding on each side is 66
+        let mut builder = AtlasBuilder::new(64, 64);
+        builder.add("wide", solid(60, 1, [255; 4])).unwrap();
+        match builder.build() {
+            Err(AtlasError::TooLarge { name, width: 64, height: 64 }) => assert_eq!(name, "wide"),
+            other => panic!("Expected TooLarge, got {:?}", other.map(|atlas| atlas.regions)),
+        }
+
+        let mut builder = AtlasBuilder::new(64, 64);
+        builder.add("twice", solid(1, 1, [255; 4])).unwrap();
+        match builder.add("twice", solid(1, 1, [255; 4])) {
+            Err(AtlasError::DuplicateName(name)) => assert_eq!(name, "twice"),
+            _ => panic!("Expected DuplicateName"),
+        }
+    }
+
+    #[test]
+    fn empty_sprites_are_rejected() {
+        let mut builder = AtlasBuilder::new(64, 64);
+        for (name, width, height) in [("flat", 4, 0), ("thin", 0, 4)] {
+            match builder.add(name, Image::new(width, height)) {
+                Err(AtlasError::Empty(empty)) => assert_eq!(empty, name),
+                _ => panic!("Expected Empty for {}", name),
+            }
+        }
+        assert!(builder.build().unwrap().pages.is_empty());
+    }
+
+    #[test]
+    fn saved_atlas_loads_back() {
+        let directory = std::env::temp_dir().join(format!("atlas_round_trip_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let sprite = directory.join("dot.png");
+        solid(3, 2, [10, 20, 30, 255]).save(&sprite).unwrap();
+
+        let mut builder = AtlasBuilder::new(10, 10); // Room for one sprite per page
+        builder.add_png(&sprite).unwrap().add("square", solid(4, 4, [200, 100, 0, 255])).unwrap();
+        let atlas = builder.build().unwrap();
+        atlas.save(directory.join("sprites.ron")).unwrap();
+        let loaded = Atlas::load(directory.join("sprites.ron"));
+        fs::remove_dir_all(&directory).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(atlas.pages.len(), 2);
+        assert_eq!(loaded.regions, atlas.regions);
+        assert_eq!(loaded.pages, atlas.pages);
+        assert!(loaded.region("dot").is_some());
+    }
+}
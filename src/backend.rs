@@ -0,0 +1,717 @@
+use std::ffi::CString;
+
+use gl33::global_loader::*;
+use gl33::*;
+use nalgebra::Matrix4;
+
+use crate::debug;
+use crate::gl_check;
+use crate::texture::{Sampler, TextureFormat};
+
+// The GL calls the renderer makes, so the code that makes them can be run
+// without a context. `Gl33` is the real thing, `MockBackend` (tests only)
+// writes down every call instead. Object names are plain u32s like in GL, 0
+// means none.
+pub trait Backend {
+
+    //
+    // ─── BUFFERS ────────────────────────────────────────────────────────────────────
+    //
+
+    fn create_buffer(&mut self) -> u32;
+    fn bind_buffer(&mut self, target: GLenum, buffer: u32);
+    fn buffer_data(&mut self, target: GLenum, data: &[u8], usage: GLenum); // To whatever is bound to `target`
+    fn delete_buffer(&mut self, buffer: u32);
+
+    fn create_vertex_array(&mut self) -> u32;
+    fn bind_vertex_array(&mut self, vertex_array: u32);
+    // Float attribute read from the bound GL_ARRAY_BUFFER, `stride` and `offset` in bytes.
+    fn vertex_attrib_pointer(&mut self, location: u32, components: i32, stride: i32, offset: usize);
+    fn enable_vertex_attrib_array(&mut self, location: u32);
+    fn delete_vertex_array(&mut self, vertex_array: u32);
+
+    //
+    // ─── TEXTURES AND SHADERS ───────────────────────────────────────────────────────
+    //
+
+    fn create_texture(&mut self) -> u32;
+    fn bind_texture(&mut self, unit: u32, target: GLenum, texture: u32);
+    // Allocates the base level, with `data` in it if there is any. `size` is
+    // width, height and depth or layers, 2D textures ignore the depth.
+    // Texture uploads and settings bind the texture to unit 0.
+    fn texture_image(&mut self, target: GLenum, texture: u32, format: TextureFormat, size: [u32; 3], data: Option<&[u8]>);
+    #[allow(dead_code)]
+    fn texture_sub_image(&mut self, target: GLenum, texture: u32, format: TextureFormat, offset: [u32; 3], size: [u32; 3], data: &[u8]);
+    fn texture_sampler(&mut self, target: GLenum, texture: u32, sampler: &Sampler);
+    fn generate_mipmaps(&mut self, target: GLenum, texture: u32);
+    fn delete_texture(&mut self, texture: u32);
+
+    // Compiles and links, panics with the log when either fails.
+    fn create_program(&mut self, vertex_source: &[u8], fragment_source: &[u8]) -> u32;
+    fn use_program(&mut self, program: u32);
+    fn uniform_location(&mut self, program: u32, name: &str) -> i32; // -1 when the shader has no such uniform
+    fn uniform_int(&mut self, location: i32, value: i32);
+    fn uniform_uint(&mut self, location: i32, value: u32);
+    fn uniform_vec2(&mut self, location: i32, value: [f32; 2]);
+    fn uniform_mat4(&mut self, location: i32, value: &Matrix4<f32>);
+    fn delete_program(&mut self, program: u32);
+
+    //
+    // ─── FRAMEBUFFERS ───────────────────────────────────────────────────────────────
+    //
+
+    fn create_renderbuffer(&mut self, format: GLenum, width: u32, height: u32) -> u32;
+    fn delete_renderbuffer(&mut self, renderbuffer: u32);
+    // 2D color texture and depth renderbuffer, panics when GL says that isn't
+    // complete. Left bound.
+    fn create_framebuffer(&mut self, color: u32, depth: u32) -> u32;
+    fn bind_framebuffer(&mut self, framebuffer: u32); // Drawing and reading, 0 is the window
+    fn delete_framebuffer(&mut self, framebuffer: u32);
+    fn viewport(&mut self, x: i32, y: i32, width: i32, height: i32);
+    // `rect` is x, y, width and height in the bound framebuffer, reading its
+    // color attachment or its depth with GL_DEPTH_COMPONENT. Rows start at
+    // the bottom and are tightly packed.
+    fn read_pixels(&mut self, rect: [i32; 4], format: GLenum, kind: GLenum, data: &mut [u8]);
+
+    //
+    // ─── STATE AND DRAWING ──────────────────────────────────────────────────────────
+    //
+
+    fn enable(&mut self, capability: GLenum);
+    fn disable(&mut self, capability: GLenum);
+    fn polygon_mode(&mut self, mode: GLenum); // Front and back
+    fn depth_func(&mut self, func: GLenum);
+    fn depth_mask(&mut self, write: bool);
+    fn clear_depth(&mut self, depth: f64);
+    fn clear_color(&mut self, color: [f32; 4]);
+    fn blend_func(&mut self, source: GLenum, destination: GLenum);
+    fn clear(&mut self, mask: GLbitfield);
+    fn clear_color_uint(&mut self, value: u32); // Integer color attachments must not be cleared with glClear
+
+    // Triangles from the bound vertex array's u32 indices.
+    fn draw_elements(&mut self, mode: GLenum, count: i32);
+    fn draw_arrays(&mut self, mode: GLenum, first: i32, count: i32);
+
+    //
+    // ─── DEBUGGING ──────────────────────────────────────────────────────────────────
+    //
+
+    // Names an object in debug messages and graphics debuggers, `kind` is
+    // e.g. GL_TEXTURE or GL_BUFFER.
+    fn label(&mut self, kind: GLenum, id: u32, name: &str);
+}
+
+
+//
+// ─── GL 3.3 ─────────────────────────────────────────────────────────────────────
+//
+
+// Straight through to gl33's global functions, so they have to be loaded.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Gl33;
+
+impl Backend for Gl33 {
+
+    fn create_buffer(&mut self) -> u32 {
+        let mut buffer = 0u32;
+        unsafe { gl_check!(glGenBuffers(1, &mut buffer)) };
+        assert_ne!(buffer, 0); // Buffer is succesfully created
+        buffer
+    }
+
+    fn bind_buffer(&mut self, target: GLenum, buffer: u32) {
+        unsafe { gl_check!(glBindBuffer(target, buffer)) };
+    }
+
+    fn buffer_data(&mut self, target: GLenum, data: &[u8], usage: GLenum) {
+        unsafe { gl_check!(glBufferData(target, data.len() as isize, data.as_ptr().cast(), usage)) };
+    }
+
+    fn delete_buffer(&mut self, buffer: u32) {
+        unsafe { gl_check!(glDeleteBuffers(1, &buffer)) };
+    }
+
+    fn create_vertex_array(&mut self) -> u32 {
+        let mut vertex_array = 0u32;
+        unsafe { gl_check!(glGenVertexArrays(1, &mut vertex_array)) };
+        assert_ne!(vertex_array, 0); // VAO is succesfully created
+        vertex_array
+    }
+
+    fn bind_vertex_array(&mut self, vertex_array: u32) {
+        gl_check!(glBindVertexArray(vertex_array));
+    }
+
+    fn vertex_attrib_pointer(&mut self, location: u32, components: i32, stride: i32, offset: usize) {
+        unsafe { gl_check!(glVertexAttribPointer(location, components, GL_FLOAT, 0, stride, offset as *const _)) };
+    }
+
+    fn enable_vertex_attrib_array(&mut self, location: u32) {
+        unsafe { gl_check!(glEnableVertexAttribArray(location)) };
+    }
+
+    fn delete_vertex_array(&mut self, vertex_array: u32) {
+        unsafe { gl_check!(glDeleteVertexArrays(1, &vertex_array)) };
+    }
+
+    fn create_texture(&mut self) -> u32 {
+        let mut texture = 0u32;
+        unsafe { gl_check!(glGenTextures(1, &mut texture)) };
+        assert_ne!(texture, 0); // Texture is succesfully created
+        texture
+    }
+
+    fn bind_texture(&mut self, unit: u32, target: GLenum, texture: u32) {
+        unsafe {
+            gl_check!(glActiveTexture(GLenum(GL_TEXTURE0.0 + unit)));
+            gl_check!(glBindTexture(target, texture));
+        }
+    }
+
+    // Rows are tightly packed so odd sized RGB images don't get skewed by the
+    // default 4 byte alignment.
+    fn texture_image(&mut self, target: GLenum, texture: u32, format: TextureFormat, size: [u32; 3], data: Option<&[u8]>) {
+        let pixels = data.map_or(std::ptr::null(), |data| data.as_ptr().cast()); // Null only allocates storage
+        let [width, height, depth] = size;
+        self.bind_texture(0, target, texture);
+        unsafe {
+            gl_check!(glPixelStorei(GL_UNPACK_ALIGNMENT, 1));
+            if target == GL_TEXTURE_2D {
+                gl_check!(glTexImage2D(
+                    target,
+                    0,                                   // Mipmap level 0 => base level
+                    format.internal_format().0 as i32,   // Format for storing the texture
+                    width as i32,
+                    height as i32,
+                    0,                                   // Border, always 0
+                    format.pixel_format(),               // Format of the uploaded data
+                    format.pixel_type(),
+                    pixels
+                ));
+            } else {
+                gl_check!(glTexImage3D(
+                    target, 0, format.internal_format().0 as i32,
+                    width as i32, height as i32, depth as i32, 0,
+                    format.pixel_format(), format.pixel_type(), pixels
+                ));
+            }
+            gl_check!(glPixelStorei(GL_UNPACK_ALIGNMENT, 4)); // Back to the default for everyone else
+        }
+    }
+
+    fn texture_sub_image(&mut self, target: GLenum, texture: u32, format: TextureFormat, offset: [u32; 3], size: [u32; 3], data: &[u8]) {
+        let [x, y, z] = offset;
+        let [width, height, depth] = size;
+        self.bind_texture(0, target, texture);
+        unsafe {
+            gl_check!(glPixelStorei(GL_UNPACK_ALIGNMENT, 1));
+            if target == GL_TEXTURE_2D {
+                gl_check!(glTexSubImage2D(
+                    target, 0,
+                    x as i32, y as i32, width as i32, height as i32,
+                    format.pixel_format(), format.pixel_type(), data.as_ptr().cast()
+                ));
+            } else {
+                gl_check!(glTexSubImage3D(
+                    target, 0,
+                    x as i32, y as i32, z as i32, width as i32, height as i32, depth as i32,
+                    format.pixel_format(), format.pixel_type(), data.as_ptr().cast()
+                ));
+            }
+            gl_check!(glPixelStorei(GL_UNPACK_ALIGNMENT, 4));
+        }
+    }
+
+    fn texture_sampler(&mut self, target: GLenum, texture: u32, sampler: &Sampler) {
+        self.bind_texture(0, target, texture);
+        unsafe {
+            gl_check!(glTexParameteri(target, GL_TEXTURE_MIN_FILTER, sampler.gl_min_filter().0 as i32));
+            gl_check!(glTexParameteri(target, GL_TEXTURE_MAG_FILTER, sampler.gl_mag_filter().0 as i32));
+            for (parameter, wrap) in [GL_TEXTURE_WRAP_S, GL_TEXTURE_WRAP_T, GL_TEXTURE_WRAP_R].iter().zip(sampler.gl_wrap().iter()) {
+                gl_check!(glTexParameteri(target, *parameter, wrap.0 as i32));
+            }
+            gl_check!(glTexParameterfv(target, GL_TEXTURE_BORDER_COLOR, sampler.border_color.as_ptr()));
+        }
+    }
+
+    fn generate_mipmaps(&mut self, target: GLenum, texture: u32) {
+        self.bind_texture(0, target, texture);
+        unsafe { gl_check!(glGenerateMipmap(target)) };
+    }
+
+    fn delete_texture(&mut self, texture: u32) {
+        unsafe { gl_check!(glDeleteTextures(1, &texture)) };
+    }
+
+    fn create_program(&mut self, vertex_source: &[u8], fragment_source: &[u8]) -> u32 {
+        let vertex_shader = compile_shader(GL_VERTEX_SHADER, vertex_source, "Vertex shader error");
+        let fragment_shader = compile_shader(GL_FRAGMENT_SHADER, fragment_source, "Fragment shader error");
+
+        let program = gl_check!(glCreateProgram());
+        gl_check!(glAttachShader(program, vertex_shader));   // Attach vertex shader to program
+        gl_check!(glAttachShader(program, fragment_shader)); // Attach fragment shader to program
+        gl_check!(glLinkProgram(program));                   // Link program
+
+        let mut success = 0;
+        unsafe { gl_check!(glGetProgramiv(program, GL_LINK_STATUS, &mut success)) }; // Get link status
+        if success == 0 {
+            let mut log: Vec<u8> = Vec::with_capacity(1024); // Buffer for possible error message
+            let mut len = 0;
+            unsafe {
+                gl_check!(glGetProgramInfoLog(program, log.capacity() as i32, &mut len, log.as_mut_ptr().cast()));
+                log.set_len(len as usize); // Trims the log of unused bytes
+            }
+            panic!("Shader program error: {}", String::from_utf8_lossy(&log));
+        }
+
+        // Delete shaders, they aren't needed anymore. So long, partners.
+        gl_check!(glDeleteShader(vertex_shader));
+        gl_check!(glDeleteShader(fragment_shader));
+        program
+    }
+
+    fn use_program(&mut self, program: u32) {
+        gl_check!(glUseProgram(program));
+    }
+
+    fn uniform_location(&mut self, program: u32, name: &str) -> i32 {
+        let name = CString::new(name).expect("Uniform name has a NUL in it");
+        unsafe { gl_check!(glGetUniformLocation(program, name.as_ptr().cast())) }
+    }
+
+    fn uniform_int(&mut self, location: i32, value: i32) {
+        unsafe { gl_check!(glUniform1i(location, value)) };
+    }
+
+    fn uniform_uint(&mut self, location: i32, value: u32) {
+        unsafe { gl_check!(glUniform1ui(location, value)) };
+    }
+
+    fn uniform_vec2(&mut self, location: i32, value: [f32; 2]) {
+        unsafe { gl_check!(glUniform2f(location, value[0], value[1])) };
+    }
+
+    fn uniform_mat4(&mut self, location: i32, value: &Matrix4<f32>) {
+        unsafe { gl_check!(glUniformMatrix4fv(location, 1, 0, value.as_ptr())) };
+    }
+
+    fn delete_program(&mut self, program: u32) {
+        gl_check!(glDeleteProgram(program));
+    }
+
+    fn create_renderbuffer(&mut self, format: GLenum, width: u32, height: u32) -> u32 {
+        let mut renderbuffer = 0u32;
+        unsafe {
+            gl_check!(glGenRenderbuffers(1, &mut renderbuffer));
+            gl_check!(glBindRenderbuffer(GL_RENDERBUFFER, renderbuffer));
+            gl_check!(glRenderbufferStorage(GL_RENDERBUFFER, format, width as i32, height as i32));
+        }
+        renderbuffer
+    }
+
+    fn delete_renderbuffer(&mut self, renderbuffer: u32) {
+        unsafe { gl_check!(glDeleteRenderbuffers(1, &renderbuffer)) };
+    }
+
+    fn create_framebuffer(&mut self, color: u32, depth: u32) -> u32 {
+        let mut framebuffer = 0u32;
+        unsafe {
+            gl_check!(glGenFramebuffers(1, &mut framebuffer));
+            assert_ne!(framebuffer, 0); // Framebuffer is succesfully created
+            gl_check!(glBindFramebuffer(GL_FRAMEBUFFER, framebuffer));
+            gl_check!(glFramebufferTexture2D(GL_FRAMEBUFFER, GL_COLOR_ATTACHMENT0, GL_TEXTURE_2D, color, 0));
+            gl_check!(glFramebufferRenderbuffer(GL_FRAMEBUFFER, GL_DEPTH_ATTACHMENT, GL_RENDERBUFFER, depth));
+
+            let status = gl_check!(glCheckFramebufferStatus(GL_FRAMEBUFFER));
+            if status != GL_FRAMEBUFFER_COMPLETE {
+                panic!("Framebuffer is incomplete: 0x{:X}", status.0);
+            }
+        }
+        framebuffer
+    }
+
+    fn bind_framebuffer(&mut self, framebuffer: u32) {
+        unsafe { gl_check!(glBindFramebuffer(GL_FRAMEBUFFER, framebuffer)) };
+    }
+
+    fn delete_framebuffer(&mut self, framebuffer: u32) {
+        unsafe { gl_check!(glDeleteFramebuffers(1, &framebuffer)) };
+    }
+
+    fn viewport(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        unsafe { gl_check!(glViewport(x, y, width, height)) };
+    }
+
+    fn read_pixels(&mut self, rect: [i32; 4], format: GLenum, kind: GLenum, data: &mut [u8]) {
+        let [x, y, width, height] = rect;
+        unsafe {
+            gl_check!(glPixelStorei(GL_PACK_ALIGNMENT, 1));
+            gl_check!(glReadPixels(x, y, width, height, format, kind, data.as_mut_ptr().cast()));
+            gl_check!(glPixelStorei(GL_PACK_ALIGNMENT, 4));
+        }
+    }
+
+    fn enable(&mut self, capability: GLenum) {
+        unsafe { gl_check!(glEnable(capability)) };
+    }
+
+    fn disable(&mut self, capability: GLenum) {
+        unsafe { gl_check!(glDisable(capability)) };
+    }
+
+    fn polygon_mode(&mut self, mode: GLenum) {
+        unsafe { gl_check!(glPolygonMode(GL_FRONT_AND_BACK, mode)) };
+    }
+
+    fn depth_func(&mut self, func: GLenum) {
+        unsafe { gl_check!(glDepthFunc(func)) };
+    }
+
+    fn depth_mask(&mut self, write: bool) {
+        unsafe { gl_check!(glDepthMask(write as u8)) };
+    }
+
+    fn clear_depth(&mut self, depth: f64) {
+        unsafe { gl_check!(glClearDepth(depth)) };
+    }
+
+    fn clear_color(&mut self, color: [f32; 4]) {
+        let [r, g, b, a] = color;
+        unsafe { gl_check!(glClearColor(r, g, b, a)) };
+    }
+
+    fn blend_func(&mut self, source: GLenum, destination: GLenum) {
+        unsafe { gl_check!(glBlendFunc(source, destination)) };
+    }
+
+    fn clear(&mut self, mask: GLbitfield) {
+        unsafe { gl_check!(glClear(mask)) };
+    }
+
+    fn clear_color_uint(&mut self, value: u32) {
+        unsafe { gl_check!(glClearBufferuiv(GL_COLOR, 0, [value; 4].as_ptr())) };
+    }
+
+    fn draw_elements(&mut self, mode: GLenum, count: i32) {
+        unsafe { gl_check!(glDrawElements(mode, count, GL_UNSIGNED_INT, std::ptr::null())) };
+    }
+
+    fn draw_arrays(&mut self, mode: GLenum, first: i32, count: i32) {
+        unsafe { gl_check!(glDrawArrays(mode, first, count)) };
+    }
+
+    fn label(&mut self, kind: GLenum, id: u32, name: &str) {
+        debug::label(kind, id, name);
+    }
+}
+
+// Panics with the compile log and `error_msg` when it doesn't compile.
+fn compile_shader(kind: GLenum, source: &[u8], error_msg: &str) -> u32 {
+    let shader = gl_check!(glCreateShader(kind));
+    unsafe {
+        gl_check!(glShaderSource(                 // Load shader from source
+            shader,                     // Shader object
+            1,                          // How many string objects
+            &source.as_ptr().cast(),    // Source code
+            &(source.len() as i32)      // Source code size
+        ));
+    }
+    gl_check!(glCompileShader(shader));
+
+    let mut success = 0;
+    unsafe { gl_check!(glGetShaderiv(shader, GL_COMPILE_STATUS, &mut success)) };
+    if success == 0 {
+        let mut log: Vec<u8> = Vec::with_capacity(1024); // Buffer for error message
+        let mut len = 0;
+        unsafe {
+            gl_check!(glGetShaderInfoLog(shader, log.capacity() as i32, &mut len, log.as_mut_ptr().cast()));
+            log.set_len(len as usize); // Trims the log of unused bytes
+        }
+        panic!("{}: {}", error_msg, String::from_utf8_lossy(&log));
+    }
+    shader
+}
+
+
+//
+// ─── MOCK ───────────────────────────────────────────────────────────────────────
+//
+
+// One call to the backend. GL enums are kept as their raw values.
+#[cfg(test)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Call {
+    CreateBuffer(u32),
+    BindBuffer { target: u32, buffer: u32 },
+    BufferData { target: u32, len: usize, usage: u32 },
+    DeleteBuffer(u32),
+    CreateVertexArray(u32),
+    BindVertexArray(u32),
+    VertexAttribPointer { location: u32, components: i32, stride: i32, offset: usize },
+    EnableVertexAttribArray(u32),
+    DeleteVertexArray(u32),
+    CreateTexture(u32),
+    BindTexture { unit: u32, target: u32, texture: u32 },
+    TextureImage { target: u32, texture: u32, size: [u32; 3], len: Option<usize> },
+    TextureSubImage { target: u32, texture: u32, offset: [u32; 3], size: [u32; 3], len: usize },
+    TextureSampler { target: u32, texture: u32, sampler: Sampler },
+    GenerateMipmaps { target: u32, texture: u32 },
+    DeleteTexture(u32),
+    CreateProgram(u32),
+    UseProgram(u32),
+    UniformLocation { program: u32, name: String },
+    UniformInt { location: i32, value: i32 },
+    UniformUint { location: i32, value: u32 },
+    UniformVec2 { location: i32, value: [f32; 2] },
+    UniformMat4 { location: i32 },
+    DeleteProgram(u32),
+    CreateRenderbuffer(u32),
+    DeleteRenderbuffer(u32),
+    CreateFramebuffer { framebuffer: u32, color: u32, depth: u32 },
+    BindFramebuffer(u32),
+    DeleteFramebuffer(u32),
+    Viewport { x: i32, y: i32, width: i32, height: i32 },
+    ReadPixels([i32; 4]),
+    Enable(u32),
+    Disable(u32),
+    PolygonMode(u32),
+    DepthFunc(u32),
+    DepthMask(bool),
+    ClearDepth(f64),
+    ClearColor([f32; 4]),
+    BlendFunc { source: u32, destination: u32 },
+    Clear(u32),
+    ClearColorUint(u32),
+    DrawElements { mode: u32, count: i32 },
+    DrawArrays { mode: u32, first: i32, count: i32 },
+    Label { kind: u32, id: u32, name: String },
+}
+
+// Backend for tests, records calls in order and hands out made up names.
+// Uniform locations are numbered by first lookup of each name.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct MockBackend {
+    pub calls: Vec<Call>,
+    next_name: u32,
+    uniforms: Vec<String>,
+}
+
+#[cfg(test)]
+impl MockBackend {
+
+    pub fn new() -> Self {
+        MockBackend::default()
+    }
+
+    fn name(&mut self) -> u32 {
+        self.next_name += 1;
+        self.next_name
+    }
+
+    // Calls since the last take, e.g. only the ones a draw made after setup.
+    pub fn take_calls(&mut self) -> Vec<Call> {
+        std::mem::take(&mut self.calls)
+    }
+}
+
+#[cfg(test)]
+impl Backend for MockBackend {
+
+    fn create_buffer(&mut self) -> u32 {
+        let buffer = self.name();
+        self.calls.push(Call::CreateBuffer(buffer));
+        buffer
+    }
+
+    fn bind_buffer(&mut self, target: GLenum, buffer: u32) {
+        self.calls.push(Call::BindBuffer { target: target.0, buffer });
+    }
+
+    fn buffer_data(&mut self, target: GLenum, data: &[u8], usage: GLenum) {
+        self.calls.push(Call::BufferData { target: target.0, len: data.len(), usage: usage.0 });
+    }
+
+    fn delete_buffer(&mut self, buffer: u32) {
+        self.calls.push(Call::DeleteBuffer(buffer));
+    }
+
+    fn create_vertex_array(&mut self) -> u32 {
+        let vertex_array = self.name();
+        self.calls.push(Call::CreateVertexArray(vertex_array));
+        vertex_array
+    }
+
+    fn bind_vertex_array(&mut self, vertex_array: u32) {
+        self.calls.push(Call::BindVertexArray(vertex_array));
+    }
+
+    fn vertex_attrib_pointer(&mut self, location: u32, components: i32, stride: i32, offset: usize) {
+        self.calls.push(Call::VertexAttribPointer { location, components, stride, offset });
+    }
+
+    fn enable_vertex_attrib_array(&mut self, location: u32) {
+        self.calls.push(Call::EnableVertexAttribArray(location));
+    }
+
+    fn delete_vertex_array(&mut self, vertex_array: u32) {
+        self.calls.push(Call::DeleteVertexArray(vertex_array));
+    }
+
+    fn create_texture(&mut self) -> u32 {
+        let texture = self.name();
+        self.calls.push(Call::CreateTexture(texture));
+        texture
+    }
+
+    fn bind_texture(&mut self, unit: u32, target: GLenum, texture: u32) {
+        self.calls.push(Call::BindTexture { unit, target: target.0, texture });
+    }
+
+    fn texture_image(&mut self, target: GLenum, texture: u32, _format: TextureFormat, size: [u32; 3], data: Option<&[u8]>) {
+        self.calls.push(Call::TextureImage { target: target.0, texture, size, len: data.map(<[u8]>::len) });
+    }
+
+    fn texture_sub_image(&mut self, target: GLenum, texture: u32, _format: TextureFormat, offset: [u32; 3], size: [u32; 3], data: &[u8]) {
+        self.calls.push(Call::TextureSubImage { target: target.0, texture, offset, size, len: data.len() });
+    }
+
+    fn texture_sampler(&mut self, target: GLenum, texture: u32, sampler: &Sampler) {
+        self.calls.push(Call::TextureSampler { target: target.0, texture, sampler: *sampler });
+    }
+
+    fn generate_mipmaps(&mut self, target: GLenum, texture: u32) {
+        self.calls.push(Call::GenerateMipmaps { target: target.0, texture });
+    }
+
+    fn delete_texture(&mut self, texture: u32) {
+        self.calls.push(Call::DeleteTexture(texture));
+    }
+
+    fn create_program(&mut self, _vertex_source: &[u8], _fragment_source: &[u8]) -> u32 {
+        let program = self.name();
+        self.calls.push(Call::CreateProgram(program));
+        program
+    }
+
+    fn use_program(&mut self, program: u32) {
+        self.calls.push(Call::UseProgram(program));
+    }
+
+    fn uniform_location(&mut self, program: u32, name: &str) -> i32 {
+        self.calls.push(Call::UniformLocation { program, name: name.to_string() });
+        match self.uniforms.iter().position(|uniform| uniform == name) {
+            Some(index) => index as i32,
+            None => {
+                self.uniforms.push(name.to_string());
+                self.uniforms.len() as i32 - 1
+            }
+        }
+    }
+
+    fn uniform_int(&mut self, location: i32, value: i32) {
+        self.calls.push(Call::UniformInt { location, value });
+    }
+
+    fn uniform_uint(&mut self, location: i32, value: u32) {
+        self.calls.push(Call::UniformUint { location, value });
+    }
+
+    fn uniform_vec2(&mut self, location: i32, value: [f32; 2]) {
+        self.calls.push(Call::UniformVec2 { location, value });
+    }
+
+    fn uniform_mat4(&mut self, location: i32, _value: &Matrix4<f32>) {
+        self.calls.push(Call::UniformMat4 { location });
+    }
+
+    fn delete_program(&mut self, program: u32) {
+        self.calls.push(Call::DeleteProgram(program));
+    }
+
+    fn create_renderbuffer(&mut self, _format: GLenum, _width: u32, _height: u32) -> u32 {
+        let renderbuffer = self.name();
+        self.calls.push(Call::CreateRenderbuffer(renderbuffer));
+        renderbuffer
+    }
+
+    fn delete_renderbuffer(&mut self, renderbuffer: u32) {
+        self.calls.push(Call::DeleteRenderbuffer(renderbuffer));
+    }
+
+    fn create_framebuffer(&mut self, color: u32, depth: u32) -> u32 {
+        let framebuffer = self.name();
+        self.calls.push(Call::CreateFramebuffer { framebuffer, color, depth });
+        framebuffer
+    }
+
+    fn bind_framebuffer(&mut self, framebuffer: u32) {
+        self.calls.push(Call::BindFramebuffer(framebuffer));
+    }
+
+    fn delete_framebuffer(&mut self, framebuffer: u32) {
+        self.calls.push(Call::DeleteFramebuffer(framebuffer));
+    }
+
+    fn viewport(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        self.calls.push(Call::Viewport { x, y, width, height });
+    }
+
+    // Reads zeros, as if nothing was drawn.
+    fn read_pixels(&mut self, rect: [i32; 4], _format: GLenum, _kind: GLenum, data: &mut [u8]) {
+        data.iter_mut().for_each(|byte| *byte = 0);
+        self.calls.push(Call::ReadPixels(rect));
+    }
+
+    fn enable(&mut self, capability: GLenum) {
+        self.calls.push(Call::Enable(capability.0));
+    }
+
+    fn disable(&mut self, capability: GLenum) {
+        self.calls.push(Call::Disable(capability.0));
+    }
+
+    fn polygon_mode(&mut self, mode: GLenum) {
+        self.calls.push(Call::PolygonMode(mode.0));
+    }
+
+    fn depth_func(&mut self, func: GLenum) {
+        self.calls.push(Call::DepthFunc(func.0));
+    }
+
+    fn depth_mask(&mut self, write: bool) {
+        self.calls.push(Call::DepthMask(write));
+    }
+
+    fn clear_depth(&mut self, depth: f64) {
+        self.calls.push(Call::ClearDepth(depth));
+    }
+
+    fn clear_color(&mut self, color: [f32; 4]) {
+        self.calls.push(Call::ClearColor(color));
+    }
+
+    fn blend_func(&mut self, source: GLenum, destination: GLenum) {
+        self.calls.push(Call::BlendFunc { source: source.0, destination: destination.0 });
+    }
+
+    fn clear(&mut self, mask: GLbitfield) {
+        self.calls.push(Call::Clear(mask.0));
+    }
+
+    fn clear_color_uint(&mut self, value: u32) {
+        self.calls.push(Call::ClearColorUint(value));
+    }
+
+    fn draw_elements(&mut self, mode: GLenum, count: i32) {
+        self.calls.push(Call::DrawElements { mode: mode.0, count });
+    }
+
+    fn draw_arrays(&mut self, mode: GLenum, first: i32, count: i32) {
+        self.calls.push(Call::DrawArrays { mode: mode.0, first, count });
+    }
+
+    fn label(&mut self, kind: GLenum, id: u32, name: &str) {
+        self.calls.push(Call::Label { kind: kind.0, id, name: name.to_string() });
+    }
+}
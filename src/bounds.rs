@@ -0,0 +1,254 @@
+use nalgebra::{Matrix3, Matrix4, Point3, Vector3, Vector4};
+
+//
+// ─── BOUNDING VOLUMES ───────────────────────────────────────────────────────────
+//
+
+// Axis aligned box, min <= max on every axis.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Aabb {
+
+    pub fn new(min: Point3<f32>, max: Point3<f32>) -> Self {
+        Aabb { min, max }
+    }
+
+    // Smallest box around all points. Empty input gives a box at the origin.
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Point3<f32>>) -> Self {
+        let mut points = points.into_iter();
+        let first = match points.next() {
+            Some(point) => *point,
+            None => return Aabb::new(Point3::origin(), Point3::origin()),
+        };
+        points.fold(Aabb::new(first, first), |aabb, point| Aabb {
+            min: aabb.min.inf(point),
+            max: aabb.max.sup(point),
+        })
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        nalgebra::center(&self.min, &self.max)
+    }
+
+    // Half of the size on every axis.
+    pub fn extents(&self) -> Vector3<f32> {
+        (self.max - self.min) / 2.0
+    }
+
+    // Box around this one after an affine transform. Still axis aligned, so
+    // it grows when rotated.
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Aabb {
+        let center = matrix.transform_point(&self.center());
+        let extents = Matrix3::from_fn(|r, c| matrix[(r, c)].abs()) * self.extents();
+        Aabb::new(center - extents, center + extents)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sphere {
+    pub center: Point3<f32>,
+    pub radius: f32,
+}
+
+impl Sphere {
+
+    // Not the tightest sphere, but centered on the box around the points.
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Point3<f32>> + Clone) -> Self {
+        let center = Aabb::from_points(points.clone()).center();
+        let radius = points.into_iter().map(|p| (p - center).norm()).fold(0.0, f32::max);
+        Sphere { center, radius }
+    }
+
+    // Non-uniform scale stretches the sphere, so the radius takes the largest one.
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Sphere {
+        let scale = (0..3).map(|i| matrix.column(i).xyz().norm()).fold(0.0, f32::max);
+        Sphere {
+            center: matrix.transform_point(&self.center),
+            radius: self.radius * scale,
+        }
+    }
+}
+
+// Both volumes of one mesh. The sphere is cheap to test, the box is tighter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub aabb: Aabb,
+    pub sphere: Sphere,
+}
+
+impl Bounds {
+
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Point3<f32>> + Clone) -> Self {
+        Bounds {
+            aabb: Aabb::from_points(points.clone()),
+            sphere: Sphere::from_points(points),
+        }
+    }
+
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Bounds {
+        Bounds {
+            aabb: self.aabb.transformed(matrix),
+            sphere: self.sphere.transformed(matrix),
+        }
+    }
+}
+
+
+//
+// ─── FRUSTUM ────────────────────────────────────────────────────────────────────
+//
+
+// Six planes facing inwards: left, right, bottom, top, near, far.
+// Each is (normal, distance) so that normal · point + distance >= 0 is inside.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    pub planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+
+    // Planes straight from projection * view (Gribb & Hartmann). Points inside
+    // have -w <= x, y, z <= w in clip space, every inequality is one plane.
+    pub fn from_matrix(matrix: &Matrix4<f32>) -> Self {
+        let row = |i: usize| matrix.row(i).transpose();
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+        let normalize = |plane: Vector4<f32>| {
+            let length = plane.xyz().norm();
+            // Infinite far plane has no normal, only its sign matters then
+            if length > f32::EPSILON { plane / length } else { plane }
+        };
+
+        Frustum {
+            planes: [
+                normalize(w + x),
+                normalize(w - x),
+                normalize(w + y),
+                normalize(w - y),
+                normalize(w + z),
+                normalize(w - z),
+            ],
+        }
+    }
+
+    fn distance(plane: &Vector4<f32>, point: &Point3<f32>) -> f32 {
+        plane.xyz().dot(&point.coords) + plane.w
+    }
+
+    #[allow(dead_code)]
+    pub fn contains_point(&self, point: &Point3<f32>) -> bool {
+        self.planes.iter().all(|plane| Frustum::distance(plane, point) >= 0.0)
+    }
+
+    pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+        self.planes.iter().all(|plane| Frustum::distance(plane, &sphere.center) >= -sphere.radius)
+    }
+
+    // Only the corner furthest along each plane normal needs checking. Can
+    // keep boxes near frustum corners that are actually outside, never drops
+    // a visible one.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            let corner = Point3::new(
+                if plane.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+                if plane.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+                if plane.z >= 0.0 { aabb.max.z } else { aabb.min.z },
+            );
+            Frustum::distance(plane, &corner) >= 0.0
+        })
+    }
+
+    pub fn intersects(&self, bounds: &Bounds) -> bool {
+        self.intersects_sphere(&bounds.sphere) && self.intersects_aabb(&bounds.aabb)
+    }
+}
+
+
+//
+// ─── TESTS ──────────────────────────────────────────────────────────────────────
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::{Camera, Projection};
+
+    fn unit_cube() -> Bounds {
+        let corners: Vec<_> = (0..8)
+            .map(|i| Point3::new(
+                if i & 1 == 0 { -0.5 } else { 0.5 },
+                if i & 2 == 0 { -0.5 } else { 0.5 },
+                if i & 4 == 0 { -0.5 } else { 0.5 },
+            ))
+            .collect();
+        Bounds::from_points(&corners)
+    }
+
+    fn frustum(projection: Projection) -> Frustum {
+        let camera = Camera::new(Point3::origin(), projection); // Looking down -Z
+        Frustum::from_matrix(&(camera.projection_matrix() * camera.view_matrix()))
+    }
+
+    fn perspective() -> Frustum {
+        frustum(Projection::Perspective { fov_y_degrees: 90.0, near: 0.1, far: 100.0 })
+    }
+
+    fn at(bounds: &Bounds, x: f32, y: f32, z: f32) -> Bounds {
+        bounds.transformed(&Matrix4::new_translation(&Vector3::new(x, y, z)))
+    }
+
+    #[test]
+    fn bounds_of_cube_corners() {
+        let cube = unit_cube();
+        assert_eq!(cube.aabb, Aabb::new(Point3::new(-0.5, -0.5, -0.5), Point3::new(0.5, 0.5, 0.5)));
+        assert_eq!(cube.sphere.center, Point3::origin());
+        assert!((cube.sphere.radius - 0.75f32.sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn rotated_box_grows_and_scaled_sphere_follows_largest_axis() {
+        let rotation = Matrix4::from_euler_angles(0.0, std::f32::consts::FRAC_PI_4, 0.0);
+        let aabb = unit_cube().aabb.transformed(&rotation);
+        let half_diagonal = 0.5 * 2.0f32.sqrt();
+        assert!((aabb.max.x - half_diagonal).abs() < 1e-5);
+        assert!((aabb.max.y - 0.5).abs() < 1e-5);
+
+        let sphere = unit_cube().sphere.transformed(&Matrix4::new_nonuniform_scaling(&Vector3::new(1.0, 3.0, 2.0)));
+        assert!((sphere.radius - 3.0 * 0.75f32.sqrt()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn perspective_frustum_keeps_what_is_in_front() {
+        let frustum = perspective();
+        let cube = unit_cube();
+
+        assert!(frustum.intersects(&at(&cube, 0.0, 0.0, -5.0)));
+        assert!(!frustum.intersects(&at(&cube, 0.0, 0.0, 5.0)));    // Behind
+        assert!(!frustum.intersects(&at(&cube, 0.0, 0.0, -200.0))); // Past far plane
+        assert!(!frustum.intersects(&at(&cube, 10.0, 0.0, -5.0)));  // Off to the right (90° => x <= 5)
+        assert!(frustum.intersects(&at(&cube, 5.4, 0.0, -5.0)));    // Pokes in over the edge
+    }
+
+    #[test]
+    fn orthographic_frustum_has_parallel_sides() {
+        let frustum = frustum(Projection::Orthographic { height: 4.0, near: 0.1, far: 100.0 });
+        let cube = unit_cube();
+
+        assert!(frustum.intersects(&at(&cube, 0.0, 2.3, -50.0)));
+        assert!(!frustum.intersects(&at(&cube, 0.0, 2.6, -50.0)));
+        assert!(frustum.contains_point(&Point3::new(0.0, 1.9, -99.0)));
+    }
+
+    #[test]
+    fn infinite_frustum_has_no_far_plane() {
+        let frustum = frustum(Projection::InfiniteReversed { fov_y_degrees: 90.0, near: 0.1 });
+        let cube = unit_cube();
+
+        assert!(frustum.intersects(&at(&cube, 0.0, 0.0, -1.0e5)));
+        assert!(!frustum.intersects(&at(&cube, 0.0, 0.0, 5.0)));
+    }
+}
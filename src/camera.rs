@@ -0,0 +1,483 @@
+use nalgebra::{Isometry3, Matrix4, Point3, Translation3, UnitQuaternion, Vector2, Vector3};
+use serde::{Deserialize, Serialize};
+
+fn radians(degrees: f32) -> f32 {
+    degrees * (std::f32::consts::PI / 180.0)
+}
+
+//
+// ─── CAMERA ─────────────────────────────────────────────────────────────────────
+//
+
+// How camera space gets squashed into clip space. Aspect ratio comes from
+// the window, so it is passed in when the matrix is built.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Projection {
+    Perspective { fov_y_degrees: f32, near: f32, far: f32 },
+    Orthographic { height: f32, near: f32, far: f32 }, // Height of the view in world units
+    // No far plane, depth goes from 1 at near to 0 at infinity. Needs
+    // glDepthFunc(GL_GREATER) and depth cleared to 0, see `reversed_z`.
+    // GL 3.3 has no glClipControl, so NDC z in [-1, 1] is still squashed to
+    // 0.5 * z + 0.5 and the float precision reverse-Z is known for is lost.
+    // On this context it only buys the infinite far plane.
+    InfiniteReversed { fov_y_degrees: f32, near: f32 },
+}
+
+impl Projection {
+
+    pub fn matrix(&self, aspect: f32) -> Matrix4<f32> {
+        match *self {
+            Projection::Perspective { fov_y_degrees, near, far } =>
+                Matrix4::new_perspective(aspect, radians(fov_y_degrees), near, far),
+
+            Projection::Orthographic { height, near, far } => {
+                let half_height = height / 2.0;
+                let half_width = half_height * aspect;
+                Matrix4::new_orthographic(-half_width, half_width, -half_height, half_height, near, far)
+            }
+
+            Projection::InfiniteReversed { fov_y_degrees, near } => {
+                // Limit of the perspective matrix as far => infinity, with z
+                // flipped so that clip z / w = 2 * near / distance - 1
+                let f = 1.0 / (radians(fov_y_degrees) / 2.0).tan();
+                Matrix4::new(
+                    f / aspect, 0.0,  0.0, 0.0,
+                    0.0,        f,    0.0, 0.0,
+                    0.0,        0.0,  1.0, 2.0 * near,
+                    0.0,        0.0, -1.0, 0.0,
+                )
+            }
+        }
+    }
+
+    pub fn reversed_z(&self) -> bool {
+        matches!(self, Projection::InfiniteReversed { .. })
+    }
+
+    // Positive => closer. Narrows the field of view, or shows less of the
+    // world when orthographic. 10% per step like orbit zoom.
+    pub fn zoom(&mut self, amount: f32) {
+        let factor = 1.0 - amount * 0.1;
+        match self {
+            Projection::Perspective { fov_y_degrees, .. } | Projection::InfiniteReversed { fov_y_degrees, .. } =>
+                *fov_y_degrees = (*fov_y_degrees * factor).clamp(10.0, 120.0),
+            Projection::Orthographic { height, .. } =>
+                *height = (*height * factor).clamp(0.1, 1000.0),
+        }
+    }
+}
+
+// Camera looks down its local -Z axis with +Y up, like OpenGL expects.
+// Orientation rotates these local axes into the world.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Camera {
+    pub position: Point3<f32>,
+    pub orientation: UnitQuaternion<f32>,
+    pub projection: Projection,
+    pub aspect: f32, // Width / height of the viewport
+}
+
+impl Camera {
+
+    pub fn new(position: Point3<f32>, projection: Projection) -> Self {
+        Camera {
+            position,
+            orientation: UnitQuaternion::identity(),
+            projection,
+            aspect: 1.0,
+        }
+    }
+
+    pub fn forward(&self) -> Vector3<f32> {
+        self.orientation * -Vector3::z()
+    }
+
+    pub fn right(&self) -> Vector3<f32> {
+        self.orientation * Vector3::x()
+    }
+
+    pub fn up(&self) -> Vector3<f32> {
+        self.orientation * Vector3::y()
+    }
+
+    // Turns the camera towards `target` keeping world Y as up.
+    #[allow(dead_code)]
+    pub fn look_at(&mut self, target: &Point3<f32>) {
+        let direction = target - self.position;
+        if direction.norm_squared() > 0.0 {
+            self.orientation = UnitQuaternion::face_towards(&-direction, &Vector3::y());
+        }
+    }
+
+    // World => camera space. Inverse of the camera placement in the world.
+    pub fn view_matrix(&self) -> Matrix4<f32> {
+        Isometry3::from_parts(Translation3::from(self.position.coords), self.orientation)
+            .inverse()
+            .to_homogeneous()
+    }
+
+    pub fn projection_matrix(&self) -> Matrix4<f32> {
+        self.projection.matrix(self.aspect)
+    }
+
+    // Placement `alpha` of the way from this camera to `next`, the rest comes
+    // from `next`. For drawing between two fixed simulation ticks.
+    pub fn interpolate(&self, next: &Camera, alpha: f32) -> Camera {
+        Camera {
+            position: self.position + (next.position - self.position) * alpha,
+            orientation: self.orientation.slerp(&next.orientation, alpha),
+            ..next.clone()
+        }
+    }
+
+    pub fn set_aspect(&mut self, width: f32, height: f32) {
+        if width > 0.0 && height > 0.0 { // Minimized window reports zero size
+            self.aspect = width / height;
+        }
+    }
+}
+
+
+//
+// ─── CONTROLLERS ────────────────────────────────────────────────────────────────
+//
+
+// What the player wants to do this frame. Controllers decide what it means.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CameraInput {
+    pub movement: Vector3<f32>, // x => right, y => up, z => forward; length 1 is normal speed
+    pub look: Vector2<f32>,     // Yaw and pitch change in degrees, positive => right and up
+    pub zoom: f32,              // Positive => closer
+}
+
+pub trait CameraController {
+    fn update(&mut self, camera: &mut Camera, input: &CameraInput, delta_time: f32);
+}
+
+// Yaw around world Y followed by pitch around the turned X axis.
+fn yaw_pitch_orientation(yaw: f32, pitch: f32) -> UnitQuaternion<f32> {
+    UnitQuaternion::from_axis_angle(&Vector3::y_axis(), -radians(yaw))
+        * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), radians(pitch))
+}
+
+// Free flight, moves wherever the camera looks.
+pub struct FlyController {
+    pub yaw: f32,   // Degrees, 0 => looking down -Z
+    pub pitch: f32, // Degrees, clamped so the camera never flips over
+    pub speed: f32, // Units per second
+}
+
+impl Default for FlyController {
+    fn default() -> Self {
+        FlyController {
+            yaw: 0.0,
+            pitch: 0.0,
+            speed: 5.0,
+        }
+    }
+}
+
+impl CameraController for FlyController {
+    fn update(&mut self, camera: &mut Camera, input: &CameraInput, delta_time: f32) {
+        self.yaw = (self.yaw + input.look.x) % 360.0;
+        self.pitch = (self.pitch + input.look.y).clamp(-89.0, 89.0);
+        camera.orientation = yaw_pitch_orientation(self.yaw, self.pitch);
+        camera.projection.zoom(input.zoom);
+
+        let movement = camera.right() * input.movement.x
+            + camera.up() * input.movement.y
+            + camera.forward() * input.movement.z;
+        camera.position += movement * self.speed * delta_time;
+    }
+}
+
+// Circles around a target point, zoom changes the distance.
+pub struct OrbitController {
+    pub target: Point3<f32>,
+    pub distance: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub yaw: f32,        // Degrees, 0 => camera sits on +Z side of the target
+    pub pitch: f32,      // Degrees, positive => looking down from above
+    pub pan_speed: f32,  // Units per second, movement input slides the target
+    pub zoom_speed: f32, // Fraction of distance per zoom step
+}
+
+impl OrbitController {
+    pub fn new(target: Point3<f32>, distance: f32) -> Self {
+        OrbitController {
+            target,
+            distance,
+            min_distance: 0.5,
+            max_distance: 100.0,
+            yaw: 0.0,
+            pitch: 0.0,
+            pan_speed: 5.0,
+            zoom_speed: 0.1,
+        }
+    }
+}
+
+impl CameraController for OrbitController {
+    fn update(&mut self, camera: &mut Camera, input: &CameraInput, delta_time: f32) {
+        self.yaw = (self.yaw + input.look.x) % 360.0;
+        self.pitch = (self.pitch + input.look.y).clamp(-89.0, 89.0);
+
+        // Moving closer does nothing to an orthographic view, so zoom that instead
+        match camera.projection {
+            Projection::Orthographic { .. } => camera.projection.zoom(input.zoom),
+            _ => self.distance = (self.distance * (1.0 - input.zoom * self.zoom_speed))
+                .clamp(self.min_distance, self.max_distance),
+        }
+
+        // Dragging up tilts the camera over the target, so it looks down
+        camera.orientation = yaw_pitch_orientation(self.yaw, -self.pitch);
+        let pan = camera.right() * input.movement.x
+            + camera.up() * input.movement.y
+            + camera.forward() * input.movement.z;
+        self.target += pan * self.pan_speed * delta_time;
+
+        camera.position = self.target - camera.forward() * self.distance;
+    }
+}
+
+// Walking: looks around freely but moves only along the ground plane at eye height.
+pub struct FirstPersonController {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub speed: f32,
+    pub eye_height: f32, // Height above the ground plane y = 0
+}
+
+impl Default for FirstPersonController {
+    fn default() -> Self {
+        FirstPersonController {
+            yaw: 0.0,
+            pitch: 0.0,
+            speed: 3.0,
+            eye_height: 1.7,
+        }
+    }
+}
+
+impl CameraController for FirstPersonController {
+    fn update(&mut self, camera: &mut Camera, input: &CameraInput, delta_time: f32) {
+        self.yaw = (self.yaw + input.look.x) % 360.0;
+        self.pitch = (self.pitch + input.look.y).clamp(-89.0, 89.0);
+        camera.orientation = yaw_pitch_orientation(self.yaw, self.pitch);
+        camera.projection.zoom(input.zoom);
+
+        // Pitch must not slow walking down, so walk along the heading only
+        let heading = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), -radians(self.yaw));
+        let forward = heading * -Vector3::z();
+        let right = heading * Vector3::x();
+        camera.position += (right * input.movement.x + forward * input.movement.z) * self.speed * delta_time;
+        camera.position.y = self.eye_height;
+    }
+}
+
+
+//
+// ─── TESTS ──────────────────────────────────────────────────────────────────────
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn perspective() -> Projection {
+        Projection::Perspective { fov_y_degrees: 45.0, near: 0.1, far: 100.0 }
+    }
+
+    fn assert_matrix_eq(a: &Matrix4<f32>, b: &Matrix4<f32>) {
+        assert!((a - b).abs().max() < 1e-5, "\n{} !=\n{}", a, b);
+    }
+
+    fn assert_vector_eq(a: &Vector3<f32>, b: &Vector3<f32>) {
+        assert!((a - b).abs().max() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    // Reference view matrix built the textbook way from where the camera looks.
+    fn expected_view(camera: &Camera) -> Matrix4<f32> {
+        Matrix4::look_at_rh(&camera.position, &(camera.position + camera.forward()), &camera.up())
+    }
+
+    #[test]
+    fn perspective_fov_is_in_degrees() {
+        let projection = Projection::Perspective { fov_y_degrees: 90.0, near: 1.0, far: 3.0 };
+        let expected = Matrix4::new(
+            0.5, 0.0,  0.0,  0.0,
+            0.0, 1.0,  0.0,  0.0,
+            0.0, 0.0, -2.0, -3.0,
+            0.0, 0.0, -1.0,  0.0,
+        );
+        assert_matrix_eq(&projection.matrix(2.0), &expected);
+
+        // 45° used to go in as 45 radians
+        let camera = Camera::new(Point3::origin(), perspective());
+        let cot = 1.0 / radians(22.5).tan();
+        assert!((camera.projection_matrix()[(1, 1)] - cot).abs() < 1e-5);
+    }
+
+    #[test]
+    fn orthographic_keeps_height_and_follows_aspect() {
+        let projection = Projection::Orthographic { height: 2.0, near: 0.0, far: 10.0 };
+        let expected = Matrix4::new(
+            0.5, 0.0,  0.0,  0.0,
+            0.0, 1.0,  0.0,  0.0,
+            0.0, 0.0, -0.2, -1.0,
+            0.0, 0.0,  0.0,  1.0,
+        );
+        assert_matrix_eq(&projection.matrix(2.0), &expected);
+    }
+
+    #[test]
+    fn infinite_reversed_maps_near_to_one_and_far_away_to_minus_one() {
+        let projection = Projection::InfiniteReversed { fov_y_degrees: 90.0, near: 0.5 };
+        let expected = Matrix4::new(
+            1.0, 0.0,  0.0, 0.0,
+            0.0, 1.0,  0.0, 0.0,
+            0.0, 0.0,  1.0, 1.0,
+            0.0, 0.0, -1.0, 0.0,
+        );
+        let matrix = projection.matrix(1.0);
+        assert_matrix_eq(&matrix, &expected);
+
+        let near = matrix.transform_point(&Point3::new(0.0, 0.0, -0.5));
+        let far = matrix.transform_point(&Point3::new(0.0, 0.0, -1.0e6));
+        assert!((near.z - 1.0).abs() < 1e-6);
+        assert!(far.z > -1.0 && far.z < -0.999);
+        assert!(projection.reversed_z());
+    }
+
+    #[test]
+    fn resize_changes_aspect_but_not_vertical_fov() {
+        let mut camera = Camera::new(Point3::origin(), perspective());
+        let before = camera.projection_matrix();
+        camera.set_aspect(1600.0, 900.0);
+        let after = camera.projection_matrix();
+        assert_eq!(before[(1, 1)], after[(1, 1)]);
+        assert!((after[(0, 0)] - after[(1, 1)] * 900.0 / 1600.0).abs() < 1e-5);
+
+        camera.set_aspect(0.0, 0.0); // Minimized
+        assert_eq!(camera.projection_matrix(), after);
+    }
+
+    #[test]
+    fn zoom_narrows_fov_and_is_clamped() {
+        let mut projection = perspective();
+        projection.zoom(1.0);
+        assert_eq!(projection, Projection::Perspective { fov_y_degrees: 40.5, near: 0.1, far: 100.0 });
+        projection.zoom(100.0);
+        assert_eq!(projection, Projection::Perspective { fov_y_degrees: 10.0, near: 0.1, far: 100.0 });
+
+        let mut projection = Projection::Orthographic { height: 4.0, near: 0.1, far: 100.0 };
+        projection.zoom(-1.0);
+        assert_eq!(projection, Projection::Orthographic { height: 4.4, near: 0.1, far: 100.0 });
+    }
+
+    #[test]
+    fn camera_at_origin_has_identity_view() {
+        let camera = Camera::new(Point3::origin(), perspective());
+        assert_matrix_eq(&camera.view_matrix(), &Matrix4::identity());
+    }
+
+    #[test]
+    fn interpolation_blends_placement_only() {
+        let previous = Camera::new(Point3::new(0.0, 0.0, 0.0), Projection::Perspective { fov_y_degrees: 45.0, near: 0.1, far: 100.0 });
+        let mut next = Camera::new(Point3::new(2.0, 0.0, -4.0), Projection::Orthographic { height: 5.0, near: 0.1, far: 100.0 });
+        next.orientation = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), std::f32::consts::FRAC_PI_2);
+
+        let halfway = previous.interpolate(&next, 0.5);
+        assert!((halfway.position - Point3::new(1.0, 0.0, -2.0)).norm() < 1e-6);
+        assert!((halfway.orientation.angle() - std::f32::consts::FRAC_PI_4).abs() < 1e-5);
+        assert_eq!(halfway.projection, next.projection);
+        assert!((previous.interpolate(&next, 1.0).position - next.position).norm() < 1e-6);
+    }
+
+    #[test]
+    fn view_matrix_moves_world_opposite_to_camera() {
+        let camera = Camera::new(Point3::new(0.0, 0.0, 3.0), perspective());
+        assert_matrix_eq(&camera.view_matrix(), &Matrix4::new_translation(&Vector3::new(0.0, 0.0, -3.0)));
+        let origin = camera.view_matrix().transform_point(&Point3::origin());
+        assert!((origin - Point3::new(0.0, 0.0, -3.0)).norm() < 1e-5);
+    }
+
+    #[test]
+    fn look_at_matches_nalgebra() {
+        let mut camera = Camera::new(Point3::new(4.0, 2.0, 3.0), perspective());
+        let target = Point3::new(-1.0, 0.5, -2.0);
+        camera.look_at(&target);
+        assert_matrix_eq(&camera.view_matrix(), &Matrix4::look_at_rh(&camera.position, &target, &Vector3::y()));
+    }
+
+    #[test]
+    fn fly_controller_turns_and_moves_forward() {
+        let mut camera = Camera::new(Point3::origin(), perspective());
+        let mut controller = FlyController::default();
+
+        let turn_right = CameraInput { look: Vector2::new(90.0, 0.0), ..Default::default() };
+        controller.update(&mut camera, &turn_right, 0.0);
+        assert_vector_eq(&camera.forward(), &Vector3::x());
+
+        let forward = CameraInput { movement: Vector3::z(), ..Default::default() };
+        controller.update(&mut camera, &forward, 0.5);
+        assert_vector_eq(&camera.position.coords, &Vector3::new(2.5, 0.0, 0.0));
+        assert_matrix_eq(&camera.view_matrix(), &expected_view(&camera));
+    }
+
+    #[test]
+    fn fly_controller_clamps_pitch_and_keeps_up_vector_current() {
+        let mut camera = Camera::new(Point3::origin(), perspective());
+        let mut controller = FlyController::default();
+
+        let look_up = CameraInput { look: Vector2::new(30.0, 120.0), ..Default::default() };
+        controller.update(&mut camera, &look_up, 0.0);
+        assert_eq!(controller.pitch, 89.0);
+        assert!(camera.forward().y > 0.99);
+        assert!(camera.up().dot(&camera.forward()).abs() < 1e-5);
+        assert_matrix_eq(&camera.view_matrix(), &expected_view(&camera));
+    }
+
+    #[test]
+    fn orbit_controller_looks_at_target_from_distance() {
+        let mut camera = Camera::new(Point3::origin(), perspective());
+        let target = Point3::new(1.0, 2.0, 3.0);
+        let mut controller = OrbitController::new(target, 5.0);
+
+        let input = CameraInput { look: Vector2::new(45.0, 30.0), ..Default::default() };
+        controller.update(&mut camera, &input, 0.0);
+
+        assert!(((camera.position - target).norm() - 5.0).abs() < 1e-4);
+        assert!(camera.position.y > target.y); // Dragged up => looking down at the target
+        assert_matrix_eq(&camera.view_matrix(), &Matrix4::look_at_rh(&camera.position, &target, &Vector3::y()));
+    }
+
+    #[test]
+    fn orbit_controller_zoom_is_clamped() {
+        let mut camera = Camera::new(Point3::origin(), perspective());
+        let mut controller = OrbitController::new(Point3::origin(), 1.0);
+
+        let zoom_in = CameraInput { zoom: 100.0, ..Default::default() };
+        controller.update(&mut camera, &zoom_in, 0.0);
+        assert_eq!(controller.distance, controller.min_distance);
+        assert!((camera.position.coords.norm() - controller.min_distance).abs() < 1e-5);
+    }
+
+    #[test]
+    fn first_person_controller_stays_on_ground() {
+        let mut camera = Camera::new(Point3::origin(), perspective());
+        let mut controller = FirstPersonController::default();
+
+        let look_up_and_walk = CameraInput {
+            movement: Vector3::new(0.0, 1.0, 1.0), // Up input is ignored while walking
+            look: Vector2::new(0.0, 60.0),
+            ..Default::default()
+        };
+        controller.update(&mut camera, &look_up_and_walk, 1.0);
+
+        assert_vector_eq(&camera.position.coords, &Vector3::new(0.0, 1.7, -3.0));
+        assert!(camera.forward().y > 0.8);
+        assert_matrix_eq(&camera.view_matrix(), &expected_view(&camera));
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+
+use crate::image::Image;
+
+#[derive(Debug)]
+pub enum CaptureError {
+    Io(io::Error),
+    Encode(png::EncodingError),
+    SizeChanged { expected: (u32, u32), got: (u32, u32) },
+    EncoderFailed(std::process::ExitStatus),
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CaptureError::Io(e) => write!(f, "{}", e),
+            CaptureError::Encode(e) => write!(f, "Could not write PNG: {}", e),
+            CaptureError::SizeChanged { expected, got } => write!(
+                f, "Frame size changed from {}x{} to {}x{}, the encoder can't follow", expected.0, expected.1, got.0, got.1
+            ),
+            CaptureError::EncoderFailed(status) => write!(f, "Encoder exited with {}", status),
+        }
+    }
+}
+
+impl From<io::Error> for CaptureError {
+    fn from(e: io::Error) -> Self {
+        CaptureError::Io(e)
+    }
+}
+
+enum Output {
+    Frames(PathBuf), // Directory of frame_00000.png, frame_00001.png, ...
+    Encoder(Child),  // Reads raw RGBA frames, top row first, from stdin
+}
+
+// Writes every rendered frame somewhere for turning into a video. Frames are
+// `timestep` apart in simulated time no matter how long they took to render,
+// so the same replay always exports the same animation.
+pub struct Capture {
+    output: Output,
+    size: Option<(u32, u32)>, // Of the first frame, an encoder can't take anything else
+    pub frame: u32,
+    pub timestep: f32,
+}
+
+impl Capture {
+
+    // Numbered PNGs in `directory`, which is created if needed.
+    pub fn frames(directory: impl Into<PathBuf>, fps: u32) -> Result<Self, CaptureError> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory)?;
+        Ok(Capture::with_output(Output::Frames(directory), fps))
+    }
+
+    // Runs `command` through the shell and pipes raw RGBA frames into it.
+    // {width}, {height} and {fps} in the command are filled in, e.g.
+    // ffmpeg -f rawvideo -pix_fmt rgba -s {width}x{height} -r {fps} -i - out.mp4
+    pub fn encoder(command: &str, width: u32, height: u32, fps: u32) -> Result<Self, CaptureError> {
+        let command = command
+            .replace("{width}", &width.to_string())
+            .replace("{height}", &height.to_string())
+            .replace("{fps}", &fps.to_string());
+
+        let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+        let child = Command::new(shell)
+            .args([flag, &command])
+            .stdin(Stdio::piped())
+            .spawn()?;
+
+        let mut capture = Capture::with_output(Output::Encoder(child), fps);
+        capture.size = Some((width, height));
+        Ok(capture)
+    }
+
+    fn with_output(output: Output, fps: u32) -> Self {
+        Capture { output, size: None, frame: 0, timestep: 1.0 / fps as f32 }
+    }
+
+    pub fn write(&mut self, image: &Image) -> Result<(), CaptureError> {
+        let size = (image.width, image.height);
+        match self.size {
+            Some(expected) if expected != size => return Err(CaptureError::SizeChanged { expected, got: size }),
+            _ => self.size = Some(size),
+        }
+
+        match &mut self.output {
+            Output::Frames(directory) => {
+                let path = directory.join(format!("frame_{:05}.png", self.frame));
+                image.save(path).map_err(CaptureError::Encode)?;
+            }
+            Output::Encoder(child) => {
+                let stdin = child.stdin.as_mut().expect("Encoder is spawned with piped stdin");
+                stdin.write_all(&image.data)?;
+            }
+        }
+        self.frame += 1;
+        Ok(())
+    }
+
+    // Closes the pipe so the encoder sees the end of the video, then waits for it.
+    pub fn finish(self) -> Result<u32, CaptureError> {
+        if let Output::Encoder(mut child) = self.output {
+            drop(child.stdin.take());
+            let status = child.wait()?;
+            if !status.success() {
+                return Err(CaptureError::EncoderFailed(status));
+            }
+        }
+        Ok(self.frame)
+    }
+}
+
+
+//
+// ─── TESTS ──────────────────────────────────────────────────────────────────────
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(shade: u8) -> Image {
+        let mut image = Image::new(2, 2);
+        image.data.iter_mut().for_each(|byte| *byte = shade);
+        image
+    }
+
+    #[test]
+    fn frames_are_numbered_pngs() {
+        let directory = std::env::temp_dir().join(format!("capture_frames_{}", std::process::id()));
+        std::fs::remove_dir_all(&directory).ok();
+
+        let mut capture = Capture::frames(&directory, 30).unwrap();
+        assert!((capture.timestep - 1.0 / 30.0).abs() < 1e-6);
+        for shade in 0..3 {
+            capture.write(&frame(shade * 100)).unwrap();
+        }
+        assert_eq!(capture.finish().unwrap(), 3);
+
+        assert_eq!(Image::open(directory.join("frame_00002.png")).unwrap(), frame(200));
+        assert!(!directory.join("frame_00003.png").exists());
+        std::fs::remove_dir_all(directory).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn encoder_gets_raw_frames() {
+        let path = std::env::temp_dir().join(format!("capture_pipe_{}.rgba", std::process::id()));
+        let mut capture = Capture::encoder(&format!("cat > {}", path.display()), 2, 2, 60).unwrap();
+        capture.write(&frame(1)).unwrap();
+        capture.write(&frame(2)).unwrap();
+        assert!(matches!(capture.write(&Image::new(4, 4)), Err(CaptureError::SizeChanged { .. })));
+        capture.finish().unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(bytes, [frame(1).data, frame(2).data].concat());
+        std::fs::remove_file(path).ok();
+    }
+}
@@ -0,0 +1,55 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// Settings files are RON, same as atlas metadata.
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError), // Knows line and column of the mistake
+    Serialize(ron::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "{}", e),
+            ConfigError::Parse(e) => write!(f, "{}", e),
+            ConfigError::Serialize(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+pub fn load<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, ConfigError> {
+    let text = fs::read_to_string(path).map_err(ConfigError::Io)?;
+    ron::de::from_str(&text).map_err(ConfigError::Parse)
+}
+
+// Missing file means defaults, broken file is still an error.
+pub fn load_or_default<T: DeserializeOwned + Default>(path: impl AsRef<Path>) -> Result<T, ConfigError> {
+    let path = path.as_ref();
+    match load(path) {
+        Err(ConfigError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+            log::warn!("{} not found, using defaults", path.display());
+            Ok(T::default())
+        }
+        result => result,
+    }
+}
+
+// Settings files are kept with the sources, this finds them from wherever
+// `cargo run` was started.
+pub fn source_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("src").join(name)
+}
+
+pub fn save<T: Serialize>(value: &T, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+    let text = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .map_err(ConfigError::Serialize)?;
+    fs::write(path, text).map_err(ConfigError::Io)
+}
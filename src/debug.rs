@@ -0,0 +1,218 @@
+use std::ffi::{c_void, CStr};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use gl33::global_loader::*;
+use gl33::*;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+//
+// ─── LOGGER ─────────────────────────────────────────────────────────────────────
+//
+
+// Prints records to stderr as "[LEVEL target] message". The log facade drops
+// everything above the level given to `init_logger` before it gets here.
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{} {}] {}", record.level(), record.target(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+// Only the first call installs the logger, later ones just change the level.
+pub fn init_logger(level: LevelFilter) {
+    log::set_logger(&LOGGER).ok();
+    log::set_max_level(level);
+}
+
+pub fn parse_level(value: &str) -> Result<LevelFilter, String> {
+    value.parse().map_err(|_| format!("--log needs off, error, warn, info, debug or trace, got '{}'", value))
+}
+
+
+//
+// ─── DEBUG OUTPUT ───────────────────────────────────────────────────────────────
+//
+
+// How GL errors get noticed in this context.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorReporting {
+    Callback, // KHR_debug messages, with the driver's explanation of what is wrong
+    GetError, // glGetError after each gl_check! call, debug builds only
+    Off,
+}
+
+static CALLBACK_INSTALLED: AtomicBool = AtomicBool::new(false);
+static LABELS: AtomicBool = AtomicBool::new(false);
+
+// Call once the context is current and the functions are loaded. Debug
+// contexts get the callback, everything else falls back to glGetError.
+pub fn install() -> ErrorReporting {
+    let has_khr_debug = has_khr_debug();
+    LABELS.store(has_khr_debug, Ordering::Relaxed);
+
+    let mut flags = 0;
+    unsafe { glGetIntegerv(GL_CONTEXT_FLAGS, &mut flags) };
+    let debug_context = flags as u32 & GL_CONTEXT_FLAG_DEBUG_BIT.0 != 0;
+
+    let reporting = if debug_context && has_khr_debug {
+        unsafe {
+            glEnable(GL_DEBUG_OUTPUT);
+            glEnable(GL_DEBUG_OUTPUT_SYNCHRONOUS); // Callback runs inside the offending call
+            glDebugMessageCallback(Some(callback), std::ptr::null());
+
+            // Drivers are chatty, don't make them format notifications nobody reads
+            let notifications = log::max_level() >= LevelFilter::Debug;
+            glDebugMessageControl(GL_DONT_CARE, GL_DONT_CARE, GL_DEBUG_SEVERITY_NOTIFICATION, 0, std::ptr::null(), notifications as u8);
+        }
+        ErrorReporting::Callback
+    } else if cfg!(debug_assertions) {
+        ErrorReporting::GetError
+    } else {
+        ErrorReporting::Off
+    };
+    CALLBACK_INSTALLED.store(reporting == ErrorReporting::Callback, Ordering::Relaxed);
+
+    log::info!(target: "gl", "Errors reported through {:?}, object labels {}", reporting, if has_khr_debug { "on" } else { "off" });
+    reporting
+}
+
+fn has_khr_debug() -> bool {
+    if !glDebugMessageCallback_is_loaded() || !glObjectLabel_is_loaded() {
+        return false;
+    }
+    let mut major = 0;
+    let mut minor = 0;
+    let mut count = 0;
+    unsafe {
+        glGetIntegerv(GL_MAJOR_VERSION, &mut major);
+        glGetIntegerv(GL_MINOR_VERSION, &mut minor);
+        glGetIntegerv(GL_NUM_EXTENSIONS, &mut count);
+    }
+    if (major, minor) >= (4, 3) {
+        return true; // Core since 4.3
+    }
+    (0..count as u32).any(|i| {
+        let name = unsafe { glGetStringi(GL_EXTENSIONS, i) };
+        !name.is_null() && unsafe { CStr::from_ptr(name.cast()) }.to_bytes() == b"GL_KHR_debug"
+    })
+}
+
+// Errors are always errors, the rest goes by what the driver thinks of it.
+fn level(type_: GLenum, severity: GLenum) -> Level {
+    match (type_, severity) {
+        (GL_DEBUG_TYPE_ERROR, _) | (_, GL_DEBUG_SEVERITY_HIGH) => Level::Error,
+        (_, GL_DEBUG_SEVERITY_MEDIUM) => Level::Warn,
+        (_, GL_DEBUG_SEVERITY_LOW) => Level::Info,
+        _ => Level::Debug,
+    }
+}
+
+fn type_name(type_: GLenum) -> &'static str {
+    match type_ {
+        GL_DEBUG_TYPE_ERROR => "error",
+        GL_DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated behavior",
+        GL_DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behavior",
+        GL_DEBUG_TYPE_PORTABILITY => "portability",
+        GL_DEBUG_TYPE_PERFORMANCE => "performance",
+        GL_DEBUG_TYPE_MARKER => "marker",
+        _ => "message",
+    }
+}
+
+fn source_name(source: GLenum) -> &'static str {
+    match source {
+        GL_DEBUG_SOURCE_API => "API",
+        GL_DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
+        GL_DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+        GL_DEBUG_SOURCE_THIRD_PARTY => "third party",
+        GL_DEBUG_SOURCE_APPLICATION => "application",
+        _ => "other",
+    }
+}
+
+unsafe extern "system" fn callback(
+    source: GLenum, type_: GLenum, id: u32, severity: GLenum,
+    length: i32, message: *const u8, _user: *const c_void
+) {
+    let message = String::from_utf8_lossy(std::slice::from_raw_parts(message, length as usize));
+    log::log!(target: "gl", level(type_, severity), "{} {} {}: {}", source_name(source), type_name(type_), id, message.trim_end());
+}
+
+fn error_name(error: GLenum) -> &'static str {
+    match error {
+        GL_INVALID_ENUM => "GL_INVALID_ENUM",
+        GL_INVALID_VALUE => "GL_INVALID_VALUE",
+        GL_INVALID_OPERATION => "GL_INVALID_OPERATION",
+        GL_INVALID_FRAMEBUFFER_OPERATION => "GL_INVALID_FRAMEBUFFER_OPERATION",
+        GL_OUT_OF_MEMORY => "GL_OUT_OF_MEMORY",
+        _ => "unknown error",
+    }
+}
+
+// Logs every error raised since the last check. Does nothing when the
+// callback already reported them.
+pub fn check_errors(location: &str) {
+    if CALLBACK_INSTALLED.load(Ordering::Relaxed) {
+        return;
+    }
+    loop {
+        let error = unsafe { glGetError() };
+        if error == GL_NO_ERROR {
+            break;
+        }
+        log::error!(target: "gl", "{} (0x{:X}) at {}", error_name(error), error.0, location);
+    }
+}
+
+// Runs a GL call and, in debug builds, logs any errors it raised with the
+// caller's file and line.
+#[macro_export]
+macro_rules! gl_check {
+    ($call:expr) => {{
+        let result = $call;
+        #[cfg(debug_assertions)]
+        $crate::debug::check_errors(concat!(file!(), ":", line!()));
+        result
+    }};
+}
+
+// Names the object in debug messages and in tools like RenderDoc.
+// `identifier` is the kind of object, e.g. GL_TEXTURE or GL_BUFFER.
+pub fn label(identifier: GLenum, name: u32, label: &str) {
+    if LABELS.load(Ordering::Relaxed) {
+        unsafe { glObjectLabel(identifier, name, label.len() as i32, label.as_ptr()) };
+    }
+}
+
+
+//
+// ─── TESTS ──────────────────────────────────────────────────────────────────────
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_follow_severity_except_for_errors() {
+        assert_eq!(level(GL_DEBUG_TYPE_ERROR, GL_DEBUG_SEVERITY_LOW), Level::Error);
+        assert_eq!(level(GL_DEBUG_TYPE_PERFORMANCE, GL_DEBUG_SEVERITY_HIGH), Level::Error);
+        assert_eq!(level(GL_DEBUG_TYPE_PERFORMANCE, GL_DEBUG_SEVERITY_MEDIUM), Level::Warn);
+        assert_eq!(level(GL_DEBUG_TYPE_PORTABILITY, GL_DEBUG_SEVERITY_LOW), Level::Info);
+        assert_eq!(level(GL_DEBUG_TYPE_OTHER, GL_DEBUG_SEVERITY_NOTIFICATION), Level::Debug);
+
+        assert_eq!(parse_level("info"), Ok(LevelFilter::Info));
+        assert!(parse_level("loud").is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use gl33::*;
+
+use crate::backend::Backend;
+use crate::image::Image;
+use crate::screenshot::{self, Alpha};
+use crate::texture::{Sampler, Texture2D, TextureFormat};
+
+// Offscreen render target: one color texture plus a depth buffer.
+pub struct Framebuffer {
+    pub id: u32,
+    pub width: u32,
+    pub height: u32,
+    pub color: Texture2D,
+    depth: u32, // Renderbuffer, never sampled so it doesn't need to be a texture
+    label: Option<String>, // Kept so a resized framebuffer keeps its name
+}
+
+impl Framebuffer {
+
+    // Leaves the new framebuffer bound.
+    pub fn new(gl: &mut impl Backend, width: u32, height: u32, format: TextureFormat) -> Self {
+        let color = Texture2D::new(gl, width, height, format, None, &Sampler::nearest());
+        let depth = gl.create_renderbuffer(GL_DEPTH_COMPONENT24, width, height);
+        let id = gl.create_framebuffer(color.id, depth);
+
+        Framebuffer { id, width, height, color, depth, label: None }
+    }
+
+    // Draws go here from now on, viewport covers the whole target.
+    pub fn bind(&self, gl: &mut impl Backend) {
+        gl.bind_framebuffer(self.id);
+        gl.viewport(0, 0, self.width as i32, self.height as i32);
+    }
+
+    // Same framebuffer with a new size, contents are lost.
+    pub fn resize(&mut self, gl: &mut impl Backend, width: u32, height: u32) {
+        if (width, height) != (self.width, self.height) {
+            let label = self.label.take();
+            self.delete(gl);
+            *self = Framebuffer::new(gl, width, height, self.color.format);
+            if let Some(label) = label {
+                self.set_label(gl, &label);
+            }
+        }
+    }
+
+    // Names the framebuffer and its attachments, "<label> color" and "<label> depth".
+    pub fn set_label(&mut self, gl: &mut impl Backend, label: &str) {
+        gl.label(GL_FRAMEBUFFER, self.id, label);
+        self.color.set_label(gl, &format!("{} color", label));
+        gl.label(GL_RENDERBUFFER, self.depth, &format!("{} depth", label));
+        self.label = Some(label.to_string());
+    }
+
+    // Integer color attachments must not be cleared with glClear. Expects the
+    // framebuffer to be bound.
+    pub fn clear_uint(&self, gl: &mut impl Backend, value: u32) {
+        gl.clear_color_uint(value);
+    }
+
+    // Pixel of an R32Ui attachment, y counts from the bottom like everything in OpenGL.
+    pub fn read_uint(&self, gl: &mut impl Backend, x: u32, y: u32) -> u32 {
+        assert_eq!(self.color.format, TextureFormat::R32Ui);
+        let mut value = [0u8; 4];
+        gl.bind_framebuffer(self.id);
+        gl.read_pixels([x as i32, y as i32, 1, 1], GL_RED_INTEGER, GL_UNSIGNED_INT, &mut value);
+        u32::from_ne_bytes(value)
+    }
+
+    // Whole color attachment, top row first. Float formats come out sRGB encoded.
+    pub fn read_image(&self, gl: &mut impl Backend, alpha: Alpha) -> Image {
+        assert_ne!(self.color.format, TextureFormat::R32Ui, "ID buffers are not images");
+        screenshot::read_pixels(gl, self.id, self.width, self.height, self.color.format, alpha)
+    }
+
+    // Window space depth, 0 to 1 with the default depth range.
+    pub fn read_depth(&self, gl: &mut impl Backend, x: u32, y: u32) -> f32 {
+        let mut value = [0u8; 4];
+        gl.bind_framebuffer(self.id);
+        gl.read_pixels([x as i32, y as i32, 1, 1], GL_DEPTH_COMPONENT, GL_FLOAT, &mut value);
+        f32::from_ne_bytes(value)
+    }
+
+    // Not Drop, it needs the backend that made it.
+    pub fn delete(&self, gl: &mut impl Backend) {
+        gl.delete_framebuffer(self.id);
+        gl.delete_renderbuffer(self.depth);
+        self.color.delete(gl);
+    }
+}
@@ -0,0 +1,378 @@
+#[cfg(test)]
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+//
+// ─── EVENTS ─────────────────────────────────────────────────────────────────────
+//
+
+pub type GamepadId = usize;
+
+// Xbox style layout, South is A on Xbox and Cross on PlayStation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    Select,
+    Start,
+    LeftStick, // Pressing the stick down
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum GamepadAxis {
+    LeftStickX,   // -1.0 ..= 1.0, positive => right
+    LeftStickY,   // -1.0 ..= 1.0, positive => up
+    RightStickX,
+    RightStickY,
+    LeftTrigger,  // 0.0 ..= 1.0
+    RightTrigger,
+}
+
+impl GamepadAxis {
+
+    // Other axis of the same stick, dead zone is applied to both together.
+    pub fn stick_pair(self) -> Option<(GamepadAxis, GamepadAxis)> {
+        match self {
+            GamepadAxis::LeftStickX | GamepadAxis::LeftStickY => Some((GamepadAxis::LeftStickX, GamepadAxis::LeftStickY)),
+            GamepadAxis::RightStickX | GamepadAxis::RightStickY => Some((GamepadAxis::RightStickX, GamepadAxis::RightStickY)),
+            GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GamepadEvent {
+    Connected(GamepadId),
+    Disconnected(GamepadId),
+    ButtonPressed(GamepadId, GamepadButton),
+    ButtonReleased(GamepadId, GamepadButton),
+    AxisChanged(GamepadId, GamepadAxis, f32), // Raw value, dead zones are applied by ActionMap
+}
+
+
+//
+// ─── BACKENDS ───────────────────────────────────────────────────────────────────
+//
+
+// Source of gamepad events. Polled once per frame.
+pub trait GamepadBackend {
+    fn poll(&mut self) -> Vec<GamepadEvent>;
+}
+
+// Gilrs when built with `--features gamepad`, otherwise there are no gamepads.
+pub fn default_backend() -> Option<Box<dyn GamepadBackend>> {
+    #[cfg(feature = "gamepad")]
+    {
+        match GilrsBackend::new() {
+            Ok(backend) => return Some(Box::new(backend)),
+            Err(e) => log::warn!("Gamepads are not available: {}", e),
+        }
+    }
+    None
+}
+
+// Fake device for tests. Queued events come out on the next poll.
+#[cfg(test)]
+#[derive(Default)]
+pub struct SimulatedGamepad {
+    pub id: GamepadId,
+    queue: VecDeque<GamepadEvent>,
+}
+
+#[cfg(test)]
+impl SimulatedGamepad {
+
+    pub fn new(id: GamepadId) -> Self {
+        SimulatedGamepad {
+            id,
+            queue: VecDeque::new(),
+        }
+    }
+
+    pub fn connect(&mut self) {
+        self.queue.push_back(GamepadEvent::Connected(self.id));
+    }
+
+    pub fn disconnect(&mut self) {
+        self.queue.push_back(GamepadEvent::Disconnected(self.id));
+    }
+
+    pub fn press(&mut self, button: GamepadButton) {
+        self.queue.push_back(GamepadEvent::ButtonPressed(self.id, button));
+    }
+
+    pub fn release(&mut self, button: GamepadButton) {
+        self.queue.push_back(GamepadEvent::ButtonReleased(self.id, button));
+    }
+
+    pub fn move_axis(&mut self, axis: GamepadAxis, value: f32) {
+        self.queue.push_back(GamepadEvent::AxisChanged(self.id, axis, value));
+    }
+}
+
+#[cfg(test)]
+impl GamepadBackend for SimulatedGamepad {
+    fn poll(&mut self) -> Vec<GamepadEvent> {
+        self.queue.drain(..).collect()
+    }
+}
+
+#[cfg(feature = "gamepad")]
+pub struct GilrsBackend {
+    gilrs: gilrs::Gilrs,
+}
+
+#[cfg(feature = "gamepad")]
+impl GilrsBackend {
+
+    pub fn new() -> Result<Self, String> {
+        let gilrs = gilrs::GilrsBuilder::new()
+            .with_default_filters(false) // Dead zones are ours, see ActionMap
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(GilrsBackend { gilrs })
+    }
+
+    fn button(button: gilrs::Button) -> Option<GamepadButton> {
+        use gilrs::Button as B;
+        Some(match button {
+            B::South => GamepadButton::South,
+            B::East => GamepadButton::East,
+            B::North => GamepadButton::North,
+            B::West => GamepadButton::West,
+            B::LeftTrigger => GamepadButton::LeftBumper,
+            B::RightTrigger => GamepadButton::RightBumper,
+            B::Select => GamepadButton::Select,
+            B::Start => GamepadButton::Start,
+            B::LeftThumb => GamepadButton::LeftStick,
+            B::RightThumb => GamepadButton::RightStick,
+            B::DPadUp => GamepadButton::DPadUp,
+            B::DPadDown => GamepadButton::DPadDown,
+            B::DPadLeft => GamepadButton::DPadLeft,
+            B::DPadRight => GamepadButton::DPadRight,
+            _ => return None,
+        })
+    }
+
+    fn axis(axis: gilrs::Axis) -> Option<GamepadAxis> {
+        use gilrs::Axis as A;
+        Some(match axis {
+            A::LeftStickX => GamepadAxis::LeftStickX,
+            A::LeftStickY => GamepadAxis::LeftStickY,
+            A::RightStickX => GamepadAxis::RightStickX,
+            A::RightStickY => GamepadAxis::RightStickY,
+            A::LeftZ => GamepadAxis::LeftTrigger,
+            A::RightZ => GamepadAxis::RightTrigger,
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(feature = "gamepad")]
+impl GamepadBackend for GilrsBackend {
+    fn poll(&mut self) -> Vec<GamepadEvent> {
+        use gilrs::{Button, EventType};
+
+        let mut events = Vec::new();
+        while let Some(gilrs::Event { id, event, .. }) = self.gilrs.next_event() {
+            let id: GamepadId = id.into();
+            let event = match event {
+                EventType::Connected => Some(GamepadEvent::Connected(id)),
+                EventType::Disconnected => Some(GamepadEvent::Disconnected(id)),
+                EventType::ButtonPressed(button, _) => Self::button(button).map(|b| GamepadEvent::ButtonPressed(id, b)),
+                EventType::ButtonReleased(button, _) => Self::button(button).map(|b| GamepadEvent::ButtonReleased(id, b)),
+                EventType::AxisChanged(axis, value, _) => Self::axis(axis).map(|a| GamepadEvent::AxisChanged(id, a, value)),
+                // Most pads report analog triggers as buttons with a value
+                EventType::ButtonChanged(Button::LeftTrigger2, value, _) => Some(GamepadEvent::AxisChanged(id, GamepadAxis::LeftTrigger, value)),
+                EventType::ButtonChanged(Button::RightTrigger2, value, _) => Some(GamepadEvent::AxisChanged(id, GamepadAxis::RightTrigger, value)),
+                _ => None,
+            };
+            events.extend(event);
+        }
+        events
+    }
+}
+
+
+//
+// ─── DEAD ZONES ─────────────────────────────────────────────────────────────────
+//
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeadZones {
+    pub stick: f32,   // Radial, so diagonals behave the same as straight directions
+    pub trigger: f32,
+}
+
+impl Default for DeadZones {
+    fn default() -> Self {
+        DeadZones {
+            stick: 0.15,
+            trigger: 0.05,
+        }
+    }
+}
+
+// Values inside the dead zone become 0.0, the rest is rescaled so output still
+// starts at 0.0 right at its edge instead of jumping.
+fn rescale(value: f32, dead_zone: f32) -> f32 {
+    if value <= dead_zone {
+        0.0
+    } else {
+        ((value - dead_zone) / (1.0 - dead_zone)).min(1.0)
+    }
+}
+
+// Applies radial dead zone to a stick, returns new (x, y).
+pub fn stick_dead_zone(x: f32, y: f32, dead_zone: f32) -> (f32, f32) {
+    let magnitude = (x * x + y * y).sqrt();
+    if magnitude <= dead_zone {
+        return (0.0, 0.0);
+    }
+    let scale = rescale(magnitude, dead_zone) / magnitude;
+    (x * scale, y * scale)
+}
+
+pub fn trigger_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    rescale(value, dead_zone)
+}
+
+
+//
+// ─── TESTS ──────────────────────────────────────────────────────────────────────
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{Action, ActionMap, Axis, Bindings, InputEvent};
+
+    fn actions_with(pad: &mut SimulatedGamepad, actions: &mut ActionMap) {
+        for event in pad.poll() {
+            actions.handle_event(&InputEvent::Gamepad(event));
+        }
+    }
+
+    #[test]
+    fn stick_dead_zone_is_radial_and_rescaled() {
+        assert_eq!(stick_dead_zone(0.1, 0.1, 0.15), (0.0, 0.0));
+
+        let (x, y) = stick_dead_zone(1.0, 0.0, 0.15);
+        assert!((x - 1.0).abs() < 1e-6 && y == 0.0);
+
+        // Just past the edge is close to zero, not a jump to 0.15
+        let (x, _) = stick_dead_zone(0.16, 0.0, 0.15);
+        assert!(x > 0.0 && x < 0.02);
+
+        // Direction is kept
+        let (x, y) = stick_dead_zone(0.5, 0.5, 0.15);
+        assert!((x - y).abs() < 1e-6);
+    }
+
+    #[test]
+    fn trigger_dead_zone_clamps_small_values() {
+        assert_eq!(trigger_dead_zone(0.03, 0.05), 0.0);
+        assert_eq!(trigger_dead_zone(1.0, 0.05), 1.0);
+    }
+
+    #[test]
+    fn sticks_drive_movement_axes() {
+        let mut pad = SimulatedGamepad::new(0);
+        let mut actions = ActionMap::new(Bindings::default());
+        pad.connect();
+        pad.move_axis(GamepadAxis::LeftStickX, 0.05); // Resting noise
+        pad.move_axis(GamepadAxis::LeftStickY, -0.08);
+        actions_with(&mut pad, &mut actions);
+        assert_eq!(actions.axis(Axis::MoveRight), 0.0);
+        assert_eq!(actions.axis(Axis::MoveForward), 0.0);
+
+        pad.move_axis(GamepadAxis::LeftStickX, 0.0);
+        pad.move_axis(GamepadAxis::LeftStickY, 1.0);
+        actions_with(&mut pad, &mut actions);
+        assert_eq!(actions.axis(Axis::MoveForward), 1.0);
+        assert_eq!(actions.axis(Axis::MoveRight), 0.0);
+    }
+
+    #[test]
+    fn buttons_trigger_actions() {
+        let mut pad = SimulatedGamepad::new(3);
+        let mut actions = ActionMap::new(Bindings::default());
+        pad.connect();
+        pad.press(GamepadButton::North);
+        actions_with(&mut pad, &mut actions);
+
+        assert!(actions.just_pressed(Action::ToggleWireframe));
+        actions.end_frame();
+        assert!(!actions.just_pressed(Action::ToggleWireframe));
+        assert!(actions.is_down(Action::ToggleWireframe));
+    }
+
+    #[test]
+    fn disconnect_releases_everything() {
+        let mut pad = SimulatedGamepad::new(1);
+        let mut actions = ActionMap::new(Bindings::default());
+        pad.connect();
+        pad.press(GamepadButton::LeftStick);
+        pad.move_axis(GamepadAxis::LeftStickX, -1.0);
+        actions_with(&mut pad, &mut actions);
+        assert!(actions.is_down(Action::Sprint));
+        assert_eq!(actions.axis(Axis::MoveRight), -1.0);
+
+        pad.disconnect();
+        actions_with(&mut pad, &mut actions);
+        assert!(!actions.is_down(Action::Sprint));
+        assert!(actions.just_released(Action::Sprint));
+        assert_eq!(actions.axis(Axis::MoveRight), 0.0);
+        assert_eq!(actions.connected_gamepads(), 0);
+    }
+
+    #[test]
+    fn two_gamepads_are_combined() {
+        let mut first = SimulatedGamepad::new(0);
+        let mut second = SimulatedGamepad::new(1);
+        let mut actions = ActionMap::new(Bindings::default());
+        first.connect();
+        second.connect();
+        first.move_axis(GamepadAxis::RightTrigger, 1.0);
+        second.move_axis(GamepadAxis::RightTrigger, 1.0);
+        actions_with(&mut first, &mut actions);
+        actions_with(&mut second, &mut actions);
+
+        assert_eq!(actions.connected_gamepads(), 2);
+        assert_eq!(actions.axis(Axis::MoveUp), 1.0); // Clamped, not doubled
+    }
+
+    #[test]
+    fn button_stays_down_while_another_gamepad_holds_it() {
+        let mut first = SimulatedGamepad::new(0);
+        let mut second = SimulatedGamepad::new(1);
+        let mut actions = ActionMap::new(Bindings::default());
+        first.connect();
+        second.connect();
+        first.press(GamepadButton::LeftStick);
+        second.press(GamepadButton::LeftStick);
+        actions_with(&mut first, &mut actions);
+        actions_with(&mut second, &mut actions);
+        actions.end_frame();
+
+        first.release(GamepadButton::LeftStick);
+        actions_with(&mut first, &mut actions);
+        assert!(actions.is_down(Action::Sprint));
+        assert!(!actions.just_released(Action::Sprint));
+
+        second.disconnect();
+        actions_with(&mut second, &mut actions);
+        assert!(!actions.is_down(Action::Sprint));
+        assert!(actions.just_released(Action::Sprint));
+    }
+}
@@ -0,0 +1,311 @@
+// Golden image tests: render a scene headlessly and compare it with a
+// reference PNG in tests/golden. Small differences between drivers are
+// allowed through a per-channel tolerance and a perceptual color distance,
+// anything bigger fails and leaves the actual image plus a diff image in
+// target/golden.
+//
+// GOLDEN_BLESS=1 cargo test golden   writes the current output as the new references
+//
+// Needs an OpenGL 3.3 context without a window (see headless.rs). Machines
+// that can't make one fail these tests, every one of them is named golden_*
+// so `cargo test -- --skip golden_` leaves them out on purpose.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use gl33::global_loader::*;
+use gl33::*;
+
+use crate::backend::Gl33;
+use crate::framebuffer::Framebuffer;
+use crate::headless::HeadlessContext;
+use crate::image::Image;
+use crate::screenshot::Alpha;
+use crate::texture::TextureFormat;
+
+//
+// ─── COMPARISON ─────────────────────────────────────────────────────────────────
+//
+
+#[derive(Clone, Copy, Debug)]
+pub struct Tolerance {
+    pub channel: u8,        // Pixels with every channel this close are equal, no questions asked
+    pub perceptual: f32,    // Otherwise equal while the YIQ distance is below this, 0 to 1
+    pub max_different: f32, // Fraction of pixels that may still differ
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance { channel: 2, perceptual: 0.01, max_different: 0.001 }
+    }
+}
+
+pub struct Comparison {
+    pub different: usize, // Pixels over the tolerance
+    pub max_distance: f32,
+    pub diff: Image,      // Expected image faded to gray, differing pixels in red
+}
+
+impl Comparison {
+    pub fn passes(&self, tolerance: &Tolerance) -> bool {
+        let pixels = (self.diff.width * self.diff.height) as f32;
+        self.different as f32 <= tolerance.max_different * pixels
+    }
+}
+
+// Perceived difference between two colors, 0 for equal up to 1 for the most
+// different pair (black vs white is 0.93).
+// Brightness counts most, like in the YIQ based metric of pixelmatch.
+pub fn color_distance(a: [u8; 4], b: [u8; 4]) -> f32 {
+    // Transparent pixels are seen on white
+    let blend = |px: [u8; 4]| {
+        let alpha = px[3] as f32 / 255.0;
+        [0, 1, 2].map(|i| 255.0 + (px[i] as f32 - 255.0) * alpha)
+    };
+    let yiq = |[r, g, b]: [f32; 3]| [
+        0.2988953 * r + 0.5866225 * g + 0.1144822 * b,
+        0.595978 * r - 0.2741761 * g - 0.3218019 * b,
+        0.2114702 * r - 0.5226171 * g + 0.3111469 * b,
+    ];
+    let (a, b) = (yiq(blend(a)), yiq(blend(b)));
+    let (y, i, q) = (a[0] - b[0], a[1] - b[1], a[2] - b[2]);
+    (0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q) / 35215.0 // Largest possible value
+}
+
+pub fn compare(expected: &Image, actual: &Image, tolerance: &Tolerance) -> Result<Comparison, String> {
+    if (expected.width, expected.height) != (actual.width, actual.height) {
+        return Err(format!("Expected {}x{}, got {}x{}", expected.width, expected.height, actual.width, actual.height));
+    }
+
+    let mut comparison = Comparison { different: 0, max_distance: 0.0, diff: Image::new(expected.width, expected.height) };
+    for y in 0..expected.height {
+        for x in 0..expected.width {
+            let (a, b) = (expected.pixel(x, y), actual.pixel(x, y));
+            let close = a.iter().zip(b.iter()).all(|(a, b)| a.abs_diff(*b) <= tolerance.channel);
+            let distance = if close { 0.0 } else { color_distance(a, b) };
+            comparison.max_distance = comparison.max_distance.max(distance);
+
+            let pixel = if distance > tolerance.perceptual {
+                comparison.different += 1;
+                [255, 0, 0, 255]
+            } else {
+                let gray = 191 + ((a[0] as u32 + a[1] as u32 + a[2] as u32) / 12) as u8; // Faded, so red stands out
+                [gray, gray, gray, 255]
+            };
+            comparison.diff.set_pixel(x, y, pixel);
+        }
+    }
+    Ok(comparison)
+}
+
+
+//
+// ─── HARNESS ────────────────────────────────────────────────────────────────────
+//
+
+fn reference_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.png", name))
+}
+
+fn output_path(name: &str, kind: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/golden").join(format!("{}-{}.png", name, kind))
+}
+
+fn blessing() -> bool {
+    std::env::var("GOLDEN_BLESS").is_ok_and(|value| value != "0")
+}
+
+// Compares `actual` with the reference called `name`, or makes it the
+// reference when blessing.
+pub fn check(name: &str, actual: &Image, tolerance: &Tolerance) -> Result<(), String> {
+    let reference = reference_path(name);
+    if blessing() {
+        std::fs::create_dir_all(reference.parent().unwrap()).map_err(|e| e.to_string())?;
+        actual.save(&reference).map_err(|e| e.to_string())?;
+        println!("Blessed {}", reference.display());
+        return Ok(());
+    }
+
+    let keep_actual = || {
+        let path = output_path(name, "actual");
+        std::fs::create_dir_all(path.parent().unwrap()).ok();
+        actual.save(&path).ok();
+        path
+    };
+
+    let expected = match Image::open(&reference) {
+        Ok(expected) => expected,
+        Err(e) => return Err(format!(
+            "No reference {} ({}), output is in {}. Run with GOLDEN_BLESS=1 to accept it",
+            reference.display(), e, keep_actual().display()
+        )),
+    };
+
+    let comparison = compare(&expected, actual, tolerance)
+        .map_err(|e| format!("{}: {}, output is in {}", name, e, keep_actual().display()))?;
+    if comparison.passes(tolerance) {
+        return Ok(());
+    }
+
+    let diff_path = output_path(name, "diff");
+    keep_actual();
+    comparison.diff.save(&diff_path).ok();
+    Err(format!(
+        "{}: {} pixels differ (largest distance {:.4}), see {}",
+        name, comparison.different, comparison.max_distance, diff_path.display()
+    ))
+}
+
+// One context at a time, they are made current on the test's own thread.
+static GL_LOCK: Mutex<()> = Mutex::new(());
+
+// Runs `draw` with a current context and a `width` x `height` target bound,
+// then returns what it drew. Panics when there is no way to get a context.
+pub fn render(width: u32, height: u32, draw: impl FnOnce(&Framebuffer)) -> Image {
+    let _lock = GL_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let context = HeadlessContext::new(false).unwrap_or_else(|e| {
+        panic!("No headless OpenGL for golden image tests: {}. Run with `-- --skip golden_` to leave them out", e)
+    });
+    unsafe {
+        load_global_gl(&|ptr| {
+            let c_str = std::ffi::CStr::from_ptr(ptr as *const i8);
+            context.get_proc_address(c_str.to_str().unwrap())
+        });
+    }
+
+    let target = Framebuffer::new(&mut Gl33, width, height, TextureFormat::Rgba8);
+    target.bind(&mut Gl33);
+    draw(&target);
+    unsafe { glFinish() };
+    let image = target.read_image(&mut Gl33, Alpha::Opaque);
+    target.delete(&mut Gl33);
+    image
+}
+
+
+//
+// ─── TESTS ──────────────────────────────────────────────────────────────────────
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Matrix4;
+    use std::mem::size_of;
+
+    use crate::app::App;
+    use crate::input::Bindings;
+    use crate::renderer::Renderer;
+    use crate::scene::Scene;
+    use crate::shader::Shader;
+    use crate::texture::{Sampler, Texture2D};
+
+    const WIDTH: u32 = 160;
+    const HEIGHT: u32 = 120;
+
+    fn assert_golden(name: &str, image: Image) {
+        if let Err(e) = check(name, &image, &Tolerance::default()) {
+            panic!("{}", e);
+        }
+    }
+
+    fn cube_scene(wireframe: bool) -> Image {
+        render(WIDTH, HEIGHT, |target| {
+            let scene = Scene::default();
+            let mut app = App::new(WIDTH, HEIGHT, Bindings::default(), &scene.camera);
+            app.wireframe = wireframe;
+            Renderer::new(&scene).render(&mut app, Some(target));
+        })
+    }
+
+    #[test]
+    fn identical_images_match() {
+        let image = Image::from_png_bytes(include_bytes!("images/car.png")).unwrap();
+        let comparison = compare(&image, &image, &Tolerance::default()).unwrap();
+        assert_eq!(comparison.different, 0);
+        assert!(compare(&image, &Image::new(1, 1), &Tolerance::default()).is_err());
+    }
+
+    #[test]
+    fn small_changes_pass_and_visible_ones_fail() {
+        let mut expected = Image::new(10, 10);
+        expected.data.iter_mut().for_each(|byte| *byte = 128);
+
+        let mut noisy = expected.clone();
+        noisy.set_pixel(3, 3, [131, 126, 128, 128]); // Slightly over the channel tolerance, not visible
+        let comparison = compare(&expected, &noisy, &Tolerance::default()).unwrap();
+        assert_eq!(comparison.different, 0);
+        assert!(comparison.max_distance > 0.0);
+
+        let mut broken = expected.clone();
+        broken.set_pixel(5, 5, [255, 0, 0, 255]);
+        let comparison = compare(&expected, &broken, &Tolerance::default()).unwrap();
+        assert_eq!(comparison.different, 1);
+        assert!(!comparison.passes(&Tolerance::default()));
+        assert_eq!(comparison.diff.pixel(5, 5), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn color_distance_is_normalized() {
+        assert_eq!(color_distance([10, 20, 30, 255], [10, 20, 30, 255]), 0.0);
+        assert!((color_distance([0, 0, 0, 255], [255, 255, 255, 255]) - 0.933).abs() < 1e-3);
+        assert!(color_distance([0, 0, 0, 0], [255, 255, 255, 255]) < 1e-6); // Transparent is white
+    }
+
+    #[test]
+    fn golden_cube_scene() {
+        assert_golden("cube_scene", cube_scene(false));
+    }
+
+    #[test]
+    fn golden_wireframe() {
+        assert_golden("wireframe", cube_scene(true));
+    }
+
+    #[test]
+    fn golden_textured_quad() {
+        let image = render(WIDTH, HEIGHT, |_| unsafe {
+            type Vertex = [f32; 8];
+            let vertices: [Vertex; 4] = [
+                // Positions         // Colors       // Texture coordinates, images are stored top row first
+                [ -0.8,  0.8,  0.0,  1.0, 1.0, 1.0,  0.0, 0.0 ],
+                [  0.8,  0.8,  0.0,  1.0, 1.0, 1.0,  1.0, 0.0 ],
+                [ -0.8, -0.8,  0.0,  1.0, 1.0, 1.0,  0.0, 1.0 ],
+                [  0.8, -0.8,  0.0,  1.0, 1.0, 1.0,  1.0, 1.0 ],
+            ];
+
+            let (mut vao, mut vbo) = (0u32, 0u32);
+            glGenVertexArrays(1, &mut vao);
+            glBindVertexArray(vao);
+            glGenBuffers(1, &mut vbo);
+            glBindBuffer(GL_ARRAY_BUFFER, vbo);
+            glBufferData(GL_ARRAY_BUFFER, size_of::<[Vertex; 4]>() as isize, vertices.as_ptr().cast(), GL_STATIC_DRAW);
+            for (location, size, offset) in [(0, 3, 0), (1, 3, 3), (2, 2, 6)] {
+                glVertexAttribPointer(location, size, GL_FLOAT, 0, size_of::<Vertex>() as i32, (offset * size_of::<f32>()) as *const _);
+                glEnableVertexAttribArray(location);
+            }
+
+            let image = Image::from_png_bytes(include_bytes!("images/car.png")).unwrap();
+            let texture = Texture2D::from_image(&mut Gl33, &image, &Sampler::nearest());
+            texture.bind(&mut Gl33, 0);
+
+            let shader = Shader::from_source(&mut Gl33, include_bytes!("shaders/vertex.vert"), include_bytes!("shaders/textured.frag"));
+            shader.use_shader();
+            shader.set_int("texture1", 0);
+            let identity = Matrix4::<f32>::identity();
+            glUniformMatrix4fv(glGetUniformLocation(shader.id, "transform\0".as_ptr()), 1, 0, identity.as_ptr());
+            glUniformMatrix4fv(glGetUniformLocation(shader.id, "model\0".as_ptr()), 1, 0, identity.as_ptr());
+
+            glClearColor(0.0, 0.0, 0.0, 1.0);
+            glClear(GL_COLOR_BUFFER_BIT);
+            glDrawArrays(GL_TRIANGLE_STRIP, 0, 4);
+
+            glDeleteBuffers(1, &vbo);
+            glDeleteVertexArrays(1, &vao);
+            texture.delete(&mut Gl33);
+            shader.delete(&mut Gl33);
+        });
+        assert_golden("textured_quad", image);
+    }
+}
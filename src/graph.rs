@@ -0,0 +1,273 @@
+use nalgebra::{Matrix4, Translation3, UnitQuaternion, Vector3};
+
+use crate::scene::Transform;
+
+// Translation, rotation and scale, applied scale first.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Trs {
+    pub translation: Vector3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Default for Trs {
+    fn default() -> Self {
+        Trs { translation: Vector3::zeros(), rotation: UnitQuaternion::identity(), scale: Vector3::repeat(1.0) }
+    }
+}
+
+impl Trs {
+
+    #[allow(dead_code)]
+    pub fn at(translation: Vector3<f32>) -> Self {
+        Trs { translation, ..Trs::default() }
+    }
+
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Translation3::from(self.translation).to_homogeneous()
+            * self.rotation.to_homogeneous()
+            * Matrix4::new_nonuniform_scaling(&self.scale)
+    }
+}
+
+impl From<&Transform> for Trs {
+    fn from(transform: &Transform) -> Self {
+        let [x, y, z, w] = transform.rotation;
+        Trs {
+            translation: Vector3::from(transform.translation),
+            rotation: UnitQuaternion::from_quaternion(nalgebra::Quaternion::new(w, x, y, z)),
+            scale: Vector3::from(transform.scale),
+        }
+    }
+}
+
+//
+// ─── GRAPH ──────────────────────────────────────────────────────────────────────
+//
+
+// Handle to a node, only meaningful for the graph that made it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+struct Node {
+    name: String,
+    local: Trs,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    world: Matrix4<f32>, // Parent's world times local, stale while dirty
+    dirty: bool,
+}
+
+// Nodes placed relative to their parents. World matrices are only worked out
+// when asked for, changing a node marks it and everything below it dirty.
+// A dirty node's children are always dirty too, so marking can stop early.
+#[derive(Default)]
+pub struct SceneGraph {
+    nodes: Vec<Node>,
+}
+
+impl SceneGraph {
+
+    pub fn new() -> Self {
+        SceneGraph::default()
+    }
+
+    pub fn add(&mut self, name: &str, local: Trs, parent: Option<NodeId>) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Node {
+            name: name.to_string(),
+            local,
+            parent,
+            children: Vec::new(),
+            world: Matrix4::identity(),
+            dirty: true,
+        });
+        if let Some(parent) = parent {
+            self.nodes[parent.0].children.push(id);
+        }
+        id
+    }
+
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    #[allow(dead_code)]
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.nodes.iter().position(|node| node.name == name).map(NodeId)
+    }
+
+    pub fn name(&self, id: NodeId) -> &str {
+        &self.nodes[id.0].name
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].parent
+    }
+
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.nodes[id.0].children
+    }
+
+    #[allow(dead_code)]
+    pub fn local(&self, id: NodeId) -> &Trs {
+        &self.nodes[id.0].local
+    }
+
+    #[allow(dead_code)]
+    pub fn set_local(&mut self, id: NodeId, local: Trs) {
+        self.nodes[id.0].local = local;
+        self.mark_dirty(id);
+    }
+
+    #[allow(dead_code)]
+    pub fn is_dirty(&self, id: NodeId) -> bool {
+        self.nodes[id.0].dirty
+    }
+
+    // Moves `id` under `parent`, or to the top with None. The local transform
+    // stays, so the node moves along with its new parent.
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> Result<(), String> {
+        let mut ancestor = parent;
+        while let Some(node) = ancestor {
+            if node == id {
+                return Err(format!("\"{}\" can't go under itself", self.name(id)));
+            }
+            ancestor = self.parent(node);
+        }
+
+        if let Some(old) = self.nodes[id.0].parent {
+            self.nodes[old.0].children.retain(|&child| child != id);
+        }
+        if let Some(parent) = parent {
+            self.nodes[parent.0].children.push(id);
+        }
+        self.nodes[id.0].parent = parent;
+        self.mark_dirty(id);
+        Ok(())
+    }
+
+    fn mark_dirty(&mut self, id: NodeId) {
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let node = &mut self.nodes[id.0];
+            if !node.dirty {
+                node.dirty = true;
+                stack.extend_from_slice(&node.children);
+            }
+        }
+    }
+
+    // Brings the parent chain up to date first, only as far up as it is dirty.
+    pub fn world(&mut self, id: NodeId) -> &Matrix4<f32> {
+        if self.nodes[id.0].dirty {
+            let parent = self.nodes[id.0].parent.map(|parent| *self.world(parent));
+            let node = &mut self.nodes[id.0];
+            node.world = match parent {
+                Some(parent) => parent * node.local.matrix(),
+                None => node.local.matrix(),
+            };
+            node.dirty = false;
+        }
+        &self.nodes[id.0].world
+    }
+
+    // Every node, parents before their children, with world matrices up to date.
+    pub fn traverse(&mut self, mut visit: impl FnMut(NodeId, &Matrix4<f32>)) {
+        let mut stack: Vec<NodeId> = (0..self.nodes.len()).rev()
+            .map(NodeId)
+            .filter(|&id| self.parent(id).is_none())
+            .collect();
+        while let Some(id) = stack.pop() {
+            visit(id, self.world(id));
+            stack.extend(self.children(id).iter().rev().copied());
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.traverse(|_, _| ());
+    }
+}
+
+
+//
+// ─── TESTS ──────────────────────────────────────────────────────────────────────
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Point3;
+
+    fn origin_of(graph: &mut SceneGraph, id: NodeId) -> Point3<f32> {
+        graph.world(id).transform_point(&Point3::origin())
+    }
+
+    #[test]
+    fn children_follow_parents_lazily() {
+        let mut graph = SceneGraph::new();
+        let car = graph.add("car", Trs::at(Vector3::new(10.0, 0.0, 0.0)), None);
+        let wheel = graph.add("wheel", Trs::at(Vector3::new(1.0, -0.5, 0.0)), Some(car));
+        let bolt = graph.add("bolt", Trs::at(Vector3::new(0.0, 0.0, 0.2)), Some(wheel));
+        assert_eq!(origin_of(&mut graph, bolt), Point3::new(11.0, -0.5, 0.2));
+        assert!(!graph.is_dirty(car));
+
+        // Turning the car a quarter left swings the wheel around it
+        let mut turned = *graph.local(car);
+        turned.rotation = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), std::f32::consts::FRAC_PI_2);
+        graph.set_local(car, turned);
+        assert!(graph.is_dirty(car) && graph.is_dirty(wheel) && graph.is_dirty(bolt));
+
+        let wheel_origin = origin_of(&mut graph, wheel);
+        assert!((wheel_origin - Point3::new(10.0, -0.5, -1.0)).norm() < 1e-5, "{}", wheel_origin);
+        assert!(graph.is_dirty(bolt)); // Nobody asked yet
+
+        // Scale carries down too
+        let mut bigger = *graph.local(wheel);
+        bigger.scale = Vector3::repeat(2.0);
+        graph.set_local(wheel, bigger);
+        assert!(!graph.is_dirty(car));
+        let bolt_origin = origin_of(&mut graph, bolt);
+        assert!((bolt_origin - Point3::new(10.4, -0.5, -1.0)).norm() < 1e-5, "{}", bolt_origin);
+    }
+
+    #[test]
+    fn reparenting_moves_along_and_refuses_cycles() {
+        let mut graph = SceneGraph::new();
+        let a = graph.add("a", Trs::at(Vector3::new(1.0, 0.0, 0.0)), None);
+        let b = graph.add("b", Trs::at(Vector3::new(0.0, 2.0, 0.0)), Some(a));
+        let c = graph.add("c", Trs::at(Vector3::new(0.0, 0.0, 3.0)), None);
+        assert_eq!(origin_of(&mut graph, b), Point3::new(1.0, 2.0, 0.0));
+
+        graph.set_parent(b, Some(c)).unwrap();
+        assert_eq!(graph.children(a), &[]);
+        assert_eq!(origin_of(&mut graph, b), Point3::new(0.0, 2.0, 3.0));
+
+        assert!(graph.set_parent(c, Some(b)).is_err());
+        assert!(graph.set_parent(c, Some(c)).is_err());
+        assert_eq!(graph.parent(c), None);
+    }
+
+    #[test]
+    fn traversal_visits_parents_first_in_order() {
+        let mut graph = SceneGraph::new();
+        let root = graph.add("root", Trs::default(), None);
+        let late_child = graph.add("late child", Trs::default(), None);
+        graph.add("other", Trs::default(), None);
+        let child = graph.add("child", Trs::default(), Some(root));
+        graph.set_parent(late_child, Some(root)).unwrap();
+        graph.add("grandchild", Trs::default(), Some(child));
+
+        let mut order = Vec::new();
+        graph.traverse(|id, _| order.push(id));
+        let names: Vec<&str> = order.iter().map(|&id| graph.name(id)).collect();
+        assert_eq!(names, ["root", "child", "grandchild", "late child", "other"]);
+        assert!(order.iter().all(|&id| !graph.is_dirty(id)));
+    }
+}
@@ -0,0 +1,101 @@
+// OpenGL context without a window, for rendering on machines with no display
+// (CI). On Linux this is EGL on the Mesa surfaceless platform, which falls
+// back to llvmpipe when there is no GPU either. Elsewhere glutin makes a
+// hidden context. Either way there is no default framebuffer to draw into,
+// so render into a Framebuffer.
+
+#[cfg(target_os = "linux")]
+use khronos_egl as egl;
+
+#[cfg(target_os = "linux")]
+const EGL_PLATFORM_SURFACELESS_MESA: egl::Enum = 0x31DD;
+
+#[cfg(target_os = "linux")]
+pub struct HeadlessContext {
+    egl: egl::DynamicInstance<egl::EGL1_5>,
+    display: egl::Display,
+    context: egl::Context,
+}
+
+#[cfg(target_os = "linux")]
+impl HeadlessContext {
+
+    // Creates an OpenGL 3.3 core context and makes it current. `debug` asks
+    // for a debug context, drivers that can't just ignore it.
+    pub fn new(debug: bool) -> Result<Self, String> {
+        let egl = unsafe { egl::DynamicInstance::<egl::EGL1_5>::load_required() }
+            .map_err(|e| format!("Could not load libEGL: {}", e))?;
+
+        let display = unsafe { egl.get_platform_display(EGL_PLATFORM_SURFACELESS_MESA, egl::DEFAULT_DISPLAY, &[egl::ATTRIB_NONE]) }
+            .map_err(|e| format!("No surfaceless EGL display: {}", e))?;
+        egl.initialize(display).map_err(|e| format!("Could not initialize EGL: {}", e))?;
+        egl.bind_api(egl::OPENGL_API).map_err(|e| format!("EGL has no desktop OpenGL: {}", e))?;
+
+        // Surfaceless displays have no window configs, so ask for pbuffer ones
+        let config = egl
+            .choose_first_config(display, &[
+                egl::SURFACE_TYPE, egl::PBUFFER_BIT,
+                egl::RENDERABLE_TYPE, egl::OPENGL_BIT,
+                egl::NONE,
+            ])
+            .map_err(|e| format!("Could not choose EGL config: {}", e))?
+            .ok_or("No EGL config supports OpenGL")?;
+
+        let context = egl
+            .create_context(display, config, None, &[
+                egl::CONTEXT_MAJOR_VERSION, 3,
+                egl::CONTEXT_MINOR_VERSION, 3,
+                egl::CONTEXT_OPENGL_PROFILE_MASK, egl::CONTEXT_OPENGL_CORE_PROFILE_BIT,
+                egl::CONTEXT_OPENGL_DEBUG, debug as egl::Int,
+                egl::NONE,
+            ])
+            .map_err(|e| format!("Could not create OpenGL 3.3 context: {}", e))?;
+        egl.make_current(display, None, None, Some(context))
+            .map_err(|e| format!("Could not make context current: {}", e))?;
+
+        Ok(HeadlessContext { egl, display, context })
+    }
+
+    pub fn get_proc_address(&self, name: &str) -> *const std::ffi::c_void {
+        self.egl.get_proc_address(name).map_or(std::ptr::null(), |f| f as *const _)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for HeadlessContext {
+    fn drop(&mut self) {
+        self.egl.make_current(self.display, None, None, None).ok();
+        self.egl.destroy_context(self.display, self.context).ok();
+        self.egl.terminate(self.display).ok();
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub struct HeadlessContext {
+    context: glutin::Context<glutin::PossiblyCurrent>,
+    _event_loop: glutin::event_loop::EventLoop<()>, // Context must not outlive it
+}
+
+#[cfg(not(target_os = "linux"))]
+impl HeadlessContext {
+
+    pub fn new(debug: bool) -> Result<Self, String> {
+        use glutin::{Api, ContextBuilder, GlProfile, GlRequest};
+
+        let event_loop = glutin::event_loop::EventLoop::new();
+        let context = ContextBuilder::new()
+            .with_gl(GlRequest::Specific(Api::OpenGl, (3, 3)))
+            .with_gl_profile(GlProfile::Core)
+            .with_gl_debug_flag(debug)
+            .build_headless(&event_loop, glutin::dpi::PhysicalSize::new(1, 1))
+            .map_err(|e| format!("Could not create OpenGL 3.3 context: {}", e))?;
+        let context = unsafe { context.make_current() }
+            .map_err(|(_, e)| format!("Could not make context current: {}", e))?;
+
+        Ok(HeadlessContext { context, _event_loop: event_loop })
+    }
+
+    pub fn get_proc_address(&self, name: &str) -> *const std::ffi::c_void {
+        self.context.get_proc_address(name) as *const _
+    }
+}
@@ -0,0 +1,115 @@
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+use png::{BitDepth, ColorType};
+
+// 8-bit RGBA image stored row by row, top row first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>, // width * height * 4 bytes
+}
+
+impl Image {
+
+    // Creates fully transparent image of given size.
+    pub fn new(width: u32, height: u32) -> Self {
+        Image {
+            width,
+            height,
+            data: vec![0; (width * height) as usize * 4],
+        }
+    }
+
+    // Decodes PNG from any reader and converts it to RGBA.
+    pub fn from_png<R: Read>(reader: R) -> Result<Self, png::DecodingError> {
+        let decoder = png::Decoder::new(reader); // Expands palettes and strips 16 bit channels by default
+        let (info, mut reader) = decoder.read_info()?;
+
+        let mut buffer = vec![0; info.buffer_size()];
+        reader.next_frame(&mut buffer)?;
+
+        let pixel_count = (info.width * info.height) as usize;
+        let mut data = Vec::with_capacity(pixel_count * 4);
+        match info.color_type {
+            ColorType::RGBA => data = buffer,
+            ColorType::RGB => for px in buffer.chunks_exact(3) {
+                data.extend_from_slice(&[px[0], px[1], px[2], 255]);
+            },
+            ColorType::GrayscaleAlpha => for px in buffer.chunks_exact(2) {
+                data.extend_from_slice(&[px[0], px[0], px[0], px[1]]);
+            },
+            ColorType::Grayscale => for px in buffer.iter() {
+                data.extend_from_slice(&[*px, *px, *px, 255]);
+            },
+            ColorType::Indexed => unreachable!("Palette is expanded by the decoder"),
+        }
+
+        Ok(Image {
+            width: info.width,
+            height: info.height,
+            data,
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn from_png_bytes(bytes: &[u8]) -> Result<Self, png::DecodingError> {
+        Self::from_png(std::io::Cursor::new(bytes))
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, png::DecodingError> {
+        Self::from_png(File::open(path)?)
+    }
+
+    // Width and height from the PNG header, the pixels are not decoded.
+    pub fn read_size(path: impl AsRef<Path>) -> Result<(u32, u32), png::DecodingError> {
+        let (info, _) = png::Decoder::new(File::open(path)?).read_info()?;
+        Ok((info.width, info.height))
+    }
+
+    // Encodes image as RGBA PNG, tagged as sRGB so viewers don't guess the gamma.
+    pub fn write_png<W: Write>(&self, writer: W) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(ColorType::RGBA);
+        encoder.set_depth(BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_chunk(*b"sRGB", &[0])?; // Perceptual rendering intent
+        writer.write_image_data(&self.data)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), png::EncodingError> {
+        self.write_png(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * self.width + x) * 4) as usize;
+        [self.data[i], self.data[i + 1], self.data[i + 2], self.data[i + 3]]
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, rgba: [u8; 4]) {
+        let i = ((y * self.width + x) * 4) as usize;
+        self.data[i..i + 4].copy_from_slice(&rgba);
+    }
+
+    // Copies whole `source` into this image with its top-left corner at (x, y).
+    pub fn blit(&mut self, source: &Image, x: u32, y: u32) {
+        let row_len = source.width as usize * 4;
+        for row in 0..source.height {
+            let src = (row * source.width) as usize * 4;
+            let dst = (((y + row) * self.width + x) * 4) as usize;
+            self.data[dst..dst + row_len].copy_from_slice(&source.data[src..src + row_len]);
+        }
+    }
+
+    // Mirrors the image vertically. OpenGL returns rows bottom first.
+    pub fn flip_vertically(&mut self) {
+        let row_len = self.width as usize * 4;
+        let height = self.height as usize;
+        for row in 0..height / 2 {
+            let (top, bottom) = self.data.split_at_mut((height - row - 1) * row_len);
+            top[row * row_len..(row + 1) * row_len].swap_with_slice(&mut bottom[..row_len]);
+        }
+    }
+}
@@ -0,0 +1,609 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use glutin::event::{DeviceEvent, ElementState, Event, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent};
+use glutin::window::Window;
+
+use nalgebra::{Vector2, Vector3};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{self, ConfigError};
+use crate::gamepad::{self, DeadZones, GamepadAxis, GamepadButton, GamepadEvent, GamepadId};
+
+//
+// ─── ACTIONS ────────────────────────────────────────────────────────────────────
+//
+
+// Things that are either happening or not. Game logic asks for these instead of keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Action {
+    Sprint,
+    ToggleWireframe,
+    ToggleCursor,
+    Quit,
+    CameraFly,
+    CameraOrbit,
+    CameraFirstPerson,
+    CycleProjection,
+    Select,
+    Screenshot,
+    Pause, // Freezes the simulation, rendering goes on
+    Step,  // One tick while paused
+    ToggleStats,
+}
+
+// Things with an amount, e.g. how far forward to move or how much to turn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Axis {
+    MoveRight,
+    MoveUp,
+    MoveForward,
+    LookX, // Positive => turn right, amount per frame like mouse movement
+    LookY, // Positive => look up
+    TurnX, // Like LookX but a rate, 1.0 => full turning speed, e.g. for sticks
+    TurnY,
+    Zoom,  // Positive => closer
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Button {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton), // Any connected gamepad
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AxisInput {
+    Buttons(Button, Button), // Negative and positive direction, e.g. S and W
+    MouseX,                  // Raw mouse counts since last frame
+    MouseY,                  // Grows downwards like screen coordinates
+    Wheel,                   // Lines scrolled since last frame, positive => away from user
+    Gamepad(GamepadAxis),    // Current stick or trigger position after dead zone
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AxisBinding {
+    pub input: AxisInput,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+impl AxisBinding {
+    pub fn new(input: AxisInput, scale: f32) -> Self {
+        AxisBinding { input, scale }
+    }
+
+    fn keys(negative: VirtualKeyCode, positive: VirtualKeyCode) -> Self {
+        Self::new(AxisInput::Buttons(Button::Key(negative), Button::Key(positive)), 1.0)
+    }
+
+    fn gamepad(axis: GamepadAxis, scale: f32) -> Self {
+        Self::new(AxisInput::Gamepad(axis), scale)
+    }
+}
+
+
+//
+// ─── INPUT EVENTS ───────────────────────────────────────────────────────────────
+//
+
+// The part of window, device and gamepad events the app reacts to. Unlike winit
+// events these can be saved and replayed, see recording.rs.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum InputEvent {
+    Key(VirtualKeyCode, ElementState),
+    MouseButton(MouseButton, ElementState),
+    MouseMotion(f32, f32), // Raw counts, keeps coming when cursor hits the window edge
+    MouseWheel(f32),       // Lines, positive => away from user
+    CursorMoved(f32, f32), // Pixels from the top left corner of the window
+    Focused(bool),
+    Resized(u32, u32),
+    Gamepad(GamepadEvent),
+}
+
+impl InputEvent {
+    pub fn from_winit<T>(event: &Event<T>) -> Option<Self> {
+        match event {
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::KeyboardInput { input, .. } => {
+                    input.virtual_keycode.map(|key| InputEvent::Key(key, input.state))
+                }
+                WindowEvent::MouseInput { button, state, .. } => Some(InputEvent::MouseButton(*button, *state)),
+                WindowEvent::MouseWheel { delta, .. } => Some(InputEvent::MouseWheel(match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / 20.0, // Roughly a line
+                })),
+                WindowEvent::CursorMoved { position, .. } => {
+                    Some(InputEvent::CursorMoved(position.x as f32, position.y as f32))
+                }
+                WindowEvent::Focused(focused) => Some(InputEvent::Focused(*focused)),
+                WindowEvent::Resized(size) => Some(InputEvent::Resized(size.width, size.height)),
+                _ => None,
+            },
+            Event::DeviceEvent { event: DeviceEvent::MouseMotion { delta }, .. } => {
+                Some(InputEvent::MouseMotion(delta.0 as f32, delta.1 as f32))
+            }
+            _ => None,
+        }
+    }
+}
+
+
+//
+// ─── BINDINGS ───────────────────────────────────────────────────────────────────
+//
+
+// Which inputs trigger which action. Stored as RON so players can rebind keys
+// without recompiling, see input.ron.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bindings {
+    #[serde(default)]
+    pub actions: BTreeMap<Action, Vec<Button>>,
+    #[serde(default)]
+    pub axes: BTreeMap<Axis, Vec<AxisBinding>>,
+    #[serde(default)]
+    pub dead_zones: DeadZones,
+    #[serde(default)]
+    pub mouse: MouseSettings,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let key = |key| Button::Key(key);
+        let pad = |button| Button::Gamepad(button);
+
+        let mut actions = BTreeMap::new();
+        actions.insert(Action::Sprint, vec![key(VirtualKeyCode::LShift), pad(GamepadButton::LeftStick)]);
+        actions.insert(Action::ToggleWireframe, vec![key(VirtualKeyCode::X), pad(GamepadButton::North)]);
+        actions.insert(Action::ToggleCursor, vec![key(VirtualKeyCode::Tab)]);
+        actions.insert(Action::Quit, vec![key(VirtualKeyCode::Escape)]);
+        actions.insert(Action::CameraFly, vec![key(VirtualKeyCode::Key1), pad(GamepadButton::DPadUp)]);
+        actions.insert(Action::CameraOrbit, vec![key(VirtualKeyCode::Key2), pad(GamepadButton::DPadRight)]);
+        actions.insert(Action::CameraFirstPerson, vec![key(VirtualKeyCode::Key3), pad(GamepadButton::DPadDown)]);
+        actions.insert(Action::CycleProjection, vec![key(VirtualKeyCode::P), pad(GamepadButton::DPadLeft)]);
+        actions.insert(Action::Select, vec![Button::Mouse(MouseButton::Right), pad(GamepadButton::South)]);
+        actions.insert(Action::Screenshot, vec![key(VirtualKeyCode::F12)]);
+        actions.insert(Action::Pause, vec![key(VirtualKeyCode::F9)]);
+        actions.insert(Action::Step, vec![key(VirtualKeyCode::F10)]);
+        actions.insert(Action::ToggleStats, vec![key(VirtualKeyCode::F3)]);
+
+        let mut axes = BTreeMap::new();
+        axes.insert(Axis::MoveRight, vec![
+            AxisBinding::keys(VirtualKeyCode::A, VirtualKeyCode::D),
+            AxisBinding::gamepad(GamepadAxis::LeftStickX, 1.0),
+        ]);
+        axes.insert(Axis::MoveUp, vec![
+            AxisBinding::keys(VirtualKeyCode::LControl, VirtualKeyCode::Space),
+            AxisBinding::gamepad(GamepadAxis::RightTrigger, 1.0),
+            AxisBinding::gamepad(GamepadAxis::LeftTrigger, -1.0),
+        ]);
+        axes.insert(Axis::MoveForward, vec![
+            AxisBinding::keys(VirtualKeyCode::S, VirtualKeyCode::W),
+            AxisBinding::gamepad(GamepadAxis::LeftStickY, 1.0),
+        ]);
+        axes.insert(Axis::LookX, vec![AxisBinding::new(AxisInput::MouseX, 1.0)]);
+        axes.insert(Axis::LookY, vec![AxisBinding::new(AxisInput::MouseY, -1.0)]); // Mouse up => look up
+        axes.insert(Axis::TurnX, vec![AxisBinding::gamepad(GamepadAxis::RightStickX, 1.0)]);
+        axes.insert(Axis::TurnY, vec![AxisBinding::gamepad(GamepadAxis::RightStickY, 1.0)]);
+        axes.insert(Axis::Zoom, vec![AxisBinding::new(AxisInput::Wheel, 1.0)]);
+
+        Bindings { actions, axes, dead_zones: DeadZones::default(), mouse: MouseSettings::default() }
+    }
+}
+
+impl Bindings {
+
+    #[allow(dead_code)]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        config::load(path)
+    }
+
+    // Defaults when the file doesn't exist yet.
+    pub fn load_or_default(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        config::load_or_default(path)
+    }
+
+    #[allow(dead_code)]
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        config::save(self, path)
+    }
+}
+
+
+//
+// ─── ACTION MAP ─────────────────────────────────────────────────────────────────
+//
+
+// Collects window, device and gamepad events during a frame and answers questions
+// about actions. Call `end_frame` after the frame so presses and mouse motion reset.
+pub struct ActionMap {
+    pub bindings: Bindings,
+    held: HashSet<Button>,
+    pressed: HashSet<Button>,  // Went down this frame
+    released: HashSet<Button>, // Went up this frame
+    mouse_delta: Vector2<f32>,
+    wheel: f32,
+    gamepads: HashMap<GamepadId, GamepadState>,
+    rebinding: Option<Action>,
+}
+
+#[derive(Default)]
+struct GamepadState {
+    buttons: HashSet<GamepadButton>,
+    axes: HashMap<GamepadAxis, f32>, // Raw values
+}
+
+impl ActionMap {
+
+    pub fn new(bindings: Bindings) -> Self {
+        ActionMap {
+            bindings,
+            held: HashSet::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+            mouse_delta: Vector2::zeros(),
+            wheel: 0.0,
+            gamepads: HashMap::new(),
+            rebinding: None,
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::Key(key, state) => self.button(Button::Key(key), state),
+            InputEvent::MouseButton(button, state) => self.button(Button::Mouse(button), state),
+            InputEvent::MouseMotion(x, y) => self.mouse_delta += Vector2::new(x, y),
+            InputEvent::MouseWheel(lines) => self.wheel += lines,
+            InputEvent::Focused(false) => {
+                // Key releases won't arrive while unfocused, gamepads keep working
+                self.held.retain(|button| matches!(button, Button::Gamepad(_)));
+            }
+            InputEvent::Gamepad(ref event) => self.handle_gamepad_event(event),
+            InputEvent::Focused(true) | InputEvent::Resized(..) | InputEvent::CursorMoved(..) => (),
+        }
+    }
+
+    fn handle_gamepad_event(&mut self, event: &GamepadEvent) {
+        match *event {
+            GamepadEvent::Connected(id) => {
+                self.gamepads.entry(id).or_default();
+            }
+            GamepadEvent::Disconnected(id) => {
+                if let Some(state) = self.gamepads.remove(&id) {
+                    for button in state.buttons {
+                        self.gamepad_button_released(button);
+                    }
+                }
+            }
+            GamepadEvent::ButtonPressed(id, button) => {
+                self.gamepads.entry(id).or_default().buttons.insert(button);
+                self.button(Button::Gamepad(button), ElementState::Pressed);
+            }
+            GamepadEvent::ButtonReleased(id, button) => {
+                self.gamepads.entry(id).or_default().buttons.remove(&button);
+                self.gamepad_button_released(button);
+            }
+            GamepadEvent::AxisChanged(id, axis, value) => {
+                self.gamepads.entry(id).or_default().axes.insert(axis, value);
+            }
+        }
+    }
+
+    // All pads share one Button per gamepad button, it is only up once none of
+    // them holds it.
+    fn gamepad_button_released(&mut self, button: GamepadButton) {
+        if !self.gamepads.values().any(|state| state.buttons.contains(&button)) {
+            self.button(Button::Gamepad(button), ElementState::Released);
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn connected_gamepads(&self) -> usize {
+        self.gamepads.len()
+    }
+
+    // Position of a stick or trigger summed over all gamepads, after dead zone.
+    fn gamepad_axis(&self, axis: GamepadAxis) -> f32 {
+        let dead_zones = &self.bindings.dead_zones;
+        let value: f32 = self.gamepads.values().map(|state| {
+            let raw = |axis| state.axes.get(&axis).copied().unwrap_or(0.0);
+            match axis.stick_pair() {
+                Some((x_axis, y_axis)) => {
+                    let (x, y) = gamepad::stick_dead_zone(raw(x_axis), raw(y_axis), dead_zones.stick);
+                    if axis == x_axis { x } else { y }
+                }
+                None => gamepad::trigger_dead_zone(raw(axis), dead_zones.trigger),
+            }
+        }).sum();
+        value.clamp(-1.0, 1.0)
+    }
+
+    fn button(&mut self, button: Button, state: ElementState) {
+        match state {
+            ElementState::Pressed => {
+                if let Some(action) = self.rebinding.take() {
+                    self.rebind(action, vec![button]);
+                    return; // The press only picked the new binding
+                }
+                if self.held.insert(button) { // Key repeat sends presses of held keys again
+                    self.pressed.insert(button);
+                }
+            }
+            ElementState::Released => {
+                if self.held.remove(&button) {
+                    self.released.insert(button);
+                }
+            }
+        }
+    }
+
+    fn buttons(&self, action: Action) -> impl Iterator<Item = &Button> {
+        self.bindings.actions.get(&action).into_iter().flatten()
+    }
+
+    pub fn is_down(&self, action: Action) -> bool {
+        self.buttons(action).any(|button| self.held.contains(button))
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.buttons(action).any(|button| self.pressed.contains(button))
+    }
+
+    // Like just_pressed, but only answers yes once. For actions checked every
+    // frame while end_frame only runs on simulation ticks.
+    pub fn take_pressed(&mut self, action: Action) -> bool {
+        let buttons: Vec<Button> = self.buttons(action).copied().collect();
+        let count = self.pressed.len();
+        self.pressed.retain(|button| !buttons.contains(button));
+        self.pressed.len() != count
+    }
+
+    #[allow(dead_code)]
+    pub fn just_released(&self, action: Action) -> bool {
+        self.buttons(action).any(|button| self.released.contains(button))
+    }
+
+    // Sum of all bindings of the axis.
+    pub fn axis(&self, axis: Axis) -> f32 {
+        let bindings = self.bindings.axes.get(&axis).into_iter().flatten();
+        bindings.map(|binding| {
+            let value = match binding.input {
+                AxisInput::Buttons(negative, positive) => {
+                    self.held.contains(&positive) as i32 as f32 - self.held.contains(&negative) as i32 as f32
+                }
+                AxisInput::MouseX => self.mouse_delta.x,
+                AxisInput::MouseY => self.mouse_delta.y,
+                AxisInput::Wheel => self.wheel,
+                AxisInput::Gamepad(axis) => self.gamepad_axis(axis),
+            };
+            value * binding.scale
+        }).sum()
+    }
+
+    pub fn end_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+        self.mouse_delta = Vector2::zeros();
+        self.wheel = 0.0;
+    }
+
+    pub fn rebind(&mut self, action: Action, buttons: Vec<Button>) {
+        self.bindings.actions.insert(action, buttons);
+    }
+
+    #[allow(dead_code)]
+    pub fn rebind_axis(&mut self, axis: Axis, bindings: Vec<AxisBinding>) {
+        self.bindings.axes.insert(axis, bindings);
+    }
+
+    // Next pressed key or mouse button becomes the only binding of `action`.
+    #[allow(dead_code)]
+    pub fn rebind_next(&mut self, action: Action) {
+        self.rebinding = Some(action);
+    }
+
+    #[allow(dead_code)]
+    pub fn is_rebinding(&self) -> bool {
+        self.rebinding.is_some()
+    }
+}
+
+// Camera movement input (x => right, y => up, z => forward). Diagonals are
+// clamped so they aren't faster than moving along a single axis.
+pub fn movement(actions: &ActionMap, sprint_multiplier: f32) -> Vector3<f32> {
+    let direction = Vector3::new(
+        actions.axis(Axis::MoveRight),
+        actions.axis(Axis::MoveUp),
+        actions.axis(Axis::MoveForward)
+    );
+    let direction = if direction.norm() > 1.0 { direction.normalize() } else { direction };
+
+    if actions.is_down(Action::Sprint) {
+        direction * sprint_multiplier
+    } else {
+        direction
+    }
+}
+
+
+//
+// ─── MOUSE LOOK ─────────────────────────────────────────────────────────────────
+//
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MouseSettings {
+    pub sensitivity: f32, // Degrees per mouse count
+    pub invert_y: bool,
+}
+
+impl Default for MouseSettings {
+    fn default() -> Self {
+        MouseSettings {
+            sensitivity: 0.1,
+            invert_y: false,
+        }
+    }
+}
+
+// Turns look axes into camera look input. Raw deltas keep coming when
+// the cursor would hit the window edge, so the cursor is grabbed and hidden
+// while looking around and released again for UI or other windows.
+pub struct MouseLook {
+    pub sensitivity: f32, // Degrees per mouse count
+    pub invert_y: bool,
+    pub captured: bool,   // Applied to the window with `grab_cursor`
+}
+
+impl MouseLook {
+
+    pub fn new(settings: &MouseSettings) -> Self {
+        MouseLook {
+            sensitivity: settings.sensitivity,
+            invert_y: settings.invert_y,
+            captured: false,
+        }
+    }
+
+    // Yaw and pitch change for this frame's look axes. Nothing while released.
+    pub fn look(&self, actions: &ActionMap) -> Vector2<f32> {
+        if !self.captured {
+            return Vector2::zeros();
+        }
+        let pitch = actions.axis(Axis::LookY);
+        let pitch = if self.invert_y { -pitch } else { pitch };
+        Vector2::new(actions.axis(Axis::LookX), pitch) * self.sensitivity
+    }
+}
+
+// Grabs and hides the cursor or gives it back.
+pub fn grab_cursor(window: &Window, grab: bool) {
+    if let Err(e) = window.set_cursor_grab(grab) {
+        log::warn!("Could not {} cursor: {}", if grab { "grab" } else { "release" }, e);
+    }
+    window.set_cursor_visible(!grab);
+}
+
+
+//
+// ─── TESTS ──────────────────────────────────────────────────────────────────────
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(actions: &mut ActionMap, key: VirtualKeyCode, state: ElementState) {
+        actions.handle_event(&InputEvent::Key(key, state));
+    }
+
+    #[test]
+    fn input_ron_matches_the_defaults() {
+        let bindings = Bindings::load(config::source_path("input.ron")).unwrap();
+        assert_eq!(bindings, Bindings::default());
+    }
+
+    #[test]
+    fn presses_last_one_frame_and_holds_until_release() {
+        let mut actions = ActionMap::new(Bindings::default());
+        key(&mut actions, VirtualKeyCode::X, ElementState::Pressed);
+        assert!(actions.just_pressed(Action::ToggleWireframe));
+        assert!(actions.is_down(Action::ToggleWireframe));
+        assert!(!actions.just_pressed(Action::Quit));
+
+        // Key repeat doesn't press it again
+        actions.end_frame();
+        key(&mut actions, VirtualKeyCode::X, ElementState::Pressed);
+        assert!(!actions.just_pressed(Action::ToggleWireframe));
+        assert!(actions.is_down(Action::ToggleWireframe));
+
+        key(&mut actions, VirtualKeyCode::X, ElementState::Released);
+        assert!(actions.just_released(Action::ToggleWireframe));
+        assert!(!actions.is_down(Action::ToggleWireframe));
+        actions.end_frame();
+        assert!(!actions.just_released(Action::ToggleWireframe));
+
+        // Focus loss lets go of keys, their releases never arrive
+        key(&mut actions, VirtualKeyCode::LShift, ElementState::Pressed);
+        actions.handle_event(&InputEvent::Focused(false));
+        assert!(!actions.is_down(Action::Sprint));
+    }
+
+    #[test]
+    fn taken_presses_are_gone_before_end_frame() {
+        let mut actions = ActionMap::new(Bindings::default());
+        actions.handle_event(&InputEvent::MouseButton(MouseButton::Right, ElementState::Pressed));
+        key(&mut actions, VirtualKeyCode::F12, ElementState::Pressed);
+        assert!(actions.take_pressed(Action::Select));
+        assert!(!actions.take_pressed(Action::Select));
+        assert!(actions.is_down(Action::Select));
+        assert!(actions.just_pressed(Action::Screenshot)); // Other actions keep theirs
+    }
+
+    #[test]
+    fn axes_sum_their_bindings_and_motion_resets_each_frame() {
+        let mut actions = ActionMap::new(Bindings::default());
+        key(&mut actions, VirtualKeyCode::D, ElementState::Pressed);
+        assert_eq!(actions.axis(Axis::MoveRight), 1.0);
+        key(&mut actions, VirtualKeyCode::A, ElementState::Pressed);
+        assert_eq!(actions.axis(Axis::MoveRight), 0.0);
+
+        actions.handle_event(&InputEvent::MouseMotion(2.0, 3.0));
+        actions.handle_event(&InputEvent::MouseMotion(1.0, 1.0));
+        actions.handle_event(&InputEvent::MouseWheel(-1.0));
+        assert_eq!(actions.axis(Axis::LookX), 3.0);
+        assert_eq!(actions.axis(Axis::LookY), -4.0); // Mouse down => look down
+        assert_eq!(actions.axis(Axis::Zoom), -1.0);
+
+        actions.end_frame();
+        assert_eq!((actions.axis(Axis::LookX), actions.axis(Axis::Zoom)), (0.0, 0.0));
+        key(&mut actions, VirtualKeyCode::A, ElementState::Released);
+        assert_eq!(actions.axis(Axis::MoveRight), 1.0); // Held keys stay held
+    }
+
+    #[test]
+    fn rebinding_replaces_the_old_buttons() {
+        let mut actions = ActionMap::new(Bindings::default());
+        actions.rebind(Action::Quit, vec![Button::Key(VirtualKeyCode::Q)]);
+        key(&mut actions, VirtualKeyCode::Escape, ElementState::Pressed);
+        assert!(!actions.just_pressed(Action::Quit));
+        key(&mut actions, VirtualKeyCode::Q, ElementState::Pressed);
+        assert!(actions.just_pressed(Action::Quit));
+
+        actions.rebind_axis(Axis::Zoom, vec![AxisBinding::new(AxisInput::Wheel, -2.0)]);
+        actions.handle_event(&InputEvent::MouseWheel(1.0));
+        assert_eq!(actions.axis(Axis::Zoom), -2.0);
+    }
+
+    #[test]
+    fn rebind_next_takes_the_next_press() {
+        let mut actions = ActionMap::new(Bindings::default());
+        actions.rebind_next(Action::Screenshot);
+        assert!(actions.is_rebinding());
+
+        // The picking press doesn't count as a press of anything
+        key(&mut actions, VirtualKeyCode::X, ElementState::Pressed);
+        assert!(!actions.is_rebinding());
+        assert!(!actions.just_pressed(Action::ToggleWireframe));
+        assert!(!actions.just_pressed(Action::Screenshot));
+        assert_eq!(actions.bindings.actions[&Action::Screenshot], vec![Button::Key(VirtualKeyCode::X)]);
+
+        key(&mut actions, VirtualKeyCode::X, ElementState::Released);
+        key(&mut actions, VirtualKeyCode::X, ElementState::Pressed);
+        assert!(actions.just_pressed(Action::Screenshot));
+    }
+
+    #[test]
+    fn mouse_look_uses_the_settings() {
+        let mut actions = ActionMap::new(Bindings::default());
+        actions.handle_event(&InputEvent::MouseMotion(10.0, 20.0));
+        let mut look = MouseLook::new(&MouseSettings { sensitivity: 0.5, invert_y: true });
+        assert_eq!(look.look(&actions), Vector2::zeros()); // Not captured
+        look.captured = true;
+        assert_eq!(look.look(&actions), Vector2::new(5.0, 10.0));
+    }
+}
@@ -1,40 +1,194 @@
-use glutin::event::{Event, KeyboardInput, VirtualKeyCode, WindowEvent, ElementState};
+use glutin::event::{Event, WindowEvent};
 use glutin::event_loop::{ControlFlow, EventLoop};
-use glutin::window::WindowBuilder;
-use glutin::{Api, ContextBuilder, GlRequest};
 
-use std::mem::{size_of, size_of_val};
+use std::path::{Path, PathBuf};
 
 use gl33::global_loader::*;
 use gl33::*;
 
-use nalgebra::{Matrix4, Rotation3, Vector, Vector3, Point3};
-
+mod app;
+mod atlas;
+mod backend;
+mod bounds;
+mod camera;
+mod capture;
+mod config;
+mod debug;
+mod framebuffer;
+mod gamepad;
+#[cfg(test)]
+mod golden;
+mod graph;
+mod headless;
+mod image;
+mod input;
+mod mesh;
+mod overlay;
+mod picking;
+mod queue;
+mod recording;
+mod renderer;
+mod scene;
+mod screenshot;
 mod shader;
-use shader::Shader;
+mod state;
+mod stats;
+mod texture;
+mod timestep;
+mod window;
+use app::App;
+use backend::Backend;
+use capture::Capture;
+use framebuffer::Framebuffer;
+use headless::HeadlessContext;
+use image::Image;
+use input::{Action, Bindings, InputEvent};
+use overlay::Overlay;
+use recording::{Recorder, Recording, Replay};
+use renderer::{RenderStats, Renderer};
+use scene::Scene;
+use screenshot::Alpha;
+use stats::{FrameHistory, FrameStats, GpuTimer};
+use texture::TextureFormat;
+use timestep::FixedTimestep;
+use window::WindowConfig;
 
 const TITLE: &str = "This took me just like 25 hours ~ OpenGL";
+const TICKS_PER_SECOND: f32 = 60.0; // Simulation rate, independent of the frame rate
 
 fn main() {
 
+    //
+    // ─── OFFLINE TOOLS ──────────────────────────────────────────────────────────────
+    //
+
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 1 && args[1] == "--pack-atlas" {
+        if args.len() < 4 {
+            eprintln!("Usage: {} --pack-atlas <output.ron> <image.png>...", args[0]);
+            std::process::exit(2);
+        }
+        let inputs: Vec<_> = args[3..].iter().map(std::path::PathBuf::from).collect();
+        match atlas::pack_files(args[2].as_ref(), &inputs) {
+            Ok(atlas) => println!("Packed {} sprites into {} page(s)", atlas.regions.len(), atlas.pages.len()),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    //
+    // ─── OPTIONS ────────────────────────────────────────────────────────────────────
+    //
+
+    let usage = || -> ! {
+        eprintln!("Usage: {} [--record <file.ron>] [--replay <file.ron>] [--gpu-picking] [--headless <frames>] [--screenshot <file.png>]", args[0]);
+        eprintln!("       [--width <px>] [--height <px>] [--fullscreen windowed|borderless|exclusive]");
+        eprintln!("       [--msaa <samples>] [--vsync on|off] [--debug-gl on|off] [--depth-bits <bits>]");
+        eprintln!("       [--capture <directory> | --capture-pipe <command>] [--fps <frames per second>]");
+        eprintln!("       [--stats-csv <file.csv>] [--log off|error|warn|info|debug|trace] [--scene <file.ron>]");
+        std::process::exit(2);
+    };
+
+    let mut record_path = None;  // Input recording, see recording.rs
+    let mut replay_recording = None; // Runs with a fixed timestep and ignores live input
+    let mut gpu_picking = false; // Pick through an ID buffer instead of ray casts
+    let mut headless_frames = None; // Render this many frames offscreen and exit
+    let mut screenshot_path = None; // Saves the first frame in a window, the last one headless
+    let mut capture_to = None;      // Every frame into numbered PNGs or an encoder, see capture.rs
+    let mut fps = 60;               // Frame rate of captures, the simulation ticks at TICKS_PER_SECOND anyway
+    let mut stats_csv = None;       // Every frame's timings, written on exit
+    let mut log_level = log::LevelFilter::Warn; // GL debug messages below this are dropped
+    let mut scene_path = config::source_path("scene.ron"); // What to draw, see scene.rs
+    let mut window_options = Vec::new(); // Applied over window.ron once it is loaded
+    let mut options = args.iter().skip(1);
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--record" => record_path = Some(options.next().unwrap_or_else(|| usage()).clone()),
+            "--replay" => {
+                let path = options.next().unwrap_or_else(|| usage());
+                let recording = Recording::load(path)
+                    .unwrap_or_else(|e| panic!("Could not load recording {}: {}", path, e));
+                replay_recording = Some(recording);
+            }
+            "--gpu-picking" => gpu_picking = true,
+            "--screenshot" => screenshot_path = Some(PathBuf::from(options.next().unwrap_or_else(|| usage()))),
+            "--capture" => capture_to = Some((false, options.next().unwrap_or_else(|| usage()).clone())),
+            "--capture-pipe" => capture_to = Some((true, options.next().unwrap_or_else(|| usage()).clone())),
+            "--fps" => {
+                let value = options.next().unwrap_or_else(|| usage());
+                fps = value.parse().ok().filter(|&fps| fps > 0).unwrap_or_else(|| usage());
+            }
+            "--scene" => scene_path = PathBuf::from(options.next().unwrap_or_else(|| usage())),
+            "--stats-csv" => stats_csv = Some(PathBuf::from(options.next().unwrap_or_else(|| usage()))),
+            "--log" => {
+                let value = options.next().unwrap_or_else(|| usage());
+                log_level = debug::parse_level(value).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    usage()
+                });
+            }
+            "--headless" => {
+                let value = options.next().unwrap_or_else(|| usage());
+                headless_frames = Some(value.parse().unwrap_or_else(|_| usage()));
+            }
+            option if WindowConfig::OPTIONS.contains(&option) => {
+                window_options.push((option, options.next().unwrap_or_else(|| usage())));
+            }
+            _ => usage(),
+        }
+    }
+
+    debug::init_logger(log_level);
+
+    // Window settings from window.ron, options override them
+    let mut window_config = WindowConfig::load_or_default(config::source_path("window.ron"))
+        .unwrap_or_else(|e| panic!("Could not load window.ron: {}", e));
+    if let Err(e) = window_config.validate() {
+        panic!("Bad window.ron: {}", e);
+    }
+    for (option, value) in window_options {
+        if let Err(e) = window_config.set_option(option, value) {
+            eprintln!("{}", e);
+            usage();
+        }
+    }
+
+    let scene = Scene::load_or_default(&scene_path)
+        .unwrap_or_else(|e| panic!("Could not load scene {}: {}", scene_path.display(), e));
+
+    let mut replay = replay_recording.map(|recording| Replay::new(recording, 1.0 / TICKS_PER_SECOND));
+
+    // Frame size is only known for sure once the window is up
+    let start_capture = |width: u32, height: u32| capture_to.as_ref().map(|(pipe, target)| {
+        let capture = if *pipe {
+            Capture::encoder(target, width, height, fps)
+        } else {
+            Capture::frames(target, fps)
+        };
+        capture.unwrap_or_else(|e| panic!("Could not start capture to {}: {}", target, e))
+    });
+
+    if let Some(frames) = headless_frames {
+        let capture = start_capture(window_config.width, window_config.height);
+        run_headless(frames, &window_config, &scene, replay, gpu_picking, screenshot_path, capture, stats_csv);
+        return;
+    }
+
     //
     // ─── WINDOW SETUP ───────────────────────────────────────────────────────────────
     //
 
     let event_loop = EventLoop::new();                     // Create event loop
-    let window_builder = WindowBuilder::new()              // Set window attributes 
-        .with_title(TITLE);
-
-    let context = ContextBuilder::new()
-        .with_gl(GlRequest::Specific(Api::OpenGl, (3, 3))) // OpenGL 3.3
-        .with_vsync(true)                                  // Enable vsync 
-        .build_windowed(window_builder, &event_loop)       // Build window with OpenGL context 
-        .unwrap();
+
+    // Build window with OpenGL context, settling for less if the driver says no
+    let (context, window_config) = window_config.build_with_fallbacks(TITLE, &event_loop)
+        .unwrap_or_else(|e| panic!("Could not create window: {}", e));
     let context = unsafe { context.make_current().unwrap() };
 
     let window_size = context.window().inner_size();
-    let width = window_size.width as f32;
-    let height = window_size.height as f32;
 
 
     unsafe {
@@ -45,539 +199,428 @@ fn main() {
             context.get_proc_address(r_str) as _
         });
     }
+    debug::install();
 
-    unsafe {
+    // Cubes, shaders and textures, same as in headless mode
+    let mut renderer = Renderer::new(&scene);
+    renderer.gpu_picking = gpu_picking;
 
-        //
-        // ─── VERTEX INPUT ────────────────────────────────────────────────
-        //
-
-        type Vertex = [f32; 8];
-        /*let vertices: [Vertex; 4] = [ // Rectangle
-            // Positions         // Colors       // Texture coordinates
-            [  0.5,  0.5,  0.0,  1.0, 0.0, 0.0,  1.0, 0.0  ], // Top-right corner
-            [  0.5, -0.5,  0.0,  0.0, 1.0, 0.0,  1.0, 1.0  ], // Bottom-right corner
-            [ -0.5, -0.5,  0.0,  0.0, 0.0, 1.0,  0.0, 1.0  ], // Bottom-left corner
-            [ -0.5,  0.5,  0.0,  1.1, 1.1, 1.1,  0.0, 0.0  ], // Top-left corner
-        ];
-
-        let indices: [u32; 6] = [
-            0, 1, 3, // First triangle
-            1, 2, 3,  // Second triangle
-        ];*/
-
-        let vertices: [Vertex; 8] = [
-            [ -0.5,  0.5,  0.5,  1.0, 1.0, 1.0,  1.0, 0.0  ],
-            [  0.5,  0.5,  0.5,  1.0, 1.0, 1.0,  1.0, 0.0  ],
-            [ -0.5, -0.5,  0.5,  1.0, 1.0, 1.0,  1.0, 0.0  ],
-            [  0.5, -0.5,  0.5,  1.0, 1.0, 1.0,  1.0, 0.0  ],
-
-            [ -0.5,  0.5, -0.5,  1.0, 1.0, 0.0,  1.0, 0.0  ],
-            [  0.5,  0.5, -0.5,  1.0, 1.0, 0.0,  1.0, 0.0  ],
-            [ -0.5, -0.5, -0.5,  1.0, 1.0, 0.0,  1.0, 0.0  ],
-            [  0.5, -0.5, -0.5,  1.0, 1.0, 0.0,  1.0, 0.0  ],
-        ];
-
-        let indices: [u32; 36] = [
-            0, 2, 3,
-            0, 1, 3,
-
-            4, 5, 7,
-            4, 6, 7,
-
-            0, 4, 5,
-            0, 1, 5,
-
-            2, 6, 7,
-            2, 3, 7,
-
-            0, 4, 6,
-            0, 2, 6,
-
-            1, 5, 7,
-            1, 3, 7
-        ];
-
-        /*
-        let vertices: [[f32; 5]; 36] = [ // Cube
-            [ -0.5, -0.5, -0.5,  0.0, 0.0 ],
-            [  0.5, -0.5, -0.5,  1.0, 0.0 ],
-            [  0.5,  0.5, -0.5,  1.0, 1.0 ],
-            [  0.5,  0.5, -0.5,  1.0, 1.0 ],
-            [ -0.5,  0.5, -0.5,  0.0, 1.0 ],
-            [ -0.5, -0.5, -0.5,  0.0, 0.0 ],
-
-            [ -0.5, -0.5,  0.5,  0.0, 0.0 ],
-            [  0.5, -0.5,  0.5,  1.0, 0.0 ],
-            [  0.5,  0.5,  0.5,  1.0, 1.0 ],
-            [  0.5,  0.5,  0.5,  1.0, 1.0 ],
-            [ -0.5,  0.5,  0.5,  0.0, 1.0 ],
-            [ -0.5, -0.5,  0.5,  0.0, 0.0 ],
-
-            [ -0.5,  0.5,  0.5,  1.0, 0.0 ],
-            [ -0.5,  0.5, -0.5,  1.0, 1.0 ],
-            [ -0.5, -0.5, -0.5,  0.0, 1.0 ],
-            [ -0.5, -0.5, -0.5,  0.0, 1.0 ],
-            [ -0.5, -0.5,  0.5,  0.0, 0.0 ],
-            [ -0.5,  0.5,  0.5,  1.0, 0.0 ],
-
-            [  0.5,  0.5,  0.5,  1.0, 0.0 ],
-            [  0.5,  0.5, -0.5,  1.0, 1.0 ],
-            [  0.5, -0.5, -0.5,  0.0, 1.0 ],
-            [  0.5, -0.5, -0.5,  0.0, 1.0 ],
-            [  0.5, -0.5,  0.5,  0.0, 0.0 ],
-            [  0.5,  0.5,  0.5,  1.0, 0.0 ],
-
-            [ -0.5, -0.5, -0.5,  0.0, 1.0 ],
-            [  0.5, -0.5, -0.5,  1.0, 1.0 ],
-            [  0.5, -0.5,  0.5,  1.0, 0.0 ],
-            [  0.5, -0.5,  0.5,  1.0, 0.0 ],
-            [ -0.5, -0.5,  0.5,  0.0, 0.0 ],
-            [ -0.5, -0.5, -0.5,  0.0, 1.0 ],
-
-            [ -0.5,  0.5, -0.5,  0.0, 1.0 ],
-            [  0.5,  0.5, -0.5,  1.0, 1.0 ],
-            [  0.5,  0.5,  0.5,  1.0, 0.0 ],
-            [  0.5,  0.5,  0.5,  1.0, 0.0 ],
-            [ -0.5,  0.5,  0.5,  0.0, 0.0 ],
-            [ -0.5,  0.5, -0.5,  0.0, 1.0 ]
-        ];
-        */
-
-        //
-        // ─── ELEMENT OBJECT BUFFER ───────────────────────────────────────
-        //
-
-        let mut ebo = 0u32;
-        glGenBuffers(1, &mut ebo);
-
-        glBindBuffer(GL_ELEMENT_ARRAY_BUFFER, ebo);
-        glBufferData(
-            GL_ELEMENT_ARRAY_BUFFER,
-            size_of_val(&indices) as isize,
-            indices.as_ptr().cast(),
-            GL_STATIC_DRAW
-        );
-
-
-        //
-        // ─── VERTEX BUFFER OBJECT ────────────────────────────────────────
-        //
-
-        let mut vbo = 0u32;                  // Vertex Buffer Object
-        glGenBuffers(1, &mut vbo);           // Generate ID corresponging to VBO
-        assert_ne!(vbo, 0);                  // VBO is succesfully created
-        glBindBuffer(GL_ARRAY_BUFFER, vbo);  // Binding the buffer to GL_ARRAY_BUFFER object
-        
-        glBufferData(                        // Copy vertex data to memory of GPU
-            GL_ARRAY_BUFFER,                 // Current vertex is bound to this object
-            size_of_val(&vertices) as isize, // Size of data in bytes
-            vertices.as_ptr().cast(),        // Data to be send to buffer
-            GL_STATIC_DRAW                   // How will GPU mannage the data:
-                                             //  * GL_STREAM_DRAW - data is set only once 
-                                             //                     and used at most few times
-                                             //  * GL_STATIC_DRAW - data is set only once
-                                             //                     and used many times
-                                             //  * GL_DYNAMIC_DRAW - data us changed a lot
-                                             //                      and used many times
-        );
-
-
-        //
-        // ─── VERTEX BUFFER ARRAY OBJECT ──────────────────────────────────
-        //
-
-        let mut vao = 0u32;
-        // Create vertex object id - generate one vertex array store it to VAO
-        glGenVertexArrays(1, &mut vao);
-        glBindVertexArray(vao);
-        assert_ne!(vao, 0); // VAO is succesfully created
-
-
-        //
-        // ─── LINKING VERTEX ATTRIBUTES ───────────────────────────────────
-        //
-
-        let vertex_size = size_of::<Vertex>() as i32; // 3 * size_of::<f32>() as i32;
-
-        // Position attribute
-        glVertexAttribPointer( // Linking vertex attributes
-            0,                 // In vertex shader (location = 0)
-            3,                 // Size of vertex attribute. vec3 => 3 values
-            GL_FLOAT,
-            0,                 // If 1 is supplied tha data will be normalized between 0.0 and 1.0
-            vertex_size,       // Stride - https://shorturl.me/GyNm - here size of one vertex
-            0 as *const _      // Offset
-        );
-        glEnableVertexAttribArray(0); // Enable attribute
-
-        // Color attribute
-        glVertexAttribPointer( // Linking vertex attributes
-            1,                 // In vertex shader (location = 1)
-            3,                 // Size of vertex attribute. vec3 => 3 values
-            GL_FLOAT,
-            0,                 // If 1 is supplied tha data will be normalized between 0.0 and 1.0
-            vertex_size,       // Stride - https://shorturl.me/GyNm - here size of one vertex
-            (3 * size_of::<f32>()) as *const _      // Offset
-        );
-        glEnableVertexAttribArray(1); // Enable attribute
-
-        // Texture coordinates attribute
-        glVertexAttribPointer(
-            2,                 // In vertex shader (location = 2)
-            2,                 // Size of vertex attribute. vec2 => 2 values
-            GL_FLOAT,
-            0,                 // If 1 is supplied tha data will be normalized between 0.0 and 1.0
-            vertex_size,       // Stride - https://shorturl.me/GyNm - here size of one vertex
-            (6 * size_of::<f32>()) as *const _      // Offset
-                                                    // 0     3     6   8
-                                                    // |-----|-----|---|
-                                                    //    |     |    |
-                                                    //   pos  color texture
-        );
-        glEnableVertexAttribArray(2); // Enable attribute
-
-
-        //
-        // ─── TEXTURE ─────────────────────────────────────────────────────
-        //
-
-        let border_color: [f32; 4] = [ 1.0, 0.0, 0.0, 1.0 ];    
-
-        glTexParameterfv(GL_TEXTURE_2D, GL_TEXTURE_BORDER_COLOR, border_color.as_ptr().cast());
-        glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_S, GL_REPEAT.0 as i32);
-        glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_T, GL_REPEAT.0 as i32);
-        glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, GL_NEAREST.0 as i32);
-        glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MAG_FILTER, GL_NEAREST.0 as i32);
-
-        // Loading image
-        let car_img = {
-            let img_bytes = include_bytes!("images/car.png");
-            let cursor = std::io::Cursor::new(img_bytes);
-            let decoder = png::Decoder::new(cursor);
-
-            let (info, mut reader) = decoder.read_info().expect("You are using JPEG againg, aren't you");
-            let buf_size = (info.width * info.height) as usize * 4;
-            let mut img_data = vec![0; buf_size];
-            reader.next_frame(&mut img_data).unwrap();
-            (img_data, info)
-        };
+    // Smooth edges if the context got a multisampled framebuffer
+    if window_config.msaa > 0 {
+        renderer.gl().enable(GL_MULTISAMPLE);
+    }
 
-        /*
-        let other_img = {
-            let img_bytes = include_bytes!("images/img.png");
-            let cursor = std::io::Cursor::new(img_bytes);
-            let decoder = png::Decoder::new(cursor);
-
-            let (info, mut reader) = decoder.read_info().expect("You are using JPEG againg, aren't you");
-            let buf_size = (info.width * info.height) as usize * 4;
-            let mut img_data = vec![0; buf_size];
-            reader.next_frame(&mut img_data).unwrap();
-            (img_data, info)
-        };
-        */
-            
-        // Generating texture
-        let mut texture1 = 0u32;
-        //let mut texture2 = 0u32;
-        glGenTextures(1, &mut texture1);
-        //glGenTextures(1, &mut texture2);
-
-
-        glActiveTexture(GL_TEXTURE0);
-        glBindTexture(GL_TEXTURE_2D, texture1);
-        glTexImage2D(
-            GL_TEXTURE_2D,              // Texture type TEXTURE_3D AND TEXTURE_1D arent affected.
-            0,                          // Mipmap level 0 => base level
-            0x1908, // GL_RGBA          // Format for storing the texture
-            car_img.1.width as i32,     // Image width
-            car_img.1.height as i32,    // Image height
-            0,                          // Some legacy shit => always 0
-            GL_RGB,                     // Image color format
-            GL_UNSIGNED_BYTE,           // Image datatype; this one is stored as bytes
-            car_img.0.as_ptr().cast()   // Image data
-        );
-        glGenerateMipmap(GL_TEXTURE_2D);
-        
-        
-        /*
-        glActiveTexture(GL_TEXTURE1);
-        glBindTexture(GL_TEXTURE_2D, texture2);
-        glTexImage2D(
-            GL_TEXTURE_2D,              // Texture type TEXTURE_3D AND TEXTURE_1D arent affected.
-            0,                          // Mipmap level 0 => base level
-            GL_RGBA.0 as i32,           // Format for storing the texture
-            other_img.1.width as i32,   // Image width
-            other_img.1.height as i32,  // Image height
-            0,                          // Some legacy shit => always 0
-            GL_RGB,                     // Image color format
-            GL_UNSIGNED_BYTE,           // Image datatype; this one is stored as bytes
-            other_img.0.as_ptr().cast() // Image data
-        );
-        glGenerateMipmap(GL_TEXTURE_2D);
-        */
-
-
-        //
-        // ─── SHADERS ─────────────────────────────────────────────────────
-        //
-
-        // Shader program
-        let shader_program = Shader::new("shaders/vertex.vert", "shaders/fragment.frag");
-
-
-        //
-        // ─── ETC ─────────────────────────────────────────────────────────
-        //
-
-        // Set background color
-        glClearColor(0.275, 0.51, 0.706, 1.0);
-
-        // Set polygon mode
-        glPolygonMode(GL_FRONT_AND_BACK, GL_FILL); // Filled 
-        //glPolygonMode(GL_FRONT_AND_BACK, GL_LINE); // Wireframe
-
-        /* Maximum number of possible shader attributes => in my case: 16
-        let mut attrib_num = 0i32;
-        glGetIntegerv(GL_MAX_VERTEX_ATTRIBS, &mut attrib_num);
-        println!(">> {}", attrib_num);
-        */
-
-        //let mut delta_time = 0.0;
-        let mut last_frame = 0.0;
-        let mut last_fps = 0.0;
-
-        fn radians(degrees: f32) -> f32 {
-            degrees * (std::f32::consts::PI / 180.0)
-        }
+    let mut last_frame = 0.0;
+    let mut last_title = 0.0;
 
-        let mut pitch = 0.0;
-        let mut yaw = 1.0;
-        let mut direction = Vector3::new(0.0, 0.0, 0.0);
-        let mut last_x = 0.0;
-        let mut last_y = 0.0;
-
-        // Projection
-        let mut projection_matrix = Matrix4::new_perspective(width/height, 45.0, 0.1, 100.0);
-
-
-        //
-        // ─── CAMERA ──────────────────────────────────────────────────────
-        //
-
-        let mut camera_pos = Point3::new(0.0, 0.0, 3.0);
-        let camera_pos_vec = Vector3::new(camera_pos.x, camera_pos.y, camera_pos.z);
-        let mut camera_speed = 0.05;
-        let mut camera_front = Vector3::new(0.0, 0.0, -1.0);
-        let up = Vector3::new(0.0, 1.0, 0.0);
-        let camera_target = Vector3::new(0.0, 0.0, 0.0);
-        let camera_direction: Vector3::<f32> = (camera_pos_vec - camera_target).normalize();
-        let camera_right = (cross_v3f32(up, camera_direction)).normalize();
-        let camera_up = cross_v3f32(camera_direction, camera_right);
-
-        fn cross_v3f32(v1: Vector3::<f32>, v2: Vector3::<f32>) -> Vector3::<f32> {
-            // TODO Proper function
-            Vector3::new(v1.y*v2.z - v2.y*v1.z, -(v1.x*v2.z - v2.x*v1.z), v1.x*v2.y - v2.x*v1.y)
+    // Frame timings for the title, the F3 graph and --stats-csv
+    let mut history = FrameHistory::new(240, stats_csv.is_some());
+    let mut gpu_timer = GpuTimer::new();
+    let mut overlay = Overlay::new(renderer.gl());
+
+    // Key bindings, see input.ron
+    let bindings = Bindings::load_or_default(config::source_path("input.ron"))
+        .unwrap_or_else(|e| panic!("Could not load input.ron: {}", e));
+    let mut gamepads = gamepad::default_backend(); // None unless built with `--features gamepad`
+
+
+    //
+    // ─── APP ────────────────────────────────────────────────────────────────────────
+    //
+
+    // Camera, actions and the rest of the state that input changes
+    let mut app = App::new(window_size.width, window_size.height, bindings, &scene.camera);
+    let mut cursor_grabbed = app.mouse_look.captured;
+    input::grab_cursor(context.window(), cursor_grabbed);
+
+    let mut capture = start_capture(window_size.width, window_size.height);
+    let mut timestep = FixedTimestep::new(TICKS_PER_SECOND);
+
+    let mut recorder = record_path.as_ref().map(|_| {
+        let mut recorder = Recorder::new();
+        // Replay starts with whatever window it gets, so remember ours
+        recorder.record(0, InputEvent::Resized(window_size.width, window_size.height));
+        recorder
+    });
+
+
+    //
+    // ─── EVENT LOOP ─────────────────────────────────────────────────────────────────
+    //
+    
+    let t0 = std::time::Instant::now();
+    event_loop.run(move |event, _, control_flow| {
+        if let Some(input) = InputEvent::from_winit(&event) {
+            if let Some(recorder) = recorder.as_mut() {
+                recorder.record(timestep.ticks, input);
+            }
+            if replay.is_none() {
+                app.handle_input(&input);
+            }
         }
 
+        match event {
 
-        //
-        // ─── EVENT LOOP ──────────────────────────────────────────────────
-        //
-        
-        let t0 = std::time::Instant::now();
-        event_loop.run(move |event, _, control_flow| {
-            match event {
+            //
+            // INPUT
+            //
 
-                //
-                // INPUT
-                //
+            Event::WindowEvent { event, .. } => match event {
+
+                // When window close is requested
+                WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
 
-                Event::WindowEvent { event, .. } => match event {
-
-                    // When window close is requested
-                    WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-
-                    // When window is resized
-                    WindowEvent::Resized(new_size) => {
-                        context.resize(new_size); // Resize window context
-                        glViewport( // Resize OpenGL viewport
-                            0,
-                            0,
-                            new_size.width as i32,
-                            new_size.height as i32
-                        );
-                        projection_matrix = Matrix4::new_perspective( // To remove distrotion
-                            new_size.width as f32 / new_size.height as f32,
-                            45.0, // POV
-                            0.1,
-                            100.0
-                        );
+                // When window is resized, the renderer picks up the new viewport from App
+                WindowEvent::Resized(new_size) => context.resize(new_size),
+
+                _ => ()
+            }
+
+            // Save the recording and finish the capture on the way out
+            Event::LoopDestroyed => {
+                overlay.delete(renderer.gl());
+                if let Some(path) = stats_csv.as_ref() {
+                    save_stats(&history, path);
+                }
+                if let Some(capture) = capture.take() {
+                    match capture.finish() {
+                        Ok(frames) => log::info!("Captured {} frames", frames),
+                        Err(e) => log::error!("Capture failed: {}", e),
                     }
+                }
+                if let (Some(recorder), Some(path)) = (recorder.as_ref(), record_path.as_ref()) {
+                    match recorder.recording.save(path) {
+                        Ok(()) => log::info!("Recorded {} events to {}", recorder.recording.events.len(), path),
+                        Err(e) => log::error!("Could not save recording {}: {}", path, e),
+                    }
+                }
+            }
+            
 
-                    // Handling keyboard input
-                    WindowEvent::KeyboardInput {
-                        input: KeyboardInput { virtual_keycode: Some(virtual_code), state, .. },
-                        ..
-                    } => match (virtual_code, state) {
-                        (VirtualKeyCode::Escape, ElementState::Pressed) => {
-                             *control_flow = ControlFlow::Exit;
-                        }
-                        (VirtualKeyCode::X, ElementState::Pressed) => glPolygonMode(GL_FRONT_AND_BACK, GL_LINE),
-                        (VirtualKeyCode::X, ElementState::Released) => glPolygonMode(GL_FRONT_AND_BACK, GL_FILL),
-
-                        (VirtualKeyCode::W, ElementState::Pressed) => camera_pos += camera_speed * camera_front,
-                        (VirtualKeyCode::S, ElementState::Pressed) => camera_pos -= camera_speed * camera_front,
-                        (VirtualKeyCode::A, ElementState::Pressed) => camera_pos -= cross_v3f32(camera_front, camera_up).normalize() * camera_speed,
-                        (VirtualKeyCode::D, ElementState::Pressed) => camera_pos += cross_v3f32(camera_front, camera_up).normalize() * camera_speed,
-
-                        _ => ()
-                    },
-
-                    /*WindowEvent::AxisMotion { axis, value, ..} => match axis {
-                        _ => println!("ax: {}, val: {}", axis, value)//value as f32,
-                    }*/
-                    WindowEvent::CursorMoved { position, .. } => {
-                        pitch += (last_y - position.y as f32) * 0.8;
-                        yaw += (position.x as f32 - last_x) * 0.8;
-                        if pitch > 89.0 {
-                            pitch =  89.0;
+            //
+            // RENDERING / DRAWING
+            //
+
+            Event::RedrawEventsCleared => {
+                let frame_start = std::time::Instant::now();
+                let current_frame = t0.elapsed().as_secs_f32();
+                let real_delta_time = current_frame - last_frame;
+                last_frame = current_frame;
+
+                // Captures advance by the same amount every frame however slow the readback is
+                let frame_time = capture.as_ref().map_or(real_delta_time, |capture| capture.timestep);
+
+
+                //
+                // ACTIONS
+                //
+
+                if let Some(backend) = gamepads.as_mut() {
+                    for event in backend.poll() {
+                        let input = InputEvent::Gamepad(event);
+                        if let Some(recorder) = recorder.as_mut() {
+                            recorder.record(timestep.ticks, input);
                         }
-                        if pitch < -89.0 {
-                            pitch = -89.0;
+                        if replay.is_none() {
+                            app.handle_input(&input);
                         }
-                        last_x = position.x as f32;
-                        last_y = position.y as f32;
                     }
+                }
 
-                    _ => ()
+                simulate(&mut app, &mut timestep, replay.as_mut(), frame_time);
+
+                if replay.as_ref().is_some_and(Replay::is_finished) {
+                    if capture.is_some() {
+                        log::info!("Replay finished, so is the capture");
+                        *control_flow = ControlFlow::Exit;
+                    } else {
+                        log::info!("Replay finished, back to live input");
+                        replay = None;
+                    }
                 }
-                
+
+                if app.quit {
+                    *control_flow = ControlFlow::Exit;
+                }
+                if app.mouse_look.captured != cursor_grabbed {
+                    cursor_grabbed = app.mouse_look.captured;
+                    input::grab_cursor(context.window(), cursor_grabbed);
+                }
+
 
                 //
-                // RENDERING / DRAWING
+                // DRAWING
                 //
 
-                Event::RedrawEventsCleared => {
-                    // Clear buffers
-                    glEnable(GL_DEPTH_TEST); 
-                    glClear(GL_COLOR_BUFFER_BIT | GL_DEPTH_BUFFER_BIT);                    
-                    
-                    shader_program.use_shader();
-
-                    context.resize(window_size);
-
-
-                    //
-                    // TEXTURES
-                    //
-
-                    shader_program.set_int("texture1", 0);
-                    //shader_program.set_int("texture2", 1);
-                    
-                    
-                    //
-                    // CAMERA
-                    //
-
-                    let current_frame = t0.elapsed().as_secs_f32();
-                    let delta_time = current_frame - last_frame;
-                    last_frame = current_frame;
-
-                    camera_speed = 5.0 * delta_time;
-
-                    direction.x = radians(yaw).cos() * radians(pitch).cos();
-                    direction.y = radians(pitch).sin();
-                    direction.z = radians(yaw).sin() * radians(pitch).cos();
-
-                    camera_front = direction.normalize();
-                    
-                    //
-                    // FPS
-                    //
-                    
-                    let fps = (1.0 / delta_time) * 0.05 + last_fps * 0.95; // Smooth values
-                    let title = String::from(TITLE) + " ~ FPS: " + (fps as u32).to_string().as_str();
-                    context.window().set_title(title.as_str());
-                    last_fps = fps; 
-
-
-                    let cam_view = Matrix4::look_at_rh(
-                        &camera_pos,//&Point3::new(cam_x, 0.0, cam_z),
-                        &(camera_pos + camera_front),//&Point3::new(0.0, 0.0, 0.0),
-                        &camera_up//&Vector3::new(0.0, 1.0, 0.0)
-                    );
-
-                    
-                    //
-                    // TRANSFORMATION
-                    //
-
-                    let rotation = Rotation3::from_axis_angle(
-                        &Vector3::x_axis(),
-                        0.0//20.0 * (std::f32::consts::PI / 180.0) // 20° to rad
-                    ).to_homogeneous();
-                   
-                    // Scaling
-                    let scale = Matrix4::new_scaling(1.0);
-
-                    // Translation
-                    let translation = Matrix4::new_translation(&Vector::from([0.0, 0.0, 0.0]));
-
-                    // Together
-                    let model = translation * scale * rotation;
-                    let view = cam_view;
-                    let projection = projection_matrix;
-                    let final_transformation = projection * view * model;
-                    
-                    let transform_location = glGetUniformLocation(shader_program.id, "transform\0".as_ptr());
-                    if transform_location == -1 { panic!("Transform uniform not found!") }
-                    glUniformMatrix4fv(transform_location, 1, 0, final_transformation.as_ptr());
-
-
-                    //
-                    // DRAWING
-                    //
-
-                    glBindBuffer(GL_ELEMENT_ARRAY_BUFFER, ebo);
-
-                    let locations: [Vector3::<f32>; 10] = [
-                        Vector3::new( 0.0,  0.0,  0.0), 
-                        Vector3::new( 2.0,  5.0, -15.0), 
-                        Vector3::new(-1.5, -2.2, -2.5),  
-                        Vector3::new(-3.8, -2.0, -12.3),
-                        Vector3::new( 2.4, -0.4, -3.5),  
-                        Vector3::new(-1.7,  3.0, -7.5),  
-                        Vector3::new( 1.3, -2.0, -2.5),  
-                        Vector3::new( 1.5,  2.0, -2.5), 
-                        Vector3::new( 1.5,  0.2, -1.5), 
-                        Vector3::new(-1.3,  1.0, -1.5)  
-                    ];
-
-                    let transform_location = glGetUniformLocation(shader_program.id, "offset\0".as_ptr());
-                        if transform_location == -1 { panic!("Offset uniform not found!") }
-
-                    for loc in locations.iter() {
-                        glUniformMatrix4fv(transform_location, 1, 0,  Matrix4::new_translation(loc).as_ptr());
-
-                        glDrawElements(
-                            GL_TRIANGLES,         // Drawing mode
-                            indices.len() as i32, // Number of elements
-                            GL_UNSIGNED_INT,      // Type of indices
-                            0 as *const _         // Offset
-                        );
+                gpu_timer.begin(history.next_frame());
+                let stats = renderer.render(&mut app, None);
+                gpu_timer.end();
+
+                if let (Some(_), Some(hit)) = (app.pick, app.selected) {
+                    log::info!("Picked cube {} at distance {:.2}, point {:.2} {:.2} {:.2}",
+                        hit.object, hit.distance, hit.point.x, hit.point.y, hit.point.z);
+                }
+
+
+                // F12 or --screenshot, has to happen before the back buffer is swapped away
+                let path = if app.screenshot { Some(screenshot::default_path()) } else { screenshot_path.take() };
+                if let Some(path) = path {
+                    let size = context.window().inner_size();
+                    save_screenshot(&screenshot::read_pixels(renderer.gl(), 0, size.width, size.height, TextureFormat::Rgba8, Alpha::Opaque), &path);
+                }
+                if let Some(recording) = capture.as_mut() {
+                    let size = context.window().inner_size();
+                    if let Err(e) = recording.write(&screenshot::read_pixels(renderer.gl(), 0, size.width, size.height, TextureFormat::Rgba8, Alpha::Opaque)) {
+                        log::error!("Capture stopped: {}", e);
+                        *control_flow = ControlFlow::Exit;
                     }
+                }
+                app.end_frame();
 
-                    
-                    //glDrawArrays(GL_TRIANGLES, 0, vertices.len() as i32);
 
+                //
+                // STATS
+                //
 
+                // Drawn after the captures, it is only for whoever is watching
+                if app.show_stats {
+                    overlay.draw(renderer.gl(), &history, app.viewport.x, app.viewport.y);
+                }
 
-                    // ... and finally swap the buffers
-                    context.swap_buffers().unwrap();
+                history.push(FrameStats {
+                    frame_ms: real_delta_time * 1000.0,
+                    cpu_ms: frame_start.elapsed().as_secs_f32() * 1000.0,
+                    gpu_ms: None,
+                    draw_calls: stats.draw_calls,
+                    triangles: stats.triangles,
+                    state_changes: stats.state_changes,
+                    redundant_state: stats.redundant_state,
+                });
+                for (frame, ms) in gpu_timer.poll() {
+                    history.set_gpu_time(frame, ms);
+                }
+
+                // Twice a second is plenty to read
+                if current_frame - last_title > 0.5 {
+                    last_title = current_frame;
+                    let mut title = format!("{} ~ {} ~ Drawn: {} Culled: {}", TITLE, history.summary(), stats.drawn, stats.culled);
+                    if timestep.paused {
+                        title += " ~ Paused";
+                    }
+                    if let Some(hit) = app.selected {
+                        title += &format!(" ~ Selected: {}", hit.object);
+                    }
+                    context.window().set_title(title.as_str());
                 }
-                _ => {}
+
+
+                // ... and finally swap the buffers
+                debug::check_errors("end of frame");
+                context.swap_buffers().unwrap();
+            }
+            _ => {}
+        }
+    });
+}
+
+
+//
+// ─── SIMULATION ─────────────────────────────────────────────────────────────────
+//
+
+// Runs the fixed ticks that `frame_time` seconds are worth and sets up
+// interpolation for drawing. A replay feeds its recorded input into every tick.
+fn simulate(app: &mut App, timestep: &mut FixedTimestep, mut replay: Option<&mut Replay>, frame_time: f32) {
+    // Checked every frame, ticks don't run while paused
+    if app.actions.take_pressed(Action::Pause) {
+        timestep.paused = !timestep.paused;
+        log::info!("{}", if timestep.paused { "Paused, F10 steps one tick" } else { "Running" });
+    }
+    if app.actions.take_pressed(Action::Step) {
+        timestep.request_step();
+    }
+    app.frame_actions();
+
+    let ticks = timestep.advance(frame_time);
+    for _ in 0..ticks {
+        match replay.as_deref_mut() {
+            Some(playing) => playing.step(app),
+            None => app.update(timestep.tick),
+        }
+    }
+    if ticks == 0 && timestep.paused {
+        app.actions.end_frame(); // Nothing reacts while paused, don't save it all up for later
+    }
+    app.alpha = timestep.alpha();
+}
+
+
+//
+// ─── HEADLESS ───────────────────────────────────────────────────────────────────
+//
+
+// Renders `frames` frames into an offscreen framebuffer without opening a
+// window, then exits. Input only comes from a replay, otherwise the camera
+// stays where App puts it.
+#[allow(clippy::too_many_arguments)] // Same options as the window, one by one
+fn run_headless(frames: u32, window_config: &WindowConfig, scene: &Scene, mut replay: Option<Replay>, gpu_picking: bool, screenshot_path: Option<PathBuf>, mut capture: Option<Capture>, stats_csv: Option<PathBuf>) {
+    let context = HeadlessContext::new(window_config.debug_context)
+        .unwrap_or_else(|e| panic!("Could not create headless context: {}", e));
+
+    unsafe {
+        load_global_gl(&|ptr| {
+            let c_str = std::ffi::CStr::from_ptr(ptr as *const i8);
+            context.get_proc_address(c_str.to_str().unwrap())
+        });
+    }
+    debug::install();
+
+    let (width, height) = (window_config.width, window_config.height);
+    let mut renderer = Renderer::new(scene);
+    renderer.gpu_picking = gpu_picking;
+    let target = Framebuffer::new(renderer.gl(), width, height, TextureFormat::Rgba8);
+
+    let bindings = Bindings::load_or_default(config::source_path("input.ron"))
+        .unwrap_or_else(|e| panic!("Could not load input.ron: {}", e));
+    let mut app = App::new(width, height, bindings, &scene.camera);
+    app.mouse_look.captured = false; // No window to grab the cursor of
+
+    let mut timestep = FixedTimestep::new(TICKS_PER_SECOND);
+    let frame_time = capture.as_ref().map_or(1.0 / 60.0, |capture| capture.timestep);
+
+    let mut history = FrameHistory::new(1, stats_csv.is_some());
+    let mut gpu_timer = GpuTimer::new();
+
+    let mut stats = RenderStats::default();
+    for _ in 0..frames {
+        let frame_start = std::time::Instant::now();
+        simulate(&mut app, &mut timestep, replay.as_mut(), frame_time);
+        gpu_timer.begin(history.next_frame());
+        stats = renderer.render(&mut app, Some(&target));
+        gpu_timer.end();
+
+        if let Some(recording) = capture.as_mut() {
+            if let Err(e) = recording.write(&target.read_image(renderer.gl(), Alpha::Opaque)) {
+                log::error!("Capture stopped: {}", e);
+                break;
             }
+        }
+
+        if let (Some(_), Some(hit)) = (app.pick, app.selected) {
+            log::info!("Picked cube {} at distance {:.2}", hit.object, hit.distance);
+        }
+        if app.screenshot {
+            save_screenshot(&target.read_image(renderer.gl(), Alpha::Opaque), &screenshot::default_path());
+        }
+        if app.quit {
+            break;
+        }
+        app.end_frame();
+
+        // No vsync to wait for, so the frame is all CPU
+        let cpu_ms = frame_start.elapsed().as_secs_f32() * 1000.0;
+        history.push(FrameStats {
+            frame_ms: cpu_ms,
+            cpu_ms,
+            gpu_ms: None,
+            draw_calls: stats.draw_calls,
+            triangles: stats.triangles,
+            state_changes: stats.state_changes,
+            redundant_state: stats.redundant_state,
         });
+        for (frame, ms) in gpu_timer.poll() {
+            history.set_gpu_time(frame, ms);
+        }
+    }
+
+    unsafe { glFinish() };
+    for (frame, ms) in gpu_timer.poll() {
+        history.set_gpu_time(frame, ms);
+    }
+    let gl_renderer = unsafe { std::ffi::CStr::from_ptr(glGetString(GL_RENDERER).cast()) };
+    log::info!("Rendered {} frame(s) at {}x{} on {} ~ Drawn: {} Culled: {}",
+        frames, width, height, gl_renderer.to_string_lossy(), stats.drawn, stats.culled);
+    log::info!("{}", history.summary());
+    if let Some(path) = stats_csv {
+        save_stats(&history, &path);
+    }
+
+    if let Some(path) = screenshot_path {
+        save_screenshot(&target.read_image(renderer.gl(), Alpha::Opaque), &path);
+    }
+    target.delete(renderer.gl());
+    if let Some(capture) = capture {
+        match capture.finish() {
+            Ok(frames) => log::info!("Captured {} frames", frames),
+            Err(e) => log::error!("Capture failed: {}", e),
+        }
+    }
+}
+
+fn save_stats(history: &FrameHistory, path: &Path) {
+    match history.save_csv(path) {
+        Ok(()) => log::info!("Saved frame stats to {}", path.display()),
+        Err(e) => log::error!("Could not save frame stats {}: {}", path.display(), e),
+    }
+}
+
+fn save_screenshot(image: &Image, path: &Path) {
+    match image.save(path) {
+        Ok(()) => log::info!("Saved screenshot {}", path.display()),
+        Err(e) => log::error!("Could not save screenshot {}: {}", path.display(), e),
+    }
+}
+
+
+//
+// ─── TESTS ──────────────────────────────────────────────────────────────────────
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glutin::event::{ElementState, VirtualKeyCode};
+
+    #[test]
+    fn replay_ends_where_the_live_session_did() {
+        let start = Scene::default().camera;
+        let frame_times = [0.005, 0.021, 0.033, 0.012, 0.050, 0.016];
+        let input = |frame: usize| match frame {
+            0 => vec![InputEvent::Key(VirtualKeyCode::W, ElementState::Pressed)],
+            9 => vec![InputEvent::Key(VirtualKeyCode::D, ElementState::Pressed), InputEvent::MouseMotion(25.0, -4.0)],
+            14 => vec![InputEvent::MouseMotion(-7.0, 3.5), InputEvent::MouseMotion(2.0, 1.0)],
+            23 => vec![InputEvent::Key(VirtualKeyCode::W, ElementState::Released)],
+            31 => vec![InputEvent::MouseWheel(1.0), InputEvent::Key(VirtualKeyCode::D, ElementState::Released)],
+            _ => Vec::new(),
+        };
+
+        // Events land between uneven frames, some of which run no tick at all
+        let mut live = App::new(800, 600, Bindings::default(), &start);
+        let mut timestep = FixedTimestep::new(TICKS_PER_SECOND);
+        let mut recorder = Recorder::new();
+        for frame in 0..40 {
+            for event in input(frame) {
+                recorder.record(timestep.ticks, event);
+                live.handle_input(&event);
+            }
+            simulate(&mut live, &mut timestep, None, frame_times[frame % frame_times.len()]);
+        }
+
+        // Steady frames this time, running as many ticks as the live session did
+        let mut played = App::new(800, 600, Bindings::default(), &start);
+        let mut replay = Some(Replay::new(recorder.recording, 1.0 / TICKS_PER_SECOND));
+        let mut replay_timestep = FixedTimestep::new(TICKS_PER_SECOND);
+        while replay_timestep.ticks < timestep.ticks {
+            let tick = replay_timestep.tick;
+            simulate(&mut played, &mut replay_timestep, replay.as_mut(), tick);
+            if replay.as_ref().is_some_and(Replay::is_finished) {
+                replay = None;
+            }
+        }
+
+        assert_eq!(replay_timestep.ticks, timestep.ticks);
+        assert_eq!(played.camera.position, live.camera.position);
+        assert_eq!(played.camera.orientation, live.camera.orientation);
+        assert!((live.camera.position - nalgebra::Point3::from(start.position)).norm() > 0.5);
     }
 }
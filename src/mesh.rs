@@ -0,0 +1,130 @@
+use std::mem::size_of;
+
+use gl33::*;
+
+use crate::backend::Backend;
+
+// Indexed triangles in GPU buffers. Vertices are interleaved f32s, the layout
+// says how many floats each attribute takes, in location order. The vertex
+// array remembers the index buffer, so binding it is all a draw needs.
+pub struct Mesh {
+    pub vertex_array: u32,
+    vertex_buffer: u32,
+    index_buffer: u32,
+    pub index_count: i32,
+}
+
+pub fn as_bytes<T: Copy>(data: &[T]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(data.as_ptr().cast(), std::mem::size_of_val(data)) }
+}
+
+impl Mesh {
+
+    pub fn upload(gl: &mut impl Backend, vertices: &[f32], layout: &[i32], indices: &[u32]) -> Self {
+        let floats_per_vertex: i32 = layout.iter().sum();
+        assert_eq!(vertices.len() % floats_per_vertex as usize, 0, "Vertex data doesn't fit the layout");
+
+        let vertex_array = gl.create_vertex_array();
+        gl.bind_vertex_array(vertex_array);
+
+        let vertex_buffer = gl.create_buffer();
+        gl.bind_buffer(GL_ARRAY_BUFFER, vertex_buffer);
+        gl.buffer_data(GL_ARRAY_BUFFER, as_bytes(vertices), GL_STATIC_DRAW);
+
+        let index_buffer = gl.create_buffer();
+        gl.bind_buffer(GL_ELEMENT_ARRAY_BUFFER, index_buffer);
+        gl.buffer_data(GL_ELEMENT_ARRAY_BUFFER, as_bytes(indices), GL_STATIC_DRAW);
+
+        let stride = floats_per_vertex * size_of::<f32>() as i32;
+        let mut offset = 0;
+        for (location, &components) in layout.iter().enumerate() {
+            gl.vertex_attrib_pointer(location as u32, components, stride, offset * size_of::<f32>());
+            gl.enable_vertex_attrib_array(location as u32);
+            offset += components as usize;
+        }
+
+        Mesh { vertex_array, vertex_buffer, index_buffer, index_count: indices.len() as i32 }
+    }
+
+    // Names the vertex array "<label>" and its buffers "<label> vertices" and "<label> indices".
+    pub fn set_label(&self, gl: &mut impl Backend, label: &str) {
+        gl.label(GL_VERTEX_ARRAY, self.vertex_array, label);
+        gl.label(GL_BUFFER, self.vertex_buffer, &format!("{} vertices", label));
+        gl.label(GL_BUFFER, self.index_buffer, &format!("{} indices", label));
+    }
+
+    #[allow(dead_code)]
+    pub fn bind(&self, gl: &mut impl Backend) {
+        gl.bind_vertex_array(self.vertex_array);
+    }
+
+    // Expects the mesh to be bound.
+    #[allow(dead_code)]
+    pub fn draw(&self, gl: &mut impl Backend) {
+        gl.draw_elements(GL_TRIANGLES, self.index_count);
+    }
+
+    // Not Drop, it needs the backend that made it.
+    pub fn delete(&self, gl: &mut impl Backend) {
+        gl.delete_vertex_array(self.vertex_array);
+        gl.delete_buffer(self.vertex_buffer);
+        gl.delete_buffer(self.index_buffer);
+    }
+}
+
+
+//
+// ─── TESTS ──────────────────────────────────────────────────────────────────────
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{Call, MockBackend};
+
+    #[test]
+    fn upload_describes_interleaved_attributes() {
+        let mut gl = MockBackend::new();
+        let vertices = [
+            // Position       Color          UV
+            0.0, 0.0, 0.0,    1.0, 0.0, 0.0,  0.0, 0.0,
+            1.0, 0.0, 0.0,    0.0, 1.0, 0.0,  1.0, 0.0,
+            0.0, 1.0, 0.0,    0.0, 0.0, 1.0,  0.0, 1.0,
+        ];
+        let mesh = Mesh::upload(&mut gl, &vertices, &[3, 3, 2], &[0, 1, 2]);
+
+        assert_eq!(gl.take_calls(), vec![
+            Call::CreateVertexArray(1),
+            Call::BindVertexArray(1),
+            Call::CreateBuffer(2),
+            Call::BindBuffer { target: GL_ARRAY_BUFFER.0, buffer: 2 },
+            Call::BufferData { target: GL_ARRAY_BUFFER.0, len: 24 * 4, usage: GL_STATIC_DRAW.0 },
+            Call::CreateBuffer(3),
+            Call::BindBuffer { target: GL_ELEMENT_ARRAY_BUFFER.0, buffer: 3 },
+            Call::BufferData { target: GL_ELEMENT_ARRAY_BUFFER.0, len: 3 * 4, usage: GL_STATIC_DRAW.0 },
+            Call::VertexAttribPointer { location: 0, components: 3, stride: 32, offset: 0 },
+            Call::EnableVertexAttribArray(0),
+            Call::VertexAttribPointer { location: 1, components: 3, stride: 32, offset: 12 },
+            Call::EnableVertexAttribArray(1),
+            Call::VertexAttribPointer { location: 2, components: 2, stride: 32, offset: 24 },
+            Call::EnableVertexAttribArray(2),
+        ]);
+
+        mesh.bind(&mut gl);
+        mesh.draw(&mut gl);
+        mesh.delete(&mut gl);
+        assert_eq!(gl.take_calls(), vec![
+            Call::BindVertexArray(1),
+            Call::DrawElements { mode: GL_TRIANGLES.0, count: 3 },
+            Call::DeleteVertexArray(1),
+            Call::DeleteBuffer(2),
+            Call::DeleteBuffer(3),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't fit the layout")]
+    fn upload_rejects_partial_vertices() {
+        Mesh::upload(&mut MockBackend::new(), &[0.0; 7], &[3, 2], &[0]);
+    }
+}
@@ -0,0 +1,105 @@
+use std::mem::size_of;
+
+use gl33::*;
+
+use crate::backend::Backend;
+use crate::mesh;
+use crate::shader::Shader;
+use crate::stats::FrameHistory;
+
+type Vertex = [f32; 6]; // Position in window pixels, RGBA color
+
+const BAR_WIDTH: f32 = 2.0;
+const GRAPH_HEIGHT: f32 = 120.0; // Pixels for GRAPH_MS
+const GRAPH_MS: f32 = 50.0;
+const MARGIN: f32 = 10.0;
+
+// Frame time graph in the bottom left corner, one bar per recent frame.
+// CPU time is the wide bar, green under 60 FPS worth, yellow under 30, red
+// above. GPU time is the thin blue bar in front of it. Lines mark 16.7 and
+// 33.3 ms.
+pub struct Overlay {
+    vao: u32,
+    vbo: u32,
+    shader: Shader,
+    vertices: Vec<Vertex>, // Rebuilt every frame, kept to reuse the allocation
+}
+
+impl Overlay {
+
+    pub fn new(gl: &mut impl Backend) -> Self {
+        let shader = Shader::from_source(gl, include_bytes!("shaders/overlay.vert"), include_bytes!("shaders/overlay.frag"));
+
+        let vao = gl.create_vertex_array();
+        gl.bind_vertex_array(vao);
+        let vbo = gl.create_buffer();
+        gl.bind_buffer(GL_ARRAY_BUFFER, vbo);
+
+        let stride = size_of::<Vertex>() as i32;
+        gl.vertex_attrib_pointer(0, 2, stride, 0);
+        gl.enable_vertex_attrib_array(0);
+        gl.vertex_attrib_pointer(1, 4, stride, 2 * size_of::<f32>());
+        gl.enable_vertex_attrib_array(1);
+        gl.label(GL_VERTEX_ARRAY, vao, "overlay");
+        gl.label(GL_BUFFER, vbo, "overlay vertices");
+        shader.set_label(gl, "overlay");
+
+        Overlay { vao, vbo, shader, vertices: Vec::new() }
+    }
+
+    fn rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: [f32; 4]) {
+        let [r, g, b, a] = color;
+        let corners = [(x, y), (x + width, y), (x + width, y + height), (x, y), (x + width, y + height), (x, y + height)];
+        self.vertices.extend(corners.iter().map(|&(x, y)| [x, y, r, g, b, a]));
+    }
+
+    // Draws over whatever is bound, `width` x `height` being its size. Goes
+    // through the renderer's backend so its state cache stays right.
+    pub fn draw(&mut self, gl: &mut impl Backend, history: &FrameHistory, width: f32, height: f32) {
+        let height_of = |ms: f32| (ms / GRAPH_MS).min(1.0) * GRAPH_HEIGHT;
+        let frames: Vec<_> = history.recent().copied().collect();
+        let graph_width = frames.len() as f32 * BAR_WIDTH;
+
+        self.vertices.clear();
+        self.rect(MARGIN, MARGIN, graph_width.max(1.0), GRAPH_HEIGHT, [0.0, 0.0, 0.0, 0.5]);
+        for (i, stats) in frames.iter().enumerate() {
+            let x = MARGIN + i as f32 * BAR_WIDTH;
+            let color = match stats.cpu_ms {
+                ms if ms < 1000.0 / 60.0 => [0.2, 0.9, 0.2, 0.9],
+                ms if ms < 1000.0 / 30.0 => [0.9, 0.8, 0.2, 0.9],
+                _ => [0.9, 0.2, 0.2, 0.9],
+            };
+            self.rect(x, MARGIN, BAR_WIDTH, height_of(stats.cpu_ms), color);
+            if let Some(gpu_ms) = stats.gpu_ms {
+                self.rect(x + BAR_WIDTH / 4.0, MARGIN, BAR_WIDTH / 2.0, height_of(gpu_ms), [0.3, 0.5, 1.0, 0.9]);
+            }
+        }
+        for ms in [1000.0 / 60.0, 1000.0 / 30.0].iter() {
+            self.rect(MARGIN, MARGIN + height_of(*ms), graph_width, 1.0, [1.0, 1.0, 1.0, 0.6]);
+        }
+
+        gl.disable(GL_DEPTH_TEST);
+        gl.enable(GL_BLEND);
+        gl.blend_func(GL_SRC_ALPHA, GL_ONE_MINUS_SRC_ALPHA);
+        gl.polygon_mode(GL_FILL); // The renderer sets its own every frame
+
+        gl.use_program(self.shader.id);
+        let viewport_location = gl.uniform_location(self.shader.id, "viewport");
+        gl.uniform_vec2(viewport_location, [width, height]);
+
+        gl.bind_vertex_array(self.vao);
+        gl.bind_buffer(GL_ARRAY_BUFFER, self.vbo);
+        gl.buffer_data(GL_ARRAY_BUFFER, mesh::as_bytes(&self.vertices), GL_STREAM_DRAW);
+        gl.draw_arrays(GL_TRIANGLES, 0, self.vertices.len() as i32);
+
+        gl.disable(GL_BLEND);
+        gl.enable(GL_DEPTH_TEST);
+    }
+
+    // Not Drop, it needs the backend that made it.
+    pub fn delete(&self, gl: &mut impl Backend) {
+        gl.delete_vertex_array(self.vao);
+        gl.delete_buffer(self.vbo);
+        self.shader.delete(gl);
+    }
+}
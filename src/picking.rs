@@ -0,0 +1,314 @@
+use nalgebra::{Matrix4, Point2, Point3, Vector2, Vector3};
+
+use crate::bounds::Aabb;
+use crate::camera::Camera;
+
+//
+// ─── RAYS ───────────────────────────────────────────────────────────────────────
+//
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+    pub direction: Vector3<f32>, // Unit length, so t along the ray is a distance
+}
+
+impl Ray {
+
+    pub fn new(origin: Point3<f32>, direction: Vector3<f32>) -> Self {
+        Ray { origin, direction: direction.normalize() }
+    }
+
+    pub fn at(&self, t: f32) -> Point3<f32> {
+        self.origin + self.direction * t
+    }
+
+    // Ray from the near plane through a pixel, `cursor` in window pixels with
+    // the origin top left like winit reports it.
+    pub fn from_screen(camera: &Camera, cursor: Point2<f32>, viewport: Vector2<f32>) -> Option<Ray> {
+        let inverse = (camera.projection_matrix() * camera.view_matrix()).try_inverse()?;
+        let (x, y) = to_ndc(cursor, viewport);
+
+        // Reverse-Z has near at +1 and infinity at -1, which won't unproject,
+        // so aim at a point halfway instead. Any point on the line will do.
+        let (near, far) = if camera.projection.reversed_z() { (1.0, 0.0) } else { (-1.0, 1.0) };
+        let near = inverse.transform_point(&Point3::new(x, y, near));
+        let far = inverse.transform_point(&Point3::new(x, y, far));
+        Some(Ray::new(near, far - near))
+    }
+
+    // Slab test. Distance to where the ray enters the box, 0 when it starts inside.
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        let mut t_min = 0.0f32;
+        let mut t_max = f32::INFINITY;
+
+        for axis in 0..3 {
+            // Division by zero gives ±infinity, which the comparisons handle
+            let inverse = 1.0 / self.direction[axis];
+            let t0 = (aabb.min[axis] - self.origin[axis]) * inverse;
+            let t1 = (aabb.max[axis] - self.origin[axis]) * inverse;
+            t_min = t_min.max(t0.min(t1));
+            t_max = t_max.min(t0.max(t1));
+        }
+
+        if t_min <= t_max { Some(t_min) } else { None }
+    }
+
+    // Möller–Trumbore, hits both sides of the triangle.
+    pub fn intersect_triangle(&self, a: &Point3<f32>, b: &Point3<f32>, c: &Point3<f32>) -> Option<f32> {
+        let edge1 = b - a;
+        let edge2 = c - a;
+        let p = self.direction.cross(&edge2);
+        let determinant = edge1.dot(&p);
+        if determinant.abs() < 1e-8 {
+            return None; // Parallel to the triangle
+        }
+
+        let inverse = 1.0 / determinant;
+        let s = self.origin - a;
+        let u = s.dot(&p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = s.cross(&edge1);
+        let v = self.direction.dot(&q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let t = edge2.dot(&q) * inverse;
+        if t >= 0.0 { Some(t) } else { None }
+    }
+
+    // Closest hit on an indexed triangle list placed in the world by `model`.
+    pub fn intersect_mesh(&self, mesh: &MeshRef, model: &Matrix4<f32>) -> Option<f32> {
+        // Going to model space keeps t the same as long as direction isn't renormalized
+        let inverse = model.try_inverse()?;
+        let local = Ray {
+            origin: inverse.transform_point(&self.origin),
+            direction: inverse.transform_vector(&self.direction),
+        };
+
+        mesh.indices
+            .chunks_exact(3)
+            .filter_map(|triangle| {
+                let vertex = |i: usize| &mesh.positions[triangle[i] as usize];
+                local.intersect_triangle(vertex(0), vertex(1), vertex(2))
+            })
+            .min_by(|a, b| a.total_cmp(b))
+    }
+}
+
+
+// Window => normalized device coordinates, Y flips to point up.
+fn to_ndc(pixel: Point2<f32>, viewport: Vector2<f32>) -> (f32, f32) {
+    (2.0 * pixel.x / viewport.x - 1.0, 1.0 - 2.0 * pixel.y / viewport.y)
+}
+
+
+//
+// ─── PICKING ────────────────────────────────────────────────────────────────────
+//
+
+#[derive(Clone, Copy, Debug)]
+pub struct MeshRef<'a> {
+    pub positions: &'a [Point3<f32>],
+    pub indices: &'a [u32],
+}
+
+// Something that can be clicked on. The box is in world space, the optional
+// mesh with its model matrix makes the hit exact instead of box shaped.
+#[derive(Clone, Copy, Debug)]
+pub struct Pickable<'a> {
+    pub aabb: Aabb,
+    pub mesh: Option<(MeshRef<'a>, Matrix4<f32>)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hit {
+    pub object: usize, // Index into the objects given to `raycast`
+    pub distance: f32,
+    pub point: Point3<f32>,
+}
+
+// Nearest object along the ray. Boxes that start further than the best hit
+// so far are skipped without looking at their triangles.
+pub fn raycast(ray: &Ray, objects: &[Pickable]) -> Option<Hit> {
+    let mut candidates: Vec<_> = objects
+        .iter()
+        .enumerate()
+        .filter_map(|(i, object)| ray.intersect_aabb(&object.aabb).map(|t| (i, t)))
+        .collect();
+    candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let mut best: Option<Hit> = None;
+    for (object, box_distance) in candidates {
+        if best.is_some_and(|hit| box_distance > hit.distance) {
+            break;
+        }
+        let distance = match &objects[object].mesh {
+            Some((mesh, model)) => match ray.intersect_mesh(mesh, model) {
+                Some(t) => t,
+                None => continue,
+            },
+            None => box_distance,
+        };
+        if best.is_none_or(|hit| distance < hit.distance) {
+            best = Some(Hit { object, distance, point: ray.at(distance) });
+        }
+    }
+    best
+}
+
+// What the ID buffer had under `pixel`, as the same hit a ray cast gives.
+// `id` is the object index plus one, 0 is the cleared background. `depth`
+// is window space depth read from the same pixel.
+pub fn hit_from_id_buffer(id: u32, depth: f32, camera: &Camera, pixel: Point2<f32>, viewport: Vector2<f32>) -> Option<Hit> {
+    let object = (id as usize).checked_sub(1)?;
+
+    let inverse = (camera.projection_matrix() * camera.view_matrix()).try_inverse()?;
+    let (x, y) = to_ndc(pixel, viewport);
+    let point = inverse.transform_point(&Point3::new(x, y, depth * 2.0 - 1.0));
+
+    let ray = Ray::from_screen(camera, pixel, viewport)?;
+    Some(Hit { object, distance: (point - ray.origin).norm(), point })
+}
+
+
+//
+// ─── TESTS ──────────────────────────────────────────────────────────────────────
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Projection;
+
+    const CUBE_POSITIONS: [[f32; 3]; 8] = [
+        [-0.5, -0.5, -0.5], [0.5, -0.5, -0.5], [-0.5, 0.5, -0.5], [0.5, 0.5, -0.5],
+        [-0.5, -0.5,  0.5], [0.5, -0.5,  0.5], [-0.5, 0.5,  0.5], [0.5, 0.5,  0.5],
+    ];
+    const CUBE_INDICES: [u32; 36] = [
+        0, 1, 3, 0, 3, 2, // -Z
+        4, 5, 7, 4, 7, 6, // +Z
+        0, 1, 5, 0, 5, 4, // -Y
+        2, 3, 7, 2, 7, 6, // +Y
+        0, 2, 6, 0, 6, 4, // -X
+        1, 3, 7, 1, 7, 5, // +X
+    ];
+
+    fn cube_points() -> Vec<Point3<f32>> {
+        CUBE_POSITIONS.iter().map(|p| Point3::from(*p)).collect()
+    }
+
+    fn unit_box_at(x: f32, y: f32, z: f32) -> Aabb {
+        Aabb::new(Point3::new(x - 0.5, y - 0.5, z - 0.5), Point3::new(x + 0.5, y + 0.5, z + 0.5))
+    }
+
+    #[test]
+    fn screen_center_ray_looks_forward() {
+        let camera = Camera::new(Point3::new(0.0, 0.0, 3.0), Projection::Perspective { fov_y_degrees: 45.0, near: 0.1, far: 100.0 });
+        let ray = Ray::from_screen(&camera, Point2::new(400.0, 300.0), Vector2::new(800.0, 600.0)).unwrap();
+        assert!((ray.direction - Vector3::new(0.0, 0.0, -1.0)).norm() < 1e-4);
+        assert!((ray.origin - Point3::new(0.0, 0.0, 2.9)).norm() < 1e-4);
+
+        // Top edge of the window is half the FOV up
+        let ray = Ray::from_screen(&camera, Point2::new(400.0, 0.0), Vector2::new(800.0, 600.0)).unwrap();
+        let angle = ray.direction.angle(&Vector3::new(0.0, 0.0, -1.0)).to_degrees();
+        assert!((angle - 22.5).abs() < 1e-3 && ray.direction.y > 0.0);
+    }
+
+    #[test]
+    fn reversed_and_orthographic_rays_point_away_from_camera() {
+        let mut camera = Camera::new(Point3::new(0.0, 0.0, 3.0), Projection::InfiniteReversed { fov_y_degrees: 45.0, near: 0.1 });
+        let ray = Ray::from_screen(&camera, Point2::new(400.0, 300.0), Vector2::new(800.0, 600.0)).unwrap();
+        assert!((ray.direction - Vector3::new(0.0, 0.0, -1.0)).norm() < 1e-4);
+
+        camera.projection = Projection::Orthographic { height: 4.0, near: 0.1, far: 100.0 };
+        let ray = Ray::from_screen(&camera, Point2::new(400.0, 0.0), Vector2::new(800.0, 600.0)).unwrap();
+        assert!((ray.direction - Vector3::new(0.0, 0.0, -1.0)).norm() < 1e-4);
+        assert!((ray.origin.y - 2.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn ray_hits_box_faces_and_misses_beside_it() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), -Vector3::z());
+        assert_eq!(ray.intersect_aabb(&unit_box_at(0.0, 0.0, 0.0)), Some(4.5));
+        assert_eq!(ray.intersect_aabb(&unit_box_at(0.0, 0.0, 10.0)), None); // Behind
+        assert_eq!(ray.intersect_aabb(&unit_box_at(1.1, 0.0, 0.0)), None);
+        assert_eq!(ray.intersect_aabb(&unit_box_at(0.0, 0.0, 5.0)), Some(0.0)); // Starts inside
+    }
+
+    #[test]
+    fn moller_trumbore_hits_inside_and_misses_outside() {
+        let (a, b, c) = (Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0));
+        let down = |x, y| Ray::new(Point3::new(x, y, 2.0), -Vector3::z());
+
+        assert_eq!(down(0.25, 0.25).intersect_triangle(&a, &b, &c), Some(2.0));
+        assert_eq!(down(0.75, 0.75).intersect_triangle(&a, &b, &c), None);
+        assert_eq!(down(-0.1, 0.5).intersect_triangle(&a, &b, &c), None);
+
+        let sideways = Ray::new(Point3::new(-1.0, 0.25, 0.0), Vector3::x());
+        assert_eq!(sideways.intersect_triangle(&a, &b, &c), None); // Parallel
+    }
+
+    #[test]
+    fn mesh_hit_uses_model_matrix() {
+        let points = cube_points();
+        let mesh = MeshRef { positions: &points, indices: &CUBE_INDICES };
+        let model = Matrix4::new_translation(&Vector3::new(0.0, 0.0, -2.0)) * Matrix4::new_scaling(2.0);
+
+        let ray = Ray::new(Point3::origin(), -Vector3::z());
+        let t = ray.intersect_mesh(&mesh, &model).unwrap();
+        assert!((t - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn raycast_returns_nearest_object() {
+        let points = cube_points();
+        let mesh = MeshRef { positions: &points, indices: &CUBE_INDICES };
+        let cube = |x: f32, z: f32| Pickable {
+            aabb: unit_box_at(x, 0.0, z),
+            mesh: Some((mesh, Matrix4::new_translation(&Vector3::new(x, 0.0, z)))),
+        };
+        let objects = [cube(0.0, -10.0), cube(0.0, -4.0), cube(3.0, -2.0)];
+
+        let ray = Ray::new(Point3::origin(), -Vector3::z());
+        let hit = raycast(&ray, &objects).unwrap();
+        assert_eq!(hit.object, 1);
+        assert!((hit.distance - 3.5).abs() < 1e-5);
+        assert!((hit.point - Point3::new(0.0, 0.0, -3.5)).norm() < 1e-5);
+
+        let miss = Ray::new(Point3::origin(), Vector3::y());
+        assert_eq!(raycast(&miss, &objects), None);
+    }
+
+    #[test]
+    fn id_buffer_hit_matches_ray_cast() {
+        let points = cube_points();
+        let mesh = MeshRef { positions: &points, indices: &CUBE_INDICES };
+        let objects = [Pickable { aabb: unit_box_at(0.0, 0.0, -4.0), mesh: Some((mesh, Matrix4::new_translation(&Vector3::new(0.0, 0.0, -4.0)))) }];
+
+        let projections = [
+            Projection::Perspective { fov_y_degrees: 60.0, near: 0.1, far: 100.0 },
+            Projection::InfiniteReversed { fov_y_degrees: 60.0, near: 0.1 },
+        ];
+        for projection in projections.iter() {
+            let camera = Camera::new(Point3::origin(), *projection);
+            let (pixel, viewport) = (Point2::new(420.0, 310.0), Vector2::new(800.0, 600.0));
+            let expected = raycast(&Ray::from_screen(&camera, pixel, viewport).unwrap(), &objects).unwrap();
+
+            // What the GPU would have written for that point
+            let clip = (camera.projection_matrix() * camera.view_matrix()).transform_point(&expected.point);
+            let depth = (clip.z + 1.0) / 2.0;
+
+            let hit = hit_from_id_buffer(1, depth, &camera, pixel, viewport).unwrap();
+            assert_eq!(hit.object, 0);
+            assert!((hit.distance - expected.distance).abs() < 1e-3, "{:?} != {:?}", hit, expected);
+            assert!((hit.point - expected.point).norm() < 1e-3);
+
+            assert_eq!(hit_from_id_buffer(0, depth, &camera, pixel, viewport), None);
+        }
+    }
+}
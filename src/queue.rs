@@ -0,0 +1,261 @@
+use gl33::*;
+use nalgebra::Matrix4;
+
+use crate::backend::Backend;
+use crate::renderer::RenderStats;
+
+// Passes run in this order, each into its own target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Pass {
+    Picking, // Object IDs for GPU picking
+    Main,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Blend {
+    Opaque,      // Front to back, so hidden pixels fail the depth test early
+    Transparent, // Back to front over the opaque ones, blended and without depth writes
+}
+
+//
+// ─── SORT KEY ───────────────────────────────────────────────────────────────────
+//
+
+const SHADER_BITS: u32 = 12;
+const MATERIAL_BITS: u32 = 12;
+const DEPTH_BITS: u32 = 24;
+
+// Everything that decides draw order packed into one integer, most important
+// first:
+//
+//   pass (3) | blend (1) | shader (12) | material (12) | depth (24)   opaque
+//   pass (3) | blend (1) | far depth (24) | shader (12) | material (12) transparent
+//
+// so opaque draws are grouped by state and transparent ones are strictly back
+// to front. Shader and material are GL names, only their low bits count, which
+// at worst splits a group.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SortKey(pub u64);
+
+impl SortKey {
+
+    // `depth` is the distance from the camera, anything not negative works.
+    pub fn new(pass: Pass, blend: Blend, shader: u32, material: u32, depth: f32) -> Self {
+        let shader = (shader & ((1 << SHADER_BITS) - 1)) as u64;
+        let material = (material & ((1 << MATERIAL_BITS) - 1)) as u64;
+        // Positive floats sort like their bits, the top 24 are plenty for ordering
+        let depth = (depth.max(0.0).to_bits() >> (31 - DEPTH_BITS)) as u64;
+
+        let state = shader << MATERIAL_BITS | material;
+        let order = match blend {
+            Blend::Opaque => state << DEPTH_BITS | depth,
+            Blend::Transparent => ((1 << DEPTH_BITS) - 1 - depth) << (SHADER_BITS + MATERIAL_BITS) | state,
+        };
+        SortKey((pass as u64) << 61 | ((blend == Blend::Transparent) as u64) << 60 | order)
+    }
+
+    pub fn pass(self) -> Pass {
+        match self.0 >> 61 {
+            0 => Pass::Picking,
+            _ => Pass::Main,
+        }
+    }
+
+    pub fn blend(self) -> Blend {
+        if self.0 >> 60 & 1 == 1 { Blend::Transparent } else { Blend::Opaque }
+    }
+}
+
+
+//
+// ─── QUEUE ──────────────────────────────────────────────────────────────────────
+//
+
+// One indexed draw. Uniforms every item of a shader shares, like the camera
+// transform, are set before the queue runs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DrawItem {
+    pub key: SortKey,
+    pub program: u32,
+    pub texture: u32,            // Bound to unit 0, 0 => leave whatever is bound
+    pub vertex_array: u32,
+    pub index_count: i32,
+    pub model: Matrix4<f32>,     // "model" uniform, where the mesh goes
+    pub object_id: Option<u32>,  // "object_id" uniform, for the picking pass
+}
+
+// Draws collected over a frame, then sorted and issued together.
+#[derive(Debug, Default)]
+pub struct RenderQueue {
+    items: Vec<DrawItem>,
+    sorted: bool,
+}
+
+impl RenderQueue {
+
+    pub fn new() -> Self {
+        RenderQueue::default()
+    }
+
+    pub fn submit(&mut self, item: DrawItem) {
+        self.items.push(item);
+        self.sorted = false;
+    }
+
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    // Empties the queue but keeps the allocation for the next frame.
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    // Stable, so equal keys draw in submission order.
+    pub fn sort(&mut self) {
+        if !self.sorted {
+            self.items.sort_by_key(|item| item.key);
+            self.sorted = true;
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn items(&mut self, pass: Pass) -> impl Iterator<Item = &DrawItem> {
+        self.sort();
+        self.items.iter().filter(move |item| item.key.pass() == pass)
+    }
+
+    // Issues `pass` in key order, only changing state between items that
+    // differ. Target, viewport and shared uniforms are up to the caller.
+    pub fn execute(&mut self, gl: &mut impl Backend, pass: Pass, stats: &mut RenderStats) {
+        let mut program = None;
+        let mut locations = (-1, -1); // Model and object ID of `program`
+        let mut texture = None;
+        let mut vertex_array = None;
+        let mut blend = Blend::Opaque;
+
+        self.sort();
+        for item in self.items.iter().filter(|item| item.key.pass() == pass) {
+            if item.key.blend() != blend {
+                blend = item.key.blend();
+                gl.enable(GL_BLEND);
+                gl.blend_func(GL_SRC_ALPHA, GL_ONE_MINUS_SRC_ALPHA);
+                gl.depth_mask(false);
+            }
+            if program != Some(item.program) {
+                program = Some(item.program);
+                gl.use_program(item.program);
+                locations = (gl.uniform_location(item.program, "model"), gl.uniform_location(item.program, "object_id"));
+            }
+            if item.texture != 0 && texture != Some(item.texture) {
+                texture = Some(item.texture);
+                gl.bind_texture(0, GL_TEXTURE_2D, item.texture);
+            }
+            if vertex_array != Some(item.vertex_array) {
+                vertex_array = Some(item.vertex_array);
+                gl.bind_vertex_array(item.vertex_array);
+            }
+
+            gl.uniform_mat4(locations.0, &item.model);
+            if let Some(id) = item.object_id {
+                gl.uniform_uint(locations.1, id);
+            }
+            gl.draw_elements(GL_TRIANGLES, item.index_count);
+            stats.draw(item.index_count);
+        }
+
+        // Transparent ones come last, so this is the only way back
+        if blend == Blend::Transparent {
+            gl.disable(GL_BLEND);
+            gl.depth_mask(true);
+        }
+    }
+}
+
+
+//
+// ─── TESTS ──────────────────────────────────────────────────────────────────────
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{Call, MockBackend};
+
+    fn item(pass: Pass, blend: Blend, program: u32, texture: u32, depth: f32) -> DrawItem {
+        DrawItem {
+            key: SortKey::new(pass, blend, program, texture, depth),
+            program,
+            texture,
+            vertex_array: 1,
+            index_count: 36,
+            model: Matrix4::new_translation(&nalgebra::Vector3::new(0.0, 0.0, -depth)),
+            object_id: None,
+        }
+    }
+
+    fn order(queue: &mut RenderQueue, pass: Pass) -> Vec<(u32, u32, f32)> {
+        queue.items(pass).map(|item| (item.program, item.texture, -item.model[(2, 3)])).collect()
+    }
+
+    #[test]
+    fn opaque_groups_by_state_then_front_to_back() {
+        let mut queue = RenderQueue::new();
+        queue.submit(item(Pass::Main, Blend::Opaque, 2, 5, 1.0));
+        queue.submit(item(Pass::Main, Blend::Opaque, 1, 6, 3.0));
+        queue.submit(item(Pass::Main, Blend::Opaque, 1, 5, 9.0));
+        queue.submit(item(Pass::Main, Blend::Opaque, 1, 5, 0.5));
+        queue.submit(item(Pass::Picking, Blend::Opaque, 3, 0, 2.0));
+
+        assert_eq!(order(&mut queue, Pass::Main), vec![(1, 5, 0.5), (1, 5, 9.0), (1, 6, 3.0), (2, 5, 1.0)]);
+        assert_eq!(order(&mut queue, Pass::Picking), vec![(3, 0, 2.0)]);
+        assert!(queue.items[0].key.pass() == Pass::Picking); // Passes come first of all
+    }
+
+    #[test]
+    fn transparent_goes_last_back_to_front() {
+        let mut queue = RenderQueue::new();
+        queue.submit(item(Pass::Main, Blend::Transparent, 1, 5, 2.0));
+        queue.submit(item(Pass::Main, Blend::Transparent, 2, 5, 8.0));
+        queue.submit(item(Pass::Main, Blend::Opaque, 3, 5, 20.0));
+        queue.submit(item(Pass::Main, Blend::Transparent, 1, 5, 100.0));
+
+        // Depth wins over shader, far ones first
+        assert_eq!(order(&mut queue, Pass::Main), vec![(3, 5, 20.0), (1, 5, 100.0), (2, 5, 8.0), (1, 5, 2.0)]);
+    }
+
+    #[test]
+    fn execute_only_changes_what_differs() {
+        let mut queue = RenderQueue::new();
+        queue.submit(item(Pass::Main, Blend::Opaque, 1, 5, 2.0));
+        queue.submit(item(Pass::Main, Blend::Transparent, 1, 5, 4.0));
+        queue.submit(item(Pass::Main, Blend::Opaque, 1, 5, 1.0));
+        queue.submit(item(Pass::Picking, Blend::Opaque, 2, 0, 1.0));
+
+        let mut gl = MockBackend::new();
+        let mut stats = RenderStats::default();
+        queue.execute(&mut gl, Pass::Main, &mut stats);
+        assert_eq!(stats.draw_calls, 3);
+        assert_eq!(stats.triangles, 36);
+
+        let state: Vec<Call> = gl.take_calls().into_iter()
+            .filter(|call| !matches!(call, Call::UniformMat4 { .. } | Call::UniformLocation { .. } | Call::DrawElements { .. }))
+            .collect();
+        assert_eq!(state, vec![
+            Call::UseProgram(1),
+            Call::BindTexture { unit: 0, target: GL_TEXTURE_2D.0, texture: 5 },
+            Call::BindVertexArray(1),
+            Call::Enable(GL_BLEND.0),
+            Call::BlendFunc { source: GL_SRC_ALPHA.0, destination: GL_ONE_MINUS_SRC_ALPHA.0 },
+            Call::DepthMask(false),
+            Call::Disable(GL_BLEND.0),
+            Call::DepthMask(true),
+        ]);
+    }
+}
@@ -0,0 +1,159 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::App;
+use crate::config::{self, ConfigError};
+use crate::input::InputEvent;
+
+// Input event with the simulation tick it arrived before, counted from 0.
+// Ticks rather than seconds, so a replay hands every tick exactly the input
+// it had live however the frames happened to fall.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TimedEvent {
+    pub tick: u64,
+    pub event: InputEvent,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    pub events: Vec<TimedEvent>,
+}
+
+impl Recording {
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        config::load(path)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        config::save(self, path)
+    }
+}
+
+// Stamps live events with the tick they will be handled in.
+#[derive(Default)]
+pub struct Recorder {
+    pub recording: Recording,
+}
+
+impl Recorder {
+
+    pub fn new() -> Self {
+        Recorder::default()
+    }
+
+    // `tick` is how many ticks have run so far, `FixedTimestep::ticks`.
+    pub fn record(&mut self, tick: u64, event: InputEvent) {
+        self.recording.events.push(TimedEvent { tick, event });
+    }
+}
+
+// Plays a recording back tick by tick. Every tick gets the events that
+// arrived before it, so the same recording always ends up in the same state
+// no matter how fast the machine renders.
+pub struct Replay {
+    recording: Recording,
+    next: usize,
+    tick: u64,
+    timestep: f32,
+}
+
+impl Replay {
+
+    pub fn new(recording: Recording, timestep: f32) -> Self {
+        Replay { recording, next: 0, tick: 0, timestep }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.recording.events.len()
+    }
+
+    // Returns the events the next tick handles and moves on to the one after.
+    pub fn next_frame(&mut self) -> Vec<InputEvent> {
+        let events = &self.recording.events[self.next..];
+        let count = events.iter().take_while(|e| e.tick <= self.tick).count();
+        self.next += count;
+        self.tick += 1;
+        events[..count].iter().map(|e| e.event).collect()
+    }
+
+    // Feeds one tick into the app and updates it, same order as the live loop.
+    pub fn step(&mut self, app: &mut App) {
+        for event in self.next_frame() {
+            app.handle_input(&event);
+        }
+        app.update(self.timestep);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Bindings;
+    use crate::scene::Scene;
+    use glutin::event::{ElementState, VirtualKeyCode};
+
+    fn session() -> Recording {
+        let events = vec![
+            (0, InputEvent::Key(VirtualKeyCode::W, ElementState::Pressed)),
+            (6, InputEvent::MouseMotion(40.0, -12.0)),
+            (15, InputEvent::MouseMotion(-3.0, 7.5)),
+            (24, InputEvent::Key(VirtualKeyCode::D, ElementState::Pressed)),
+            (42, InputEvent::Key(VirtualKeyCode::W, ElementState::Released)),
+            (54, InputEvent::MouseWheel(1.0)),
+            (60, InputEvent::Key(VirtualKeyCode::D, ElementState::Released)),
+        ];
+        Recording {
+            events: events.into_iter().map(|(tick, event)| TimedEvent { tick, event }).collect(),
+        }
+    }
+
+    fn replay(recording: &Recording) -> App {
+        let mut app = App::new(800, 600, Bindings::default(), &Scene::default().camera);
+        let mut replay = Replay::new(recording.clone(), 1.0 / 60.0);
+        while !replay.is_finished() {
+            replay.step(&mut app);
+        }
+        app
+    }
+
+    #[test]
+    fn recording_survives_save_and_load() {
+        let path = std::env::temp_dir().join(format!("recording_round_trip_{}.ron", std::process::id()));
+        let recording = session();
+        recording.save(&path).unwrap();
+        assert_eq!(Recording::load(&path).unwrap(), recording);
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn ticks_get_the_events_recorded_before_them() {
+        let mut replay = Replay::new(session(), 1.0 / 60.0);
+        assert_eq!(replay.next_frame().len(), 1); // 0
+        for _ in 1..6 {
+            assert!(replay.next_frame().is_empty());
+        }
+        assert_eq!(replay.next_frame().len(), 1); // 6
+        for _ in 7..60 {
+            replay.next_frame();
+        }
+        assert!(!replay.is_finished());
+        assert_eq!(replay.next_frame().len(), 1); // 60
+        assert!(replay.is_finished());
+    }
+
+    #[test]
+    fn replay_is_deterministic() {
+        let start = App::new(800, 600, Bindings::default(), &Scene::default().camera).camera;
+        let first = replay(&session());
+        let second = replay(&session());
+
+        assert_eq!(first.camera.position, second.camera.position);
+        assert_eq!(first.camera.orientation, second.camera.orientation);
+
+        // And it actually went somewhere
+        assert!((first.camera.position - start.position).norm() > 0.5);
+        assert!(first.camera.orientation.angle_to(&start.orientation) > 0.01);
+    }
+}
@@ -0,0 +1,544 @@
+use gl33::*;
+
+use nalgebra::{Matrix4, Point2, Point3, Vector2};
+
+use crate::app::App;
+use crate::backend::{Backend, Gl33};
+use crate::bounds::{Bounds, Frustum};
+use crate::camera::Camera;
+use crate::framebuffer::Framebuffer;
+use crate::graph::{NodeId, SceneGraph, Trs};
+use crate::image::Image;
+use crate::mesh::Mesh;
+use crate::picking::{self, MeshRef, Pickable, Ray};
+use crate::queue::{Blend, DrawItem, Pass, RenderQueue, SortKey};
+use crate::scene::{MeshSource, Scene};
+use crate::shader::Shader;
+use crate::state::StateCache;
+use crate::texture::{Filter, Sampler, Texture2D, TextureFormat, Wrap};
+
+// What the last frame drew.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RenderStats {
+    pub drawn: u32,
+    pub culled: u32,
+    pub draw_calls: u32,    // Including the picking pass
+    pub triangles: u32,
+    pub state_changes: u32, // Binds, uniform uploads and other GL state calls
+    pub redundant_state: u32, // State calls the cache skipped since nothing would change
+}
+
+impl RenderStats {
+    pub fn draw(&mut self, index_count: i32) {
+        self.draw_calls += 1;
+        self.triangles += index_count as u32 / 3;
+    }
+}
+
+// Mesh on the GPU plus what culling and picking need on the CPU.
+struct SceneMesh {
+    gpu: Mesh,
+    points: Vec<Point3<f32>>, // Every drawn instance is these moved into place
+    indices: Vec<u32>,
+    bounds: Bounds,
+}
+
+struct Material {
+    texture: u32, // White when the scene gives none
+    blend: Blend,
+}
+
+// Object from the scene with its names looked up.
+struct Instance {
+    node: NodeId, // World matrix goes into the "model" uniform
+    mesh: usize,
+    material: usize,
+}
+
+// Everything on the GPU side of the scene. Draws whatever App says into
+// the window or an offscreen framebuffer, so the windowed and headless modes
+// share all of it. Every GL call goes through `B`, tests use a mock.
+pub struct Renderer<B: Backend> {
+    gl: StateCache<B>,
+    meshes: Vec<SceneMesh>,
+    textures: Vec<Texture2D>, // Only owned here, materials use their IDs
+    materials: Vec<Material>,
+    objects: Vec<Instance>,
+    pub graph: SceneGraph, // Where objects are, move their nodes to move them
+    shader_program: Shader,
+    id_shader: Shader,                // Writes object IDs instead of colors, for GPU picking
+    id_buffer: Option<Framebuffer>,   // Made on first pick, follows viewport size
+    pub gpu_picking: bool,            // Pick through the ID buffer instead of ray casts
+    queue: RenderQueue,               // This frame's draws, kept for its allocation
+}
+
+impl Renderer<Gl33> {
+
+    // Needs a current OpenGL context with functions loaded.
+    pub fn new(scene: &Scene) -> Self {
+        Renderer::with_backend(Gl33, scene)
+    }
+}
+
+impl<B: Backend> Renderer<B> {
+
+    pub fn with_backend(backend: B, scene: &Scene) -> Self {
+        let mut gl = StateCache::new(backend);
+
+        //
+        // ─── MESHES ──────────────────────────────────────────────────────
+        //
+
+        let meshes: Vec<SceneMesh> = scene.meshes.iter()
+            .map(|desc| {
+                let mesh = build_mesh(&mut gl, desc.source);
+                mesh.gpu.set_label(&mut gl, &desc.name);
+                mesh
+            })
+            .collect();
+
+
+        //
+        // ─── TEXTURES ────────────────────────────────────────────────────
+        //
+
+        let sampler = Sampler {
+            min_filter: Filter::Nearest,
+            mag_filter: Filter::Nearest,
+            mipmap_filter: None,
+            wrap: [Wrap::Repeat; 3],
+            border_color: [ 1.0, 0.0, 0.0, 1.0 ],
+        };
+
+        let mut textures: Vec<Texture2D> = scene.textures.iter()
+            .map(|desc| {
+                let path = scene.texture_path(desc);
+                let image = Image::open(&path)
+                    .unwrap_or_else(|e| panic!("Could not load texture {}: {} (JPEG again?)", path.display(), e));
+                let texture = Texture2D::from_image(&mut gl, &image, &sampler);
+                texture.set_label(&mut gl, &desc.name);
+                texture
+            })
+            .collect();
+
+        // Untextured materials sample this, 0 would keep whatever the previous draw bound
+        let white = Texture2D::new(&mut gl, 1, 1, TextureFormat::Rgba8, Some(&[255; 4]), &Sampler::nearest());
+        white.set_label(&mut gl, "white");
+        let white_id = white.id;
+
+
+        //
+        // ─── MATERIALS AND OBJECTS ───────────────────────────────────────
+        //
+
+        // Scene is validated, every name is there
+        let materials: Vec<Material> = scene.materials.iter()
+            .map(|desc| Material {
+                texture: desc.texture.as_ref()
+                    .map_or(white_id, |name| textures[scene.texture_index(name).unwrap()].id),
+                blend: if desc.transparent { Blend::Transparent } else { Blend::Opaque },
+            })
+            .collect();
+        textures.push(white); // Deleted with the rest
+
+        // One node per object, parents can come later in the list so they are hooked up after
+        let mut graph = SceneGraph::new();
+        let objects: Vec<Instance> = scene.objects.iter()
+            .map(|object| Instance {
+                node: graph.add(&object.name, Trs::from(&object.transform), None),
+                mesh: scene.mesh_index(&object.mesh).unwrap(),
+                material: scene.material_index(&object.material).unwrap(),
+            })
+            .collect();
+        for (instance, object) in objects.iter().zip(&scene.objects) {
+            if let Some(parent) = &object.parent {
+                let parent = objects[scene.object_index(parent).unwrap()].node;
+                graph.set_parent(instance.node, Some(parent)).unwrap();
+            }
+        }
+
+
+        //
+        // ─── SHADERS ─────────────────────────────────────────────────────
+        //
+
+        // Shader program
+        let shader_program = Shader::from_source(
+            &mut gl,
+            include_bytes!("shaders/vertex.vert"),
+            include_bytes!("shaders/fragment.frag")
+        );
+        shader_program.set_label(&mut gl, "cube");
+
+        // Writes object IDs instead of colors, for GPU picking
+        let id_shader = Shader::from_source(
+            &mut gl,
+            include_bytes!("shaders/vertex.vert"),
+            include_bytes!("shaders/id.frag")
+        );
+        id_shader.set_label(&mut gl, "object IDs");
+
+
+        //
+        // ─── ETC ─────────────────────────────────────────────────────────
+        //
+
+        // Set background color
+        gl.clear_color([0.275, 0.51, 0.706, 1.0]);
+
+        // Set polygon mode
+        gl.polygon_mode(GL_FILL); // Filled
+
+        Renderer {
+            gl,
+            meshes,
+            textures,
+            materials,
+            objects,
+            graph,
+            shader_program,
+            id_shader,
+            id_buffer: None,
+            gpu_picking: false,
+            queue: RenderQueue::new(),
+        }
+    }
+
+    // The renderer's own backend, for drawing and reading targets without
+    // going behind its state cache.
+    pub fn gl(&mut self) -> &mut StateCache<B> {
+        &mut self.gl
+    }
+
+    // Draws one frame of `app` into `target`, or the window when None. Also
+    // answers `app.pick` by setting `app.selected`.
+    pub fn render(&mut self, app: &mut App, target: Option<&Framebuffer>) -> RenderStats {
+        let (width, height) = match target {
+            Some(framebuffer) => (framebuffer.width, framebuffer.height),
+            None => (app.viewport.x as u32, app.viewport.y as u32),
+        };
+
+        // Between the last two simulation ticks, picking goes by what is on screen too
+        let camera = app.view_camera();
+        let mut stats = RenderStats::default();
+        self.gl.take_counters(); // Only this frame's calls
+
+        // Depth setup has to be right for the picking pass as well
+        self.gl.enable(GL_DEPTH_TEST);
+        if camera.projection.reversed_z() { // Near is 1, infinity is 0
+            self.gl.clear_depth(0.0);
+            self.gl.depth_func(GL_GREATER);
+        } else {
+            self.gl.clear_depth(1.0);
+            self.gl.depth_func(GL_LESS);
+        }
+
+
+        //
+        // TRANSFORMATION
+        //
+
+        // Objects moved since the last frame get their world matrices redone,
+        // those go into the "model" uniform one by one
+        self.graph.update();
+        let view = camera.view_matrix();
+        let projection = camera.projection_matrix();
+        let final_transformation = projection * view;
+
+
+        //
+        // QUEUE
+        //
+
+        self.queue.clear();
+        if app.pick.is_some() && self.gpu_picking {
+            for (i, object) in self.objects.iter().enumerate() {
+                let mesh = &self.meshes[object.mesh].gpu;
+                let world = *self.graph.world(object.node);
+                let depth = (camera.position - world.transform_point(&Point3::origin())).norm();
+                self.queue.submit(DrawItem {
+                    key: SortKey::new(Pass::Picking, Blend::Opaque, self.id_shader.id, 0, depth),
+                    program: self.id_shader.id,
+                    texture: 0,
+                    vertex_array: mesh.vertex_array,
+                    index_count: mesh.index_count,
+                    model: world,
+                    object_id: Some(i as u32 + 1),
+                });
+            }
+        }
+
+        // Skip objects the camera can't see
+        let frustum = Frustum::from_matrix(&(projection * view));
+
+        for object in self.objects.iter() {
+            let mesh = &self.meshes[object.mesh];
+            let material = &self.materials[object.material];
+            let world = *self.graph.world(object.node);
+            if !frustum.intersects(&mesh.bounds.transformed(&world)) {
+                stats.culled += 1;
+                continue;
+            }
+            stats.drawn += 1;
+
+            let depth = (camera.position - world.transform_point(&Point3::origin())).norm();
+            self.queue.submit(DrawItem {
+                key: SortKey::new(Pass::Main, material.blend, self.shader_program.id, material.texture, depth),
+                program: self.shader_program.id,
+                texture: material.texture,
+                vertex_array: mesh.gpu.vertex_array,
+                index_count: mesh.gpu.index_count,
+                model: world,
+                object_id: None,
+            });
+        }
+        self.queue.sort();
+
+
+        //
+        // PICKING
+        //
+
+        match app.pick {
+            Some(pixel) if self.gpu_picking => {
+                app.selected = self.pick_gpu(&camera, app.viewport, pixel, &final_transformation, &mut stats);
+            }
+            Some(pixel) => {
+                let (meshes, graph) = (&self.meshes, &mut self.graph);
+                let objects: Vec<Pickable> = self.objects.iter()
+                    .map(move |object| {
+                        let mesh = &meshes[object.mesh];
+                        let world = *graph.world(object.node);
+                        let mesh_ref = MeshRef { positions: &mesh.points, indices: &mesh.indices };
+                        Pickable { aabb: mesh.bounds.aabb.transformed(&world), mesh: Some((mesh_ref, world)) }
+                    })
+                    .collect();
+
+                app.selected = Ray::from_screen(&camera, pixel, app.viewport)
+                    .and_then(|ray| picking::raycast(&ray, &objects));
+            }
+            None => (),
+        }
+
+
+        //
+        // CLEAR
+        //
+
+        match target {
+            Some(framebuffer) => framebuffer.bind(&mut self.gl),
+            None => {
+                self.gl.bind_framebuffer(0);
+                self.gl.viewport(0, 0, width as i32, height as i32);
+            }
+        }
+        self.gl.clear(GL_COLOR_BUFFER_BIT | GL_DEPTH_BUFFER_BIT);
+
+        self.gl.use_program(self.shader_program.id);
+        self.gl.polygon_mode(if app.wireframe { GL_LINE } else { GL_FILL }); // Picking always fills
+
+
+        //
+        // TEXTURES
+        //
+
+        // Materials bind their own, always to unit 0
+        let texture_location = self.gl.uniform_location(self.shader_program.id, "texture1");
+        self.gl.uniform_int(texture_location, 0);
+
+        let transform_location = self.gl.uniform_location(self.shader_program.id, "transform");
+        if transform_location == -1 { panic!("Transform uniform not found!") }
+        self.gl.uniform_mat4(transform_location, &final_transformation);
+
+
+        //
+        // DRAWING
+        //
+
+        let model_location = self.gl.uniform_location(self.shader_program.id, "model");
+        if model_location == -1 { panic!("Model uniform not found!") }
+
+        self.queue.execute(&mut self.gl, Pass::Main, &mut stats);
+
+        let counters = self.gl.take_counters();
+        stats.state_changes = counters.issued;
+        stats.redundant_state = counters.skipped;
+        stats
+    }
+
+    // Draws the picking pass, every cube filled with its index + 1, and reads
+    // back the one under `pixel`.
+    fn pick_gpu(&mut self, camera: &Camera, viewport: Vector2<f32>, pixel: Point2<f32>, final_transformation: &Matrix4<f32>, stats: &mut RenderStats) -> Option<picking::Hit> {
+        let (width, height) = (viewport.x as u32, viewport.y as u32);
+        if width == 0 || height == 0 {
+            return None; // Minimized, nothing to pick and no pixel to read
+        }
+        let gl = &mut self.gl;
+        let buffer = self.id_buffer.get_or_insert_with(|| {
+            let mut buffer = Framebuffer::new(gl, width, height, TextureFormat::R32Ui);
+            buffer.set_label(gl, "object IDs");
+            buffer
+        });
+        buffer.resize(gl, width, height);
+
+        buffer.bind(gl);
+        buffer.clear_uint(gl, 0);
+        gl.clear(GL_DEPTH_BUFFER_BIT);
+        gl.polygon_mode(GL_FILL); // Wireframe would only pick edges
+
+        gl.use_program(self.id_shader.id);
+        let transform_location = gl.uniform_location(self.id_shader.id, "transform");
+        gl.uniform_mat4(transform_location, final_transformation);
+        self.queue.execute(gl, Pass::Picking, stats);
+
+        // Window pixels count from the top, OpenGL ones from the bottom
+        let x = (pixel.x as u32).min(width - 1);
+        let y = height - 1 - (pixel.y as u32).min(height - 1);
+        let id = buffer.read_uint(gl, x, y);
+        let depth = buffer.read_depth(gl, x, y);
+
+        let center = Point2::new(x as f32 + 0.5, (height - 1 - y) as f32 + 0.5);
+        picking::hit_from_id_buffer(id, depth, camera, center, viewport)
+    }
+}
+
+// Uploads one of the built-in meshes.
+fn build_mesh(gl: &mut impl Backend, source: MeshSource) -> SceneMesh {
+    match source {
+        MeshSource::Cube => {
+            //
+            // ─── VERTEX INPUT ────────────────────────────────────────────────
+            //
+
+            type Vertex = [f32; 8];
+
+            let vertices: [Vertex; 8] = [
+                [ -0.5,  0.5,  0.5,  1.0, 1.0, 1.0,  1.0, 0.0  ],
+                [  0.5,  0.5,  0.5,  1.0, 1.0, 1.0,  1.0, 0.0  ],
+                [ -0.5, -0.5,  0.5,  1.0, 1.0, 1.0,  1.0, 0.0  ],
+                [  0.5, -0.5,  0.5,  1.0, 1.0, 1.0,  1.0, 0.0  ],
+
+                [ -0.5,  0.5, -0.5,  1.0, 1.0, 0.0,  1.0, 0.0  ],
+                [  0.5,  0.5, -0.5,  1.0, 1.0, 0.0,  1.0, 0.0  ],
+                [ -0.5, -0.5, -0.5,  1.0, 1.0, 0.0,  1.0, 0.0  ],
+                [  0.5, -0.5, -0.5,  1.0, 1.0, 0.0,  1.0, 0.0  ],
+            ];
+
+            let indices: [u32; 36] = [
+                0, 2, 3,
+                0, 1, 3,
+
+                4, 5, 7,
+                4, 6, 7,
+
+                0, 4, 5,
+                0, 1, 5,
+
+                2, 6, 7,
+                2, 3, 7,
+
+                0, 4, 6,
+                0, 2, 6,
+
+                1, 5, 7,
+                1, 3, 7
+            ];
+
+            let points: Vec<Point3<f32>> = vertices.iter().map(|v| Point3::new(v[0], v[1], v[2])).collect();
+            let bounds = Bounds::from_points(&points);
+
+            //
+            // ─── VERTEX BUFFERS ──────────────────────────────────────────────
+            //
+
+            // Position, color and texture coordinates, interleaved
+            //     0     3     6   8
+            //     |-----|-----|---|
+            //       pos  color texture
+            let gpu = Mesh::upload(gl, &vertices.concat(), &[3, 3, 2], &indices);
+
+            SceneMesh { gpu, points, indices: indices.to_vec(), bounds }
+        }
+    }
+}
+
+impl<B: Backend> Drop for Renderer<B> {
+    fn drop(&mut self) {
+        for mesh in &self.meshes {
+            mesh.gpu.delete(&mut self.gl);
+        }
+        for texture in &self.textures {
+            texture.delete(&mut self.gl);
+        }
+        self.shader_program.delete(&mut self.gl);
+        self.id_shader.delete(&mut self.gl);
+        if let Some(buffer) = &self.id_buffer {
+            buffer.delete(&mut self.gl);
+        }
+    }
+}
+
+
+//
+// ─── TESTS ──────────────────────────────────────────────────────────────────────
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{UnitQuaternion, Vector3};
+    use crate::backend::{Call, MockBackend};
+    use crate::input::{Bindings, InputEvent};
+
+    fn draws(renderer: &mut Renderer<MockBackend>) -> usize {
+        renderer.gl().inner.take_calls().iter().filter(|call| matches!(call, Call::DrawElements { .. })).count()
+    }
+
+    #[test]
+    fn culled_cubes_are_not_drawn() {
+        let scene = Scene::default();
+        let mut renderer = Renderer::with_backend(MockBackend::new(), &scene);
+        let mut app = App::new(800, 600, Bindings::default(), &scene.camera);
+        renderer.gl().inner.take_calls(); // Only what frames do
+
+        let stats = renderer.render(&mut app, None);
+        assert_eq!((stats.drawn as usize, stats.culled), (scene.objects.len(), 0));
+        assert_eq!(draws(&mut renderer), scene.objects.len());
+
+        // Turned around every cube is behind the camera
+        let turn = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), std::f32::consts::PI);
+        app.camera.orientation = turn * app.camera.orientation;
+        app.previous_camera = app.camera.clone();
+        let stats = renderer.render(&mut app, None);
+        assert_eq!((stats.drawn, stats.culled as usize), (0, scene.objects.len()));
+        assert_eq!(draws(&mut renderer), 0);
+    }
+
+    #[test]
+    fn labels_go_through_the_backend() {
+        let scene = Scene::default();
+        let mut renderer = Renderer::with_backend(MockBackend::new(), &scene);
+        let labels: Vec<(u32, String)> = renderer.gl().inner.take_calls().into_iter()
+            .filter_map(|call| match call {
+                Call::Label { kind, name, .. } => Some((kind, name)),
+                _ => None,
+            })
+            .collect();
+        for (kind, name) in [(GL_VERTEX_ARRAY, "cube"), (GL_BUFFER, "cube indices"), (GL_TEXTURE, "car"), (GL_TEXTURE, "white"), (GL_PROGRAM, "object IDs")] {
+            assert!(labels.contains(&(kind.0, name.to_string())), "{} is not labeled", name);
+        }
+    }
+
+    #[test]
+    fn minimized_windows_pick_nothing() {
+        let scene = Scene::default();
+        let mut renderer = Renderer::with_backend(MockBackend::new(), &scene);
+        renderer.gpu_picking = true;
+        let mut app = App::new(800, 600, Bindings::default(), &scene.camera);
+        app.handle_input(&InputEvent::Resized(0, 0));
+        app.pick = Some(Point2::origin());
+        renderer.render(&mut app, None);
+        assert_eq!(app.selected, None);
+        assert!(!renderer.gl().inner.calls.iter().any(|call| matches!(call, Call::CreateFramebuffer { .. })));
+    }
+}
@@ -0,0 +1,462 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use nalgebra::Point3;
+use ron::error::Position;
+use serde::{Deserialize, Serialize};
+
+use crate::camera::Projection;
+use crate::config::{self, ConfigError};
+use crate::image::Image;
+
+//
+// ─── FORMAT ─────────────────────────────────────────────────────────────────────
+//
+
+// Where the camera starts. Yaw and pitch are what the fly controller takes,
+// so looking around continues from there.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SceneCamera {
+    pub position: [f32; 3],
+    #[serde(default)]
+    pub yaw: f32,   // Degrees, 0 => looking down -Z
+    #[serde(default)]
+    pub pitch: f32, // Degrees, positive => up
+    pub projection: Projection,
+}
+
+// Meshes the renderer knows how to build.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MeshSource {
+    Cube, // Unit cube around the origin
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MeshDesc {
+    pub name: String,
+    pub source: MeshSource,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TextureDesc {
+    pub name: String,
+    pub path: String, // PNG, relative to the scene file
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MaterialDesc {
+    pub name: String,
+    #[serde(default)]
+    pub texture: Option<String>,
+    #[serde(default)]
+    pub transparent: bool, // Drawn after opaque things, back to front and blended
+}
+
+// Not lit yet, the shaders ignore these.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Light {
+    Directional { direction: [f32; 3], color: [f32; 3], intensity: f32 },
+    Point { position: [f32; 3], color: [f32; 3], intensity: f32, range: f32 },
+}
+
+// Translation, rotation and scale relative to the parent, applied scale
+// first. Rotation is a quaternion written x, y, z, w.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transform {
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform { translation: [0.0; 3], rotation: [0.0, 0.0, 0.0, 1.0], scale: [1.0; 3] }
+    }
+}
+
+impl Transform {
+
+    pub fn at(x: f32, y: f32, z: f32) -> Self {
+        Transform { translation: [x, y, z], ..Transform::default() }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Object {
+    pub name: String,
+    pub mesh: String,
+    pub material: String,
+    #[serde(default)]
+    pub transform: Transform,
+    #[serde(default)]
+    pub parent: Option<String>, // Another object this one moves with
+}
+
+// Everything that is drawn and where from, see scene.ron. Meshes, textures
+// and materials are referred to by name, `load` checks that they exist.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Scene {
+    pub camera: SceneCamera,
+    #[serde(default)]
+    pub meshes: Vec<MeshDesc>,
+    #[serde(default)]
+    pub textures: Vec<TextureDesc>,
+    #[serde(default)]
+    pub materials: Vec<MaterialDesc>,
+    #[serde(default)]
+    pub lights: Vec<Light>,
+    #[serde(default)]
+    pub objects: Vec<Object>,
+    #[serde(skip)]
+    pub directory: PathBuf, // Texture paths start here
+}
+
+// The cubes this started with, same as scene.ron.
+impl Default for Scene {
+    fn default() -> Self {
+        let locations = [
+            ( 0.0,  0.0,  0.0),
+            ( 2.0,  5.0, -15.0),
+            (-1.5, -2.2, -2.5),
+            (-3.8, -2.0, -12.3),
+            ( 2.4, -0.4, -3.5),
+            (-1.7,  3.0, -7.5),
+            ( 1.3, -2.0, -2.5),
+            ( 1.5,  2.0, -2.5),
+            ( 1.5,  0.2, -1.5),
+            (-1.3,  1.0, -1.5),
+        ];
+
+        Scene {
+            camera: SceneCamera {
+                position: [0.0, 0.0, 3.0],
+                yaw: 0.0,
+                pitch: 0.0,
+                projection: Projection::Perspective { fov_y_degrees: 45.0, near: 0.1, far: 100.0 },
+            },
+            meshes: vec![MeshDesc { name: "cube".into(), source: MeshSource::Cube }],
+            textures: vec![TextureDesc { name: "car".into(), path: "images/car.png".into() }],
+            materials: vec![MaterialDesc { name: "crate".into(), texture: Some("car".into()), transparent: false }],
+            lights: vec![Light::Directional { direction: [-0.2, -1.0, -0.3], color: [1.0; 3], intensity: 1.0 }],
+            objects: locations.iter().enumerate()
+                .map(|(i, &(x, y, z))| Object {
+                    name: format!("cube {}", i),
+                    mesh: "cube".into(),
+                    material: "crate".into(),
+                    transform: Transform::at(x, y, z),
+                    parent: None,
+                })
+                .collect(),
+            directory: config::source_path(""),
+        }
+    }
+}
+
+
+//
+// ─── ERRORS ─────────────────────────────────────────────────────────────────────
+//
+
+#[derive(Debug)]
+pub enum SceneError {
+    Load(ConfigError), // Unreadable, or not RON of the right shape
+    Invalid { message: String, position: Option<Position> }, // Parsed, but doesn't make sense
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Load(e) => write!(f, "{}", e),
+            SceneError::Invalid { message, position: Some(position) } => write!(f, "{}: {}", position, message),
+            SceneError::Invalid { message, position: None } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+// What is wrong and the text to point at, the `nth` match of `needle` in
+// the `section` of the file.
+struct Problem {
+    message: String,
+    section: &'static str,
+    needle: String,
+    nth: usize,
+}
+
+impl Problem {
+    fn new(message: String, section: &'static str, name: &str) -> Self {
+        // Nameless problems point at the section itself
+        let needle = if name.is_empty() { section.to_string() } else { format!("{:?}", name) };
+        Problem { message, section, needle, nth: 0 }
+    }
+
+    // Serde forgets where values came from, so find them again in the text.
+    // Good enough for names, which is what validation complains about.
+    fn locate(&self, text: &str) -> Option<Position> {
+        let start = find_key(text, self.section)?;
+        let offset = start + text[start..].match_indices(&self.needle).nth(self.nth)?.0;
+        let before = &text[..offset];
+        let line = before.matches('\n').count() + 1;
+        let col = before.len() - before.rfind('\n').map_or(0, |newline| newline + 1) + 1;
+        Some(Position { line, col })
+    }
+
+    fn into_error(self, text: Option<&str>) -> SceneError {
+        let position = text.and_then(|text| self.locate(text));
+        SceneError::Invalid { message: self.message, position }
+    }
+}
+
+// Where `key:` starts, skipping comments and longer names that end in `key`.
+fn find_key(text: &str, key: &str) -> Option<usize> {
+    text.match_indices(key).map(|(start, _)| start).find(|&start| {
+        let line_start = text[..start].rfind('\n').map_or(0, |newline| newline + 1);
+        let in_comment = text[line_start..start].contains("//");
+        let is_word = !text[..start].ends_with(|c: char| c.is_alphanumeric() || c == '_');
+        !in_comment && is_word && text[start + key.len()..].trim_start().starts_with(':')
+    })
+}
+
+// Names in one list, complaining about the second of a pair.
+fn unique<'a>(section: &'static str, kind: &str, names: impl Iterator<Item = &'a String>) -> Result<HashSet<&'a str>, Problem> {
+    let mut seen = HashSet::new();
+    for name in names {
+        if !seen.insert(name.as_str()) {
+            let mut problem = Problem::new(format!("{} \"{}\" is defined twice", kind, name), section, name);
+            problem.nth = 1;
+            return Err(problem);
+        }
+    }
+    Ok(seen)
+}
+
+fn defined(names: &HashSet<&str>, section: &'static str, kind: &str, name: &str) -> Result<(), Problem> {
+    if names.contains(name) {
+        Ok(())
+    } else {
+        Err(Problem::new(format!("{} \"{}\" is not defined", kind, name), section, name))
+    }
+}
+
+
+//
+// ─── LOADING ────────────────────────────────────────────────────────────────────
+//
+
+impl Scene {
+
+    // Parses and validates, errors say where in the file the problem is.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|e| SceneError::Load(ConfigError::Io(e)))?;
+        let mut scene = Scene::from_str(&text)?;
+        scene.directory = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        scene.check_files().map_err(|problem| problem.into_error(Some(&text)))?;
+        Ok(scene)
+    }
+
+    // Defaults when the file doesn't exist.
+    pub fn load_or_default(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let path = path.as_ref();
+        match Scene::load(path) {
+            Err(SceneError::Load(ConfigError::Io(e))) if e.kind() == std::io::ErrorKind::NotFound => {
+                log::warn!("{} not found, using the built-in scene", path.display());
+                Ok(Scene::default())
+            }
+            result => result,
+        }
+    }
+
+    // Texture paths are relative to the working directory.
+    pub fn from_str(text: &str) -> Result<Self, SceneError> {
+        let scene: Scene = ron::de::from_str(text).map_err(|e| SceneError::Load(ConfigError::Parse(e)))?;
+        scene.check().map_err(|problem| problem.into_error(Some(text)))?;
+        Ok(scene)
+    }
+
+    #[allow(dead_code)]
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        config::save(self, path)
+    }
+
+    #[allow(dead_code)]
+    pub fn validate(&self) -> Result<(), SceneError> {
+        self.check().map_err(|problem| problem.into_error(None))
+    }
+
+    // Texture files have to be PNGs, the renderer has no way to report a bad one.
+    fn check_files(&self) -> Result<(), Problem> {
+        for texture in &self.textures {
+            let path = self.texture_path(texture);
+            if let Err(e) = Image::read_size(&path) {
+                let message = format!("Texture \"{}\" can't be loaded from {}: {}", texture.name, path.display(), e);
+                return Err(Problem::new(message, "textures", &texture.path));
+            }
+        }
+        Ok(())
+    }
+
+    fn check(&self) -> Result<(), Problem> {
+        match self.camera.projection {
+            Projection::Perspective { near, far, .. } | Projection::Orthographic { near, far, .. } if near >= far =>
+                return Err(Problem::new(format!("Camera near plane {} is not before far plane {}", near, far), "camera", "")),
+            Projection::Perspective { near, .. } | Projection::InfiniteReversed { near, .. } if near <= 0.0 =>
+                return Err(Problem::new(format!("Camera near plane must be positive, got {}", near), "camera", "")),
+            _ => (),
+        }
+
+        let meshes = unique("meshes", "Mesh", self.meshes.iter().map(|mesh| &mesh.name))?;
+        let textures = unique("textures", "Texture", self.textures.iter().map(|texture| &texture.name))?;
+        let materials = unique("materials", "Material", self.materials.iter().map(|material| &material.name))?;
+        let objects = unique("objects", "Object", self.objects.iter().map(|object| &object.name))?;
+
+        for material in &self.materials {
+            if let Some(texture) = &material.texture {
+                defined(&textures, "materials", "Texture", texture)?;
+            }
+        }
+
+        for light in &self.lights {
+            let (Light::Directional { intensity, .. } | Light::Point { intensity, .. }) = *light;
+            if intensity < 0.0 {
+                return Err(Problem::new(format!("Light intensity must not be negative, got {}", intensity), "lights", ""));
+            }
+        }
+
+        for object in &self.objects {
+            defined(&meshes, "objects", "Mesh", &object.mesh)?;
+            defined(&materials, "objects", "Material", &object.material)?;
+
+            let [x, y, z, w] = object.transform.rotation;
+            if x * x + y * y + z * z + w * w < 1e-6 {
+                return Err(Problem::new(format!("Object \"{}\" has a zero rotation quaternion", object.name), "objects", &object.name));
+            }
+            if object.transform.scale.contains(&0.0) {
+                return Err(Problem::new(format!("Object \"{}\" is scaled to nothing", object.name), "objects", &object.name));
+            }
+
+            // Walking up from here must reach the top before coming back
+            let mut parent = object.parent.as_ref();
+            for _ in 0..self.objects.len() {
+                let name = match parent {
+                    Some(name) => name,
+                    None => break,
+                };
+                defined(&objects, "objects", "Parent", name)?;
+                if *name == object.name {
+                    return Err(Problem::new(format!("Object \"{}\" is its own ancestor", object.name), "objects", &object.name));
+                }
+                parent = self.objects.iter().find(|other| other.name == *name).and_then(|other| other.parent.as_ref());
+            }
+        }
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub fn camera_position(&self) -> Point3<f32> {
+        Point3::from(self.camera.position)
+    }
+
+    pub fn mesh_index(&self, name: &str) -> Option<usize> {
+        self.meshes.iter().position(|mesh| mesh.name == name)
+    }
+
+    pub fn texture_index(&self, name: &str) -> Option<usize> {
+        self.textures.iter().position(|texture| texture.name == name)
+    }
+
+    pub fn object_index(&self, name: &str) -> Option<usize> {
+        self.objects.iter().position(|object| object.name == name)
+    }
+
+    pub fn material_index(&self, name: &str) -> Option<usize> {
+        self.materials.iter().position(|material| material.name == name)
+    }
+
+    pub fn texture_path(&self, texture: &TextureDesc) -> PathBuf {
+        self.directory.join(&texture.path)
+    }
+}
+
+
+//
+// ─── TESTS ──────────────────────────────────────────────────────────────────────
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid(text: &str) -> String {
+        match Scene::from_str(text) {
+            Err(e @ SceneError::Invalid { .. }) => e.to_string(),
+            other => panic!("Expected a validation error, got {:?}", other),
+        }
+    }
+
+    const CAMERA: &str = "camera: (position: (0.0, 0.0, 3.0), projection: Perspective(fov_y_degrees: 45.0, near: 0.1, far: 100.0)),";
+
+    #[test]
+    fn default_round_trips_and_matches_scene_ron() {
+        let scene = Scene::default();
+        let text = ron::ser::to_string_pretty(&scene, ron::ser::PrettyConfig::default()).unwrap();
+        let mut parsed = Scene::from_str(&text).unwrap();
+        parsed.directory = scene.directory.clone();
+        assert_eq!(parsed, scene);
+
+        let file = Scene::load(config::source_path("scene.ron")).unwrap();
+        assert_eq!(file, scene);
+    }
+
+    #[test]
+    fn missing_references_point_at_the_name() {
+        let text = format!("(\n    {}\n    meshes: [(name: \"cube\", source: Cube)],\n    materials: [(name: \"red\")],\n    objects: [\n        (name: \"a\", mesh: \"cube\", material: \"red\"),\n        (name: \"b\", mesh: \"sphere\", material: \"red\"),\n    ],\n)", CAMERA);
+        assert_eq!(invalid(&text), "7:27: Mesh \"sphere\" is not defined");
+
+        let text = format!("({}\nmaterials: [(name: \"red\", texture: Some(\"paint\"))])", CAMERA);
+        assert_eq!(invalid(&text), "2:41: Texture \"paint\" is not defined");
+    }
+
+    #[test]
+    fn duplicates_and_bad_values_are_rejected() {
+        let text = format!("({}\nmeshes: [(name: \"cube\", source: Cube), (name: \"cube\", source: Cube)])", CAMERA);
+        assert_eq!(invalid(&text), "2:47: Mesh \"cube\" is defined twice");
+
+        let text = format!("({}\nmeshes: [(name: \"cube\", source: Cube)], materials: [(name: \"m\")],\nobjects: [(name: \"flat\", mesh: \"cube\", material: \"m\", transform: (scale: (1.0, 0.0, 1.0)))])", CAMERA);
+        assert_eq!(invalid(&text), "3:18: Object \"flat\" is scaled to nothing");
+
+        let objects = "meshes: [(name: \"cube\", source: Cube)], materials: [(name: \"m\")], objects: [";
+        let text = format!("({}\n{}\n(name: \"a\", mesh: \"cube\", material: \"m\", parent: Some(\"b\")),\n(name: \"b\", mesh: \"cube\", material: \"m\", parent: Some(\"a\"))])", CAMERA, objects);
+        assert_eq!(invalid(&text), "3:8: Object \"a\" is its own ancestor");
+        let text = format!("({}\n{}\n(name: \"a\", mesh: \"cube\", material: \"m\", parent: Some(\"nobody\"))])", CAMERA, objects);
+        assert_eq!(invalid(&text), "3:55: Parent \"nobody\" is not defined");
+
+        let text = "(camera: (position: (0.0, 0.0, 3.0), projection: Orthographic(height: 2.0, near: 5.0, far: 1.0)))";
+        assert!(invalid(text).contains("near plane 5 is not before far plane 1"));
+
+        // Syntax errors come from RON with their position
+        let error = Scene::from_str("(camera: (position: (0.0, 0.0)))").unwrap_err().to_string();
+        assert!(error.starts_with("1:"), "{}", error);
+    }
+
+    #[test]
+    fn comments_and_longer_keys_are_not_sections() {
+        let text = format!("// materials: and meshes are named\n({}\nsubmeshes: 1, meshes: [(name: \"cube\", source: Cube), (name: \"cube\", source: Cube)])", CAMERA);
+        assert_eq!(find_key(&text, "meshes"), text.find(" meshes:").map(|space| space + 1));
+        assert_eq!(invalid(&text), "3:61: Mesh \"cube\" is defined twice");
+    }
+
+    #[test]
+    fn unreadable_textures_fail_loading() {
+        let path = std::env::temp_dir().join(format!("scene_bad_texture_{}.ron", std::process::id()));
+        let text = format!("// textures: are PNGs\n({}\ntextures: [(name: \"car\", path: \"nowhere.png\")])", CAMERA);
+        fs::write(&path, text).unwrap();
+        let error = Scene::load(&path).unwrap_err().to_string();
+        fs::remove_file(&path).unwrap();
+        assert!(error.starts_with("3:32: Texture \"car\" can't be loaded from "), "{}", error);
+    }
+}
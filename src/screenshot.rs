@@ -0,0 +1,111 @@
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use gl33::*;
+
+use crate::backend::Backend;
+use crate::image::Image;
+use crate::texture::TextureFormat;
+
+// What the alpha channel of a capture means.
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Alpha {
+    Opaque,        // Window contents, alpha is whatever the last draw left and means nothing
+    Straight,      // Kept as is
+    Premultiplied, // Color was multiplied by alpha when blending, PNG wants it divided back out
+}
+
+// Reads a `width` x `height` color buffer of `framebuffer` (0 is the window)
+// into an image with the top row first, the way PNG stores it. `format` is
+// what the color buffer holds. The window's back buffer is read, so capture
+// after drawing and before swapping.
+pub fn read_pixels(gl: &mut impl Backend, framebuffer: u32, width: u32, height: u32, format: TextureFormat, alpha: Alpha) -> Image {
+    let mut image = Image::new(width, height);
+    gl.bind_framebuffer(framebuffer);
+
+    if matches!(format, TextureFormat::R32F | TextureFormat::Rgba32F) {
+        // Float targets hold linear light, encode it like the screen would
+        let mut bytes = vec![0u8; (width * height) as usize * 4 * 4];
+        gl.read_pixels([0, 0, width as i32, height as i32], GL_RGBA, GL_FLOAT, &mut bytes);
+        let pixels: Vec<f32> = bytes.chunks_exact(4).map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]])).collect();
+        for (px, out) in pixels.chunks_exact(4).zip(image.data.chunks_exact_mut(4)) {
+            let encoded = [linear_to_srgb(px[0]), linear_to_srgb(px[1]), linear_to_srgb(px[2]), px[3]];
+            for (byte, value) in out.iter_mut().zip(encoded.iter()) {
+                *byte = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+        }
+    } else {
+        // 8 bit targets already hold what is displayed. sRGB ones store
+        // encoded values and linear ones are shown as is, so no conversion.
+        gl.read_pixels([0, 0, width as i32, height as i32], GL_RGBA, GL_UNSIGNED_BYTE, &mut image.data);
+    }
+
+    image.flip_vertically(); // OpenGL rows start at the bottom
+    fix_alpha(&mut image, alpha);
+    image
+}
+
+pub fn fix_alpha(image: &mut Image, alpha: Alpha) {
+    match alpha {
+        Alpha::Opaque => image.data.chunks_exact_mut(4).for_each(|px| px[3] = 255),
+        Alpha::Straight => (),
+        Alpha::Premultiplied => for px in image.data.chunks_exact_mut(4) {
+            let a = px[3] as u32;
+            for channel in &mut px[..3] {
+                if let Some(value) = (*channel as u32 * 255 + a / 2).checked_div(a) { // Transparent stays as is
+                    *channel = value.min(255) as u8;
+                }
+            }
+        },
+    }
+}
+
+// Standard sRGB transfer function, 0 to 1 both ways.
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// screenshot-<unix millis>.png in the working directory, so they sort by time.
+pub fn default_path() -> PathBuf {
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_millis());
+    PathBuf::from(format!("screenshot-{}.png", millis))
+}
+
+
+//
+// ─── TESTS ──────────────────────────────────────────────────────────────────────
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_curve_hits_known_values() {
+        assert_eq!(linear_to_srgb(0.0), 0.0);
+        assert!((linear_to_srgb(1.0) - 1.0).abs() < 1e-6);
+        assert!((linear_to_srgb(0.2140) - 0.5).abs() < 1e-3); // Middle gray
+        assert!((linear_to_srgb(0.002) - 0.002 * 12.92).abs() < 1e-6); // Linear toe
+    }
+
+    #[test]
+    fn alpha_is_fixed_per_mode() {
+        let mut image = Image::new(2, 1);
+        image.set_pixel(0, 0, [64, 32, 0, 128]);
+        image.set_pixel(1, 0, [0, 0, 0, 0]);
+
+        let mut opaque = image.clone();
+        fix_alpha(&mut opaque, Alpha::Opaque);
+        assert_eq!(opaque.pixel(0, 0), [64, 32, 0, 255]);
+
+        let mut straight = image.clone();
+        fix_alpha(&mut straight, Alpha::Premultiplied);
+        assert_eq!(straight.pixel(0, 0), [128, 64, 0, 128]);
+        assert_eq!(straight.pixel(1, 0), [0, 0, 0, 0]); // Fully transparent stays black
+    }
+}
@@ -6,13 +6,15 @@ use gl33::*;
 
 use std::fs::read;
 
+use crate::backend::Backend;
+
 pub struct Shader {
     pub id: u32,
 }
 
 impl Shader {
 
-    pub fn new(vertex_path: &str, fragment_path: &str) -> Self {
+    pub fn new(gl: &mut impl Backend, vertex_path: &str, fragment_path: &str) -> Self {
 
         //
         // ─── READ SHADER FILES ───────────────────────────────────────────
@@ -21,87 +23,26 @@ impl Shader {
         let vertex_bytes = read(vertex_path).expect("Could not open vertex shader!");
         let fragment_bytes = read(fragment_path).expect("Could not open fragment shader!");
 
-        
-        //
-        // ─── VERTEX SHADER ───────────────────────────────────────────────
-        //  
-
-        let vertex_shader = glCreateShader(GL_VERTEX_SHADER);   // Create shader
-
-        unsafe {
-            glShaderSource(                     // Load shade from source
-                vertex_shader,                  // Shader object
-                1,                              // How many string objects
-                &vertex_bytes.as_ptr().cast(),  // Source code of vertex shader
-                &(vertex_bytes.len() as i32)    // Shader source code size
-            );
-        }
-
-        glCompileShader(vertex_shader); // Compile shader
-
-        // Check for errors
-        check_shader_errors(vertex_shader, "Vertex shader error");
-
-        //
-        // ─── FRAGMENT SHADER ─────────────────────────────────────────────
-        //
-
-        let fragment_shader = glCreateShader(GL_FRAGMENT_SHADER);   // Create shader
-
-        unsafe {
-            glShaderSource(                       // Load shade from source
-                fragment_shader,                  // Shader object
-                1,                                // How many string objects
-                &fragment_bytes.as_ptr().cast(),  // Source code of vertex shader
-                &(fragment_bytes.len() as i32)    // Shader source code size
-            );
-        }
-
-        glCompileShader(fragment_shader); // Compile shader
-
-        // Check for errors
-        check_shader_errors(fragment_shader, "Fragment shader error");
-        
-
-        //
-        // ─── SHADER PROGRAM ──────────────────────────────────────────────
-        //  
-
-        let id = glCreateProgram();
-
-        glAttachShader(id, vertex_shader);   // Attach vertex shader to program
-        glAttachShader(id, fragment_shader); // Attach fragment shader to program
-        glLinkProgram(id);                   // Link program
+        Self::from_source(gl, &vertex_bytes, &fragment_bytes)
+    }
 
-        let mut success = 0;
-        unsafe {
-            glGetProgramiv(id, GL_LINK_STATUS, &mut success); // Get link status
-
-            if success == 0 {
-                let mut log: Vec<u8> = Vec::with_capacity(1024); // Buffer for possible error message
-                let mut len = 0;
-                glGetProgramInfoLog(         // Get possible error message from program
-                    id,
-                    log.capacity() as i32,
-                    &mut len,
-                    log.as_mut_ptr().cast()
-                );
-
-                log.set_len(len as usize); // Trims the log of unused bytes
-                panic!(                                // In case of shader error
-                    "Shader program error: {}",        // Error message
-                    std::str::from_utf8(&log).unwrap()
-                )
-            }
+    // Same as `new` with the sources already in memory, e.g. from include_bytes!
+    // so the program runs from any working directory. Panics with the compile
+    // or link log when the sources are broken.
+    pub fn from_source(gl: &mut impl Backend, vertex_bytes: &[u8], fragment_bytes: &[u8]) -> Self {
+        Shader {
+            id: gl.create_program(vertex_bytes, fragment_bytes)
         }
+    }
 
-        // Delete shaders, they aren't needed anymore. So long, partners.
-        glDeleteShader(vertex_shader);
-        glDeleteShader(fragment_shader);
+    // Name shown in GL debug messages and graphics debuggers.
+    pub fn set_label(&self, gl: &mut impl Backend, label: &str) {
+        gl.label(GL_PROGRAM, self.id, label);
+    }
 
-        Shader {
-            id: id
-        }
+    // Not Drop, it needs the backend that made it.
+    pub fn delete(&self, gl: &mut impl Backend) {
+        gl.delete_program(self.id);
     }
 
     pub fn use_shader(&self) {
@@ -128,6 +69,16 @@ impl Shader {
         }
     }
 
+    pub fn set_uint(&self, name: &str, value: u32) {
+        let name = String::from(name) + "\0";
+        unsafe {
+            glUniform1ui(
+                glGetUniformLocation(self.id, name.as_str().as_ptr()),
+                value
+            );
+        }
+    }
+
     pub fn set_float(&self, name: &str, value: f32) {
         let name = String::from(name) + "\0";
         unsafe {
@@ -138,31 +89,15 @@ impl Shader {
         }
     }
 
-}
-
-// Gets an error from OpenGL and panics with given error message.
-pub fn check_shader_errors(shader: u32, error_msg: &str) {
-    let mut success = 0;
-    unsafe {
-        glGetShaderiv(shader, GL_COMPILE_STATUS, &mut success); // Get compile status
-
-        // Check for errors
-        if success == 0 {
-            let mut log: Vec<u8> = Vec::with_capacity(1024); // Buffer for error message
-            let mut len = 0;
-            glGetShaderInfoLog(         // Get possible error message from shader
-                shader,
-                log.capacity() as i32,
-                &mut len,
-                log.as_mut_ptr().cast()
+    pub fn set_vec2(&self, name: &str, x: f32, y: f32) {
+        let name = String::from(name) + "\0";
+        unsafe {
+            glUniform2f(
+                glGetUniformLocation(self.id, name.as_str().as_ptr()),
+                x,
+                y
             );
-
-            log.set_len(len as usize); // Trims the log of unused bytes
-            panic!(                                // In case of shader error
-                "{0}: {1}",       // Error message
-                error_msg,
-                std::str::from_utf8(&log).unwrap()
-            )
         }
     }
-}
\ No newline at end of file
+
+}
@@ -0,0 +1,11 @@
+#version 330
+
+// Object IDs for GPU picking, 0 means nothing was drawn there.
+
+out uint id;
+
+uniform uint object_id;
+
+void main() {
+    id = object_id;
+}
@@ -0,0 +1,13 @@
+#version 330 core
+
+layout (location = 0) in vec2 position; // Window pixels, origin at the bottom left
+layout (location = 1) in vec4 color;
+
+out vec4 vertex_color;
+
+uniform vec2 viewport;
+
+void main() {
+    gl_Position = vec4(position / viewport * 2.0 - 1.0, 0.0, 1.0);
+    vertex_color = color;
+}
@@ -0,0 +1,11 @@
+#version 330
+
+in vec2 texture_coords;
+
+out vec4 fragment_color;
+
+uniform sampler2D texture1;
+
+void main() {
+    fragment_color = texture(texture1, texture_coords);
+}
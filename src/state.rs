@@ -0,0 +1,462 @@
+use std::collections::HashMap;
+
+use gl33::*;
+use nalgebra::Matrix4;
+
+use crate::backend::Backend;
+use crate::texture::{Sampler, TextureFormat};
+
+// How many state calls went through and how many were dropped for setting
+// what was already set. Object creation, uploads and draws are not counted.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StateCounters {
+    pub issued: u32,
+    pub skipped: u32,
+}
+
+impl StateCounters {
+
+    // Counts the call either way, true when it has to go through.
+    fn check<T: PartialEq>(&mut self, current: Option<&T>, wanted: &T) -> bool {
+        if current == Some(wanted) {
+            self.skipped += 1;
+            false
+        } else {
+            self.issued += 1;
+            true
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Uniform {
+    Int(i32),
+    Uint(u32),
+    Vec2([f32; 2]),
+    Mat4(Matrix4<f32>),
+}
+
+// What the cache believes is set. Missing means unknown, the next call for it
+// always goes through.
+#[derive(Debug, Default)]
+struct Shadow {
+    program: Option<u32>,
+    vertex_array: Option<u32>,
+    buffers: HashMap<u32, u32>,          // Target => buffer
+    textures: HashMap<(u32, u32), u32>,  // Unit and target => texture
+    framebuffer: Option<u32>,
+    viewport: Option<[i32; 4]>,
+    capabilities: HashMap<u32, bool>,
+    polygon_mode: Option<u32>,
+    depth_func: Option<u32>,
+    depth_mask: Option<bool>,
+    clear_depth: Option<f64>,
+    clear_color: Option<[f32; 4]>,
+    blend_func: Option<(u32, u32)>,
+    uniforms: HashMap<(u32, i32), Uniform>, // Program and location => value, programs keep them while not in use
+}
+
+// Backend in front of another one that remembers the GL state it set and
+// skips calls that wouldn't change it. Only knows about calls made through
+// it, so anything that touches GL directly has to be followed by `reset`.
+pub struct StateCache<B: Backend> {
+    pub inner: B,
+    shadow: Shadow,
+    counters: StateCounters,
+}
+
+impl<B: Backend> StateCache<B> {
+
+    pub fn new(inner: B) -> Self {
+        StateCache { inner, shadow: Shadow::default(), counters: StateCounters::default() }
+    }
+
+    // Forgets everything, e.g. after other code drew with GL directly.
+    #[allow(dead_code)]
+    pub fn reset(&mut self) {
+        self.shadow = Shadow::default();
+    }
+
+    // Counters since the last take.
+    pub fn take_counters(&mut self) -> StateCounters {
+        std::mem::take(&mut self.counters)
+    }
+
+    fn set_capability(&mut self, capability: GLenum, enabled: bool) {
+        if self.counters.check(self.shadow.capabilities.get(&capability.0), &enabled) {
+            self.shadow.capabilities.insert(capability.0, enabled);
+            if enabled {
+                self.inner.enable(capability);
+            } else {
+                self.inner.disable(capability);
+            }
+        }
+    }
+
+    // Texture uploads and settings go through unit 0.
+    fn bound_for_upload(&mut self, target: GLenum, texture: u32) {
+        self.shadow.textures.insert((0, target.0), texture);
+    }
+
+    fn set_uniform(&mut self, location: i32, value: Uniform) -> bool {
+        let program = match self.shadow.program {
+            Some(program) if location != -1 => program,
+            _ => {
+                // Unknown program or a uniform the shader doesn't have, nothing to remember
+                self.counters.issued += 1;
+                return true;
+            }
+        };
+        let changed = self.counters.check(self.shadow.uniforms.get(&(program, location)), &value);
+        if changed {
+            self.shadow.uniforms.insert((program, location), value);
+        }
+        changed
+    }
+}
+
+impl<B: Backend> Backend for StateCache<B> {
+
+    fn create_buffer(&mut self) -> u32 {
+        self.inner.create_buffer()
+    }
+
+    fn bind_buffer(&mut self, target: GLenum, buffer: u32) {
+        if self.counters.check(self.shadow.buffers.get(&target.0), &buffer) {
+            self.shadow.buffers.insert(target.0, buffer);
+            self.inner.bind_buffer(target, buffer);
+        }
+    }
+
+    fn buffer_data(&mut self, target: GLenum, data: &[u8], usage: GLenum) {
+        self.inner.buffer_data(target, data, usage);
+    }
+
+    fn delete_buffer(&mut self, buffer: u32) {
+        // GL unbinds deleted buffers, and the name can come back for a new one
+        self.shadow.buffers.retain(|_, bound| *bound != buffer);
+        self.inner.delete_buffer(buffer);
+    }
+
+    fn create_vertex_array(&mut self) -> u32 {
+        self.inner.create_vertex_array()
+    }
+
+    fn bind_vertex_array(&mut self, vertex_array: u32) {
+        if self.counters.check(self.shadow.vertex_array.as_ref(), &vertex_array) {
+            self.shadow.vertex_array = Some(vertex_array);
+            // Index buffer binding is part of the vertex array
+            self.shadow.buffers.remove(&GL_ELEMENT_ARRAY_BUFFER.0);
+            self.inner.bind_vertex_array(vertex_array);
+        }
+    }
+
+    fn vertex_attrib_pointer(&mut self, location: u32, components: i32, stride: i32, offset: usize) {
+        self.inner.vertex_attrib_pointer(location, components, stride, offset);
+    }
+
+    fn enable_vertex_attrib_array(&mut self, location: u32) {
+        self.inner.enable_vertex_attrib_array(location);
+    }
+
+    fn delete_vertex_array(&mut self, vertex_array: u32) {
+        if self.shadow.vertex_array == Some(vertex_array) {
+            self.shadow.vertex_array = None;
+            self.shadow.buffers.remove(&GL_ELEMENT_ARRAY_BUFFER.0);
+        }
+        self.inner.delete_vertex_array(vertex_array);
+    }
+
+    fn create_texture(&mut self) -> u32 {
+        self.inner.create_texture()
+    }
+
+    fn bind_texture(&mut self, unit: u32, target: GLenum, texture: u32) {
+        if self.counters.check(self.shadow.textures.get(&(unit, target.0)), &texture) {
+            self.shadow.textures.insert((unit, target.0), texture);
+            self.inner.bind_texture(unit, target, texture);
+        }
+    }
+
+    fn texture_image(&mut self, target: GLenum, texture: u32, format: TextureFormat, size: [u32; 3], data: Option<&[u8]>) {
+        self.inner.texture_image(target, texture, format, size, data);
+        self.bound_for_upload(target, texture);
+    }
+
+    fn texture_sub_image(&mut self, target: GLenum, texture: u32, format: TextureFormat, offset: [u32; 3], size: [u32; 3], data: &[u8]) {
+        self.inner.texture_sub_image(target, texture, format, offset, size, data);
+        self.bound_for_upload(target, texture);
+    }
+
+    fn texture_sampler(&mut self, target: GLenum, texture: u32, sampler: &Sampler) {
+        self.inner.texture_sampler(target, texture, sampler);
+        self.bound_for_upload(target, texture);
+    }
+
+    fn generate_mipmaps(&mut self, target: GLenum, texture: u32) {
+        self.inner.generate_mipmaps(target, texture);
+        self.bound_for_upload(target, texture);
+    }
+
+    fn delete_texture(&mut self, texture: u32) {
+        // GL unbinds deleted textures from every unit, and the name can come back
+        self.shadow.textures.retain(|_, bound| *bound != texture);
+        self.inner.delete_texture(texture);
+    }
+
+    fn create_program(&mut self, vertex_source: &[u8], fragment_source: &[u8]) -> u32 {
+        self.inner.create_program(vertex_source, fragment_source)
+    }
+
+    fn use_program(&mut self, program: u32) {
+        if self.counters.check(self.shadow.program.as_ref(), &program) {
+            self.shadow.program = Some(program);
+            self.inner.use_program(program);
+        }
+    }
+
+    fn uniform_location(&mut self, program: u32, name: &str) -> i32 {
+        self.inner.uniform_location(program, name)
+    }
+
+    fn uniform_int(&mut self, location: i32, value: i32) {
+        if self.set_uniform(location, Uniform::Int(value)) {
+            self.inner.uniform_int(location, value);
+        }
+    }
+
+    fn uniform_uint(&mut self, location: i32, value: u32) {
+        if self.set_uniform(location, Uniform::Uint(value)) {
+            self.inner.uniform_uint(location, value);
+        }
+    }
+
+    fn uniform_vec2(&mut self, location: i32, value: [f32; 2]) {
+        if self.set_uniform(location, Uniform::Vec2(value)) {
+            self.inner.uniform_vec2(location, value);
+        }
+    }
+
+    fn uniform_mat4(&mut self, location: i32, value: &Matrix4<f32>) {
+        if self.set_uniform(location, Uniform::Mat4(*value)) {
+            self.inner.uniform_mat4(location, value);
+        }
+    }
+
+    fn delete_program(&mut self, program: u32) {
+        // A new program can get the name, with uniforms of its own
+        if self.shadow.program == Some(program) {
+            self.shadow.program = None;
+        }
+        self.shadow.uniforms.retain(|&(owner, _), _| owner != program);
+        self.inner.delete_program(program);
+    }
+
+    fn create_renderbuffer(&mut self, format: GLenum, width: u32, height: u32) -> u32 {
+        self.inner.create_renderbuffer(format, width, height)
+    }
+
+    fn delete_renderbuffer(&mut self, renderbuffer: u32) {
+        self.inner.delete_renderbuffer(renderbuffer);
+    }
+
+    fn create_framebuffer(&mut self, color: u32, depth: u32) -> u32 {
+        let framebuffer = self.inner.create_framebuffer(color, depth);
+        self.shadow.framebuffer = Some(framebuffer);
+        framebuffer
+    }
+
+    fn bind_framebuffer(&mut self, framebuffer: u32) {
+        if self.counters.check(self.shadow.framebuffer.as_ref(), &framebuffer) {
+            self.shadow.framebuffer = Some(framebuffer);
+            self.inner.bind_framebuffer(framebuffer);
+        }
+    }
+
+    fn delete_framebuffer(&mut self, framebuffer: u32) {
+        if self.shadow.framebuffer == Some(framebuffer) {
+            self.shadow.framebuffer = Some(0); // GL falls back to the window
+        }
+        self.inner.delete_framebuffer(framebuffer);
+    }
+
+    fn viewport(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        if self.counters.check(self.shadow.viewport.as_ref(), &[x, y, width, height]) {
+            self.shadow.viewport = Some([x, y, width, height]);
+            self.inner.viewport(x, y, width, height);
+        }
+    }
+
+    fn read_pixels(&mut self, rect: [i32; 4], format: GLenum, kind: GLenum, data: &mut [u8]) {
+        self.inner.read_pixels(rect, format, kind, data);
+    }
+
+    fn enable(&mut self, capability: GLenum) {
+        self.set_capability(capability, true);
+    }
+
+    fn disable(&mut self, capability: GLenum) {
+        self.set_capability(capability, false);
+    }
+
+    fn polygon_mode(&mut self, mode: GLenum) {
+        if self.counters.check(self.shadow.polygon_mode.as_ref(), &mode.0) {
+            self.shadow.polygon_mode = Some(mode.0);
+            self.inner.polygon_mode(mode);
+        }
+    }
+
+    fn depth_func(&mut self, func: GLenum) {
+        if self.counters.check(self.shadow.depth_func.as_ref(), &func.0) {
+            self.shadow.depth_func = Some(func.0);
+            self.inner.depth_func(func);
+        }
+    }
+
+    fn depth_mask(&mut self, write: bool) {
+        if self.counters.check(self.shadow.depth_mask.as_ref(), &write) {
+            self.shadow.depth_mask = Some(write);
+            self.inner.depth_mask(write);
+        }
+    }
+
+    fn clear_depth(&mut self, depth: f64) {
+        if self.counters.check(self.shadow.clear_depth.as_ref(), &depth) {
+            self.shadow.clear_depth = Some(depth);
+            self.inner.clear_depth(depth);
+        }
+    }
+
+    fn clear_color(&mut self, color: [f32; 4]) {
+        if self.counters.check(self.shadow.clear_color.as_ref(), &color) {
+            self.shadow.clear_color = Some(color);
+            self.inner.clear_color(color);
+        }
+    }
+
+    fn blend_func(&mut self, source: GLenum, destination: GLenum) {
+        if self.counters.check(self.shadow.blend_func.as_ref(), &(source.0, destination.0)) {
+            self.shadow.blend_func = Some((source.0, destination.0));
+            self.inner.blend_func(source, destination);
+        }
+    }
+
+    fn clear(&mut self, mask: GLbitfield) {
+        self.inner.clear(mask);
+    }
+
+    fn clear_color_uint(&mut self, value: u32) {
+        self.inner.clear_color_uint(value);
+    }
+
+    fn draw_elements(&mut self, mode: GLenum, count: i32) {
+        self.inner.draw_elements(mode, count);
+    }
+
+    fn draw_arrays(&mut self, mode: GLenum, first: i32, count: i32) {
+        self.inner.draw_arrays(mode, first, count);
+    }
+
+    fn label(&mut self, kind: GLenum, id: u32, name: &str) {
+        self.inner.label(kind, id, name);
+    }
+}
+
+
+//
+// ─── TESTS ──────────────────────────────────────────────────────────────────────
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{Call, MockBackend};
+
+    #[test]
+    fn repeated_state_is_skipped() {
+        let mut gl = StateCache::new(MockBackend::new());
+        for _ in 0..3 {
+            gl.enable(GL_DEPTH_TEST);
+            gl.use_program(7);
+            gl.uniform_int(0, 0);
+            gl.bind_texture(0, GL_TEXTURE_2D, 4);
+            gl.polygon_mode(GL_FILL);
+        }
+        gl.disable(GL_DEPTH_TEST);
+        gl.bind_texture(1, GL_TEXTURE_2D, 4); // Other unit, not the same binding
+
+        assert_eq!(gl.inner.take_calls(), vec![
+            Call::Enable(GL_DEPTH_TEST.0),
+            Call::UseProgram(7),
+            Call::UniformInt { location: 0, value: 0 },
+            Call::BindTexture { unit: 0, target: GL_TEXTURE_2D.0, texture: 4 },
+            Call::PolygonMode(GL_FILL.0),
+            Call::Disable(GL_DEPTH_TEST.0),
+            Call::BindTexture { unit: 1, target: GL_TEXTURE_2D.0, texture: 4 },
+        ]);
+        assert_eq!(gl.take_counters(), StateCounters { issued: 7, skipped: 10 });
+        assert_eq!(gl.take_counters(), StateCounters::default());
+    }
+
+    #[test]
+    fn uniforms_are_remembered_per_program() {
+        let mut gl = StateCache::new(MockBackend::new());
+        gl.use_program(1);
+        gl.uniform_int(3, 5);
+        gl.use_program(2);
+        gl.uniform_int(3, 5); // Same location, other program
+        gl.use_program(1);
+        gl.uniform_int(3, 5);
+        gl.uniform_int(-1, 5); // Not in the shader, always passed on
+
+        let uniforms: Vec<Call> = gl.inner.take_calls().into_iter()
+            .filter(|call| matches!(call, Call::UniformInt { .. }))
+            .collect();
+        assert_eq!(uniforms.len(), 3);
+    }
+
+    #[test]
+    fn index_buffer_follows_vertex_array_and_reset_forgets() {
+        let mut gl = StateCache::new(MockBackend::new());
+        gl.bind_vertex_array(1);
+        gl.bind_buffer(GL_ELEMENT_ARRAY_BUFFER, 2);
+        gl.bind_vertex_array(3);
+        gl.bind_buffer(GL_ELEMENT_ARRAY_BUFFER, 2); // New vertex array has its own
+        gl.delete_buffer(2);
+        gl.bind_buffer(GL_ELEMENT_ARRAY_BUFFER, 2); // Deleted names come back unbound
+        gl.reset();
+        gl.bind_vertex_array(3);
+
+        assert_eq!(gl.inner.take_calls(), vec![
+            Call::BindVertexArray(1),
+            Call::BindBuffer { target: GL_ELEMENT_ARRAY_BUFFER.0, buffer: 2 },
+            Call::BindVertexArray(3),
+            Call::BindBuffer { target: GL_ELEMENT_ARRAY_BUFFER.0, buffer: 2 },
+            Call::DeleteBuffer(2),
+            Call::BindBuffer { target: GL_ELEMENT_ARRAY_BUFFER.0, buffer: 2 },
+            Call::BindVertexArray(3),
+        ]);
+    }
+
+    #[test]
+    fn deleted_textures_and_programs_are_forgotten() {
+        let mut gl = StateCache::new(MockBackend::new());
+        gl.bind_texture(2, GL_TEXTURE_2D, 5);
+        gl.use_program(3);
+        gl.uniform_int(0, 1);
+        gl.delete_texture(5);
+        gl.delete_program(3);
+        gl.take_counters();
+
+        // Both names can come back for new objects, which start out unbound
+        gl.bind_texture(2, GL_TEXTURE_2D, 5);
+        gl.use_program(3);
+        gl.uniform_int(0, 1);
+        assert_eq!(gl.take_counters(), StateCounters { issued: 3, skipped: 0 });
+
+        // Uploads bind to unit 0 on the side
+        gl.texture_image(GL_TEXTURE_2D, 6, TextureFormat::Rgba8, [1, 1, 1], None);
+        gl.bind_texture(0, GL_TEXTURE_2D, 6);
+        assert_eq!(gl.take_counters(), StateCounters { issued: 0, skipped: 1 });
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use gl33::global_loader::*;
+use gl33::*;
+
+// What one frame cost. Times are in milliseconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    pub frame_ms: f32,       // Since the previous frame started, includes waiting for vsync
+    pub cpu_ms: f32,         // Simulating and submitting, up to the buffer swap
+    pub gpu_ms: Option<f32>, // Arrives a few frames late, None until then or without timer queries
+    pub draw_calls: u32,
+    pub triangles: u32,
+    pub state_changes: u32,  // Binds, uniform uploads and other GL state calls
+    pub redundant_state: u32, // State calls skipped because nothing would change
+}
+
+//
+// ─── HISTOGRAM ──────────────────────────────────────────────────────────────────
+//
+
+// Counts of times in fixed width buckets, for percentiles over a whole run
+// without keeping every sample.
+#[derive(Clone, Debug)]
+pub struct Histogram {
+    bucket_ms: f32,
+    counts: Vec<u32>, // Last bucket also takes everything too long for the others
+    total: u32,
+}
+
+impl Histogram {
+
+    pub fn new(bucket_ms: f32, max_ms: f32) -> Self {
+        let buckets = (max_ms / bucket_ms).ceil() as usize + 1;
+        Histogram { bucket_ms, counts: vec![0; buckets], total: 0 }
+    }
+
+    pub fn record(&mut self, ms: f32) {
+        let bucket = ((ms.max(0.0) / self.bucket_ms) as usize).min(self.counts.len() - 1);
+        self.counts[bucket] += 1;
+        self.total += 1;
+    }
+
+    #[allow(dead_code)]
+    pub fn count(&self) -> u32 {
+        self.total
+    }
+
+    // Upper edge of the bucket that `p` percent of samples fit under, None without samples.
+    pub fn percentile(&self, p: f32) -> Option<f32> {
+        if self.total == 0 {
+            return None;
+        }
+        let wanted = ((p / 100.0) * self.total as f32).ceil().max(1.0) as u32;
+        let mut seen = 0;
+        for (bucket, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= wanted {
+                return Some((bucket + 1) as f32 * self.bucket_ms);
+            }
+        }
+        unreachable!("Every sample is in some bucket")
+    }
+}
+
+
+//
+// ─── HISTORY ────────────────────────────────────────────────────────────────────
+//
+
+pub struct FrameHistory {
+    recent: VecDeque<(u64, FrameStats)>, // For the graph, oldest first
+    capacity: usize,
+    log: Option<Vec<FrameStats>>,        // Every frame, only kept when a CSV is wanted
+    pub frame: Histogram,
+    pub cpu: Histogram,
+    pub gpu: Histogram,
+    next: u64,
+}
+
+impl FrameHistory {
+
+    pub fn new(capacity: usize, keep_log: bool) -> Self {
+        FrameHistory {
+            recent: VecDeque::with_capacity(capacity),
+            capacity,
+            log: if keep_log { Some(Vec::new()) } else { None },
+            frame: Histogram::new(0.1, 200.0),
+            cpu: Histogram::new(0.1, 200.0),
+            gpu: Histogram::new(0.1, 200.0),
+            next: 0,
+        }
+    }
+
+    // Number the next pushed frame will get.
+    pub fn next_frame(&self) -> u64 {
+        self.next
+    }
+
+    pub fn push(&mut self, stats: FrameStats) -> u64 {
+        let number = self.next;
+        self.next += 1;
+
+        if self.recent.len() == self.capacity {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((number, stats));
+        if let Some(log) = self.log.as_mut() {
+            log.push(stats);
+        }
+        self.frame.record(stats.frame_ms);
+        self.cpu.record(stats.cpu_ms);
+        if let Some(gpu_ms) = stats.gpu_ms {
+            self.gpu.record(gpu_ms);
+        }
+        number
+    }
+
+    // Fills in a GPU time that was measured after the frame was pushed.
+    pub fn set_gpu_time(&mut self, frame: u64, ms: f32) {
+        self.gpu.record(ms);
+        if let Some((_, stats)) = self.recent.iter_mut().find(|(number, _)| *number == frame) {
+            stats.gpu_ms = Some(ms);
+        }
+        if let Some(stats) = self.log.as_mut().and_then(|log| log.get_mut(frame as usize)) {
+            stats.gpu_ms = Some(ms);
+        }
+    }
+
+    pub fn recent(&self) -> impl Iterator<Item = &FrameStats> {
+        self.recent.iter().map(|(_, stats)| stats)
+    }
+
+    pub fn last(&self) -> Option<&FrameStats> {
+        self.recent.back().map(|(_, stats)| stats)
+    }
+
+    // One line for the window title or the console.
+    pub fn summary(&self) -> String {
+        let ms = |value: Option<f32>| value.map_or("-".to_string(), |ms| format!("{:.1}", ms));
+        let fps = self.frame.percentile(50.0).map_or(0.0, |ms| 1000.0 / ms);
+        let last = self.last().copied().unwrap_or_default();
+        format!(
+            "FPS: {:.0} ~ CPU p50/p99: {}/{} ms ~ GPU p50/p99: {}/{} ms ~ Draws: {} Tris: {} State: {} (skipped {})",
+            fps, ms(self.cpu.percentile(50.0)), ms(self.cpu.percentile(99.0)),
+            ms(self.gpu.percentile(50.0)), ms(self.gpu.percentile(99.0)),
+            last.draw_calls, last.triangles, last.state_changes, last.redundant_state
+        )
+    }
+
+    // Every logged frame, empty without a log.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "frame,frame_ms,cpu_ms,gpu_ms,draw_calls,triangles,state_changes,redundant_state")?;
+        for (i, stats) in self.log.iter().flatten().enumerate() {
+            let gpu_ms = stats.gpu_ms.map_or(String::new(), |ms| format!("{:.3}", ms));
+            writeln!(
+                writer, "{},{:.3},{:.3},{},{},{},{},{}",
+                i, stats.frame_ms, stats.cpu_ms, gpu_ms, stats.draw_calls, stats.triangles, stats.state_changes, stats.redundant_state
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn save_csv(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_csv(&mut writer)?;
+        writer.flush()
+    }
+}
+
+
+//
+// ─── GPU TIMER ──────────────────────────────────────────────────────────────────
+//
+
+// GL_TIME_ELAPSED queries around the frame's draws. Results are only read
+// once the GPU has them, so a few queries are in flight at once and nothing
+// waits. Frames that start while all of them are busy go untimed.
+pub struct GpuTimer {
+    free: Vec<u32>,
+    pending: VecDeque<(u32, u64)>, // Query and the frame it timed, oldest first
+    running: Option<(u32, u64)>,
+    first: bool, // Nothing read back yet
+}
+
+impl GpuTimer {
+
+    pub fn new() -> Self {
+        let mut queries = vec![0u32; 4];
+        unsafe { glGenQueries(queries.len() as i32, queries.as_mut_ptr()) };
+        GpuTimer { free: queries, pending: VecDeque::new(), running: None, first: true }
+    }
+
+    pub fn begin(&mut self, frame: u64) {
+        if let Some(query) = self.free.pop() {
+            unsafe { glBeginQuery(GL_TIME_ELAPSED, query) };
+            self.running = Some((query, frame));
+        }
+    }
+
+    pub fn end(&mut self) {
+        if let Some(running) = self.running.take() {
+            unsafe { glEndQuery(GL_TIME_ELAPSED) };
+            self.pending.push_back(running);
+        }
+    }
+
+    // Finished measurements as (frame, milliseconds).
+    pub fn poll(&mut self) -> Vec<(u64, f32)> {
+        let mut results = Vec::new();
+        while let Some(&(query, frame)) = self.pending.front() {
+            let mut available = 0;
+            unsafe { glGetQueryObjectiv(query, GL_QUERY_RESULT_AVAILABLE, &mut available) };
+            if available == 0 {
+                break; // Later ones can't be done either
+            }
+
+            let mut nanoseconds = 0u64;
+            unsafe { glGetQueryObjectui64v(query, GL_QUERY_RESULT, &mut nanoseconds) };
+            // llvmpipe measures the very first query from some earlier point, so
+            // that one can be hours long. It also times the warm-up frame, drop it.
+            if !self.first {
+                results.push((frame, nanoseconds as f32 / 1.0e6));
+            }
+            self.first = false;
+            self.pending.pop_front();
+            self.free.push(query);
+        }
+        results
+    }
+}
+
+impl Drop for GpuTimer {
+    fn drop(&mut self) {
+        let queries: Vec<u32> = self.free.iter().copied()
+            .chain(self.pending.iter().map(|(query, _)| *query))
+            .chain(self.running.iter().map(|(query, _)| *query))
+            .collect();
+        unsafe { glDeleteQueries(queries.len() as i32, queries.as_ptr()) };
+    }
+}
+
+
+//
+// ─── TESTS ──────────────────────────────────────────────────────────────────────
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(cpu_ms: f32) -> FrameStats {
+        FrameStats { frame_ms: 16.7, cpu_ms, draw_calls: 10, triangles: 120, ..FrameStats::default() }
+    }
+
+    #[test]
+    fn percentiles_come_from_buckets() {
+        let mut histogram = Histogram::new(1.0, 100.0);
+        assert_eq!(histogram.percentile(50.0), None);
+        for ms in 0..100 {
+            histogram.record(ms as f32 + 0.5);
+        }
+        histogram.record(1000.0); // Lands in the last bucket
+
+        assert_eq!(histogram.count(), 101);
+        assert_eq!(histogram.percentile(50.0), Some(51.0));
+        assert_eq!(histogram.percentile(99.0), Some(100.0));
+        assert_eq!(histogram.percentile(100.0), Some(101.0));
+    }
+
+    #[test]
+    fn history_keeps_recent_frames_and_late_gpu_times() {
+        let mut history = FrameHistory::new(3, true);
+        for ms in 1..=5 {
+            history.push(frame(ms as f32));
+        }
+        let recent: Vec<f32> = history.recent().map(|stats| stats.cpu_ms).collect();
+        assert_eq!(recent, vec![3.0, 4.0, 5.0]);
+
+        history.set_gpu_time(3, 2.5);
+        history.set_gpu_time(0, 1.0); // Already out of the graph, still logged
+        assert_eq!(history.recent().nth(1).unwrap().gpu_ms, Some(2.5));
+        assert_eq!(history.gpu.count(), 2);
+
+        let mut csv = Vec::new();
+        history.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[1], "0,16.700,1.000,1.000,10,120,0,0");
+        assert_eq!(lines[2], "1,16.700,2.000,,10,120,0,0");
+    }
+}
@@ -0,0 +1,445 @@
+use gl33::*;
+
+use crate::backend::Backend;
+use crate::image::Image;
+
+//
+// ─── SAMPLER ────────────────────────────────────────────────────────────────────
+//
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Wrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+    ClampToBorder,
+}
+
+// How a texture is read in shaders. Same settings work for every texture kind,
+// 2D textures simply ignore the third wrap direction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sampler {
+    pub min_filter: Filter,
+    pub mag_filter: Filter,
+    pub mipmap_filter: Option<Filter>, // None => mipmaps are neither generated nor sampled
+    pub wrap: [Wrap; 3],               // S, T, R
+    pub border_color: [f32; 4],        // Used with Wrap::ClampToBorder
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Sampler {
+            min_filter: Filter::Linear,
+            mag_filter: Filter::Linear,
+            mipmap_filter: Some(Filter::Linear),
+            wrap: [Wrap::Repeat; 3],
+            border_color: [0.0, 0.0, 0.0, 0.0],
+        }
+    }
+}
+
+impl Sampler {
+
+    // Crisp pixels, e.g. for pixel art and lookup tables.
+    pub fn nearest() -> Self {
+        Sampler {
+            min_filter: Filter::Nearest,
+            mag_filter: Filter::Nearest,
+            mipmap_filter: None,
+            ..Default::default()
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn with_wrap(mut self, wrap: Wrap) -> Self {
+        self.wrap = [wrap; 3];
+        self
+    }
+
+    pub fn gl_min_filter(&self) -> GLenum {
+        match (self.min_filter, self.mipmap_filter) {
+            (Filter::Nearest, None) => GL_NEAREST,
+            (Filter::Linear, None) => GL_LINEAR,
+            (Filter::Nearest, Some(Filter::Nearest)) => GL_NEAREST_MIPMAP_NEAREST,
+            (Filter::Nearest, Some(Filter::Linear)) => GL_NEAREST_MIPMAP_LINEAR,
+            (Filter::Linear, Some(Filter::Nearest)) => GL_LINEAR_MIPMAP_NEAREST,
+            (Filter::Linear, Some(Filter::Linear)) => GL_LINEAR_MIPMAP_LINEAR,
+        }
+    }
+
+    pub fn gl_mag_filter(&self) -> GLenum {
+        match self.mag_filter {
+            Filter::Nearest => GL_NEAREST,
+            Filter::Linear => GL_LINEAR,
+        }
+    }
+
+    // S, T and R.
+    pub fn gl_wrap(&self) -> [GLenum; 3] {
+        let wrap = |wrap: Wrap| match wrap {
+            Wrap::Repeat => GL_REPEAT,
+            Wrap::MirroredRepeat => GL_MIRRORED_REPEAT,
+            Wrap::ClampToEdge => GL_CLAMP_TO_EDGE,
+            Wrap::ClampToBorder => GL_CLAMP_TO_BORDER,
+        };
+        [wrap(self.wrap[0]), wrap(self.wrap[1]), wrap(self.wrap[2])]
+    }
+}
+
+
+//
+// ─── FORMATS ────────────────────────────────────────────────────────────────────
+//
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureFormat {
+    R8,
+    Rg8,
+    Rgb8,
+    Rgba8,
+    Srgb8,       // Color textures authored in sRGB, decoded to linear when sampled
+    Srgb8Alpha8,
+    R32F,        // Data is f32 per channel, e.g. for lookup tables
+    Rgba32F,
+    R32Ui,       // Unsigned integers, read with usampler2D or as a render target. Nearest filtering only
+}
+
+impl TextureFormat {
+
+    pub fn internal_format(self) -> GLenum {
+        match self {
+            TextureFormat::R8 => GL_R8,
+            TextureFormat::Rg8 => GL_RG8,
+            TextureFormat::Rgb8 => GL_RGB8,
+            TextureFormat::Rgba8 => GL_RGBA8,
+            TextureFormat::Srgb8 => GL_SRGB8,
+            TextureFormat::Srgb8Alpha8 => GL_SRGB8_ALPHA8,
+            TextureFormat::R32F => GL_R32F,
+            TextureFormat::Rgba32F => GL_RGBA32F,
+            TextureFormat::R32Ui => GL_R32UI,
+        }
+    }
+
+    pub fn pixel_format(self) -> PixelFormat {
+        match self {
+            TextureFormat::R8 | TextureFormat::R32F => GL_RED,
+            TextureFormat::Rg8 => GL_RG,
+            TextureFormat::Rgb8 | TextureFormat::Srgb8 => GL_RGB,
+            TextureFormat::Rgba8 | TextureFormat::Srgb8Alpha8 | TextureFormat::Rgba32F => GL_RGBA,
+            TextureFormat::R32Ui => GL_RED_INTEGER,
+        }
+    }
+
+    pub fn pixel_type(self) -> PixelType {
+        match self {
+            TextureFormat::R32F | TextureFormat::Rgba32F => GL_FLOAT,
+            TextureFormat::R32Ui => GL_UNSIGNED_INT,
+            _ => GL_UNSIGNED_BYTE,
+        }
+    }
+
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            TextureFormat::R8 => 1,
+            TextureFormat::Rg8 => 2,
+            TextureFormat::Rgb8 | TextureFormat::Srgb8 => 3,
+            TextureFormat::Rgba8 | TextureFormat::Srgb8Alpha8 | TextureFormat::R32F | TextureFormat::R32Ui => 4,
+            TextureFormat::Rgba32F => 16,
+        }
+    }
+}
+
+// Generates texture name and sets it up, uploads are up to the caller.
+fn create_texture(gl: &mut impl Backend, target: TextureTarget, sampler: &Sampler) -> u32 {
+    let id = gl.create_texture();
+    gl.texture_sampler(target, id, sampler);
+    id
+}
+
+fn check_len(data: Option<&[u8]>, expected_len: usize) {
+    if let Some(data) = data {
+        assert_eq!(data.len(), expected_len, "Texture data has wrong size");
+    }
+}
+
+
+//
+// ─── TEXTURE 2D ─────────────────────────────────────────────────────────────────
+//
+
+pub struct Texture2D {
+    pub id: u32,
+    #[allow(dead_code)]
+    pub width: u32,
+    #[allow(dead_code)]
+    pub height: u32,
+    pub format: TextureFormat,
+}
+
+impl Texture2D {
+
+    // Creates texture and uploads `data` if any. Mipmaps are generated when the sampler uses them.
+    pub fn new(gl: &mut impl Backend, width: u32, height: u32, format: TextureFormat, data: Option<&[u8]>, sampler: &Sampler) -> Self {
+        check_len(data, (width * height) as usize * format.bytes_per_pixel());
+        let id = create_texture(gl, GL_TEXTURE_2D, sampler);
+        gl.texture_image(GL_TEXTURE_2D, id, format, [width, height, 1], data);
+
+        let texture = Texture2D { id, width, height, format };
+        if data.is_some() && sampler.mipmap_filter.is_some() {
+            texture.generate_mipmaps(gl);
+        }
+        texture
+    }
+
+    pub fn from_image(gl: &mut impl Backend, image: &Image, sampler: &Sampler) -> Self {
+        Self::new(gl, image.width, image.height, TextureFormat::Rgba8, Some(&image.data), sampler)
+    }
+
+    // Replaces part of the base level. Mipmaps have to be regenerated afterwards.
+    #[allow(dead_code)]
+    pub fn update(&self, gl: &mut impl Backend, x: u32, y: u32, width: u32, height: u32, data: &[u8]) {
+        check_len(Some(data), (width * height) as usize * self.format.bytes_per_pixel());
+        gl.texture_sub_image(GL_TEXTURE_2D, self.id, self.format, [x, y, 0], [width, height, 1], data);
+    }
+
+    #[allow(dead_code)]
+    pub fn bind(&self, gl: &mut impl Backend, unit: u32) {
+        gl.bind_texture(unit, GL_TEXTURE_2D, self.id);
+    }
+
+    #[allow(dead_code)]
+    pub fn set_sampler(&self, gl: &mut impl Backend, sampler: &Sampler) {
+        gl.texture_sampler(GL_TEXTURE_2D, self.id, sampler);
+    }
+
+    pub fn generate_mipmaps(&self, gl: &mut impl Backend) {
+        gl.generate_mipmaps(GL_TEXTURE_2D, self.id);
+    }
+
+    // Name shown in GL debug messages and graphics debuggers.
+    pub fn set_label(&self, gl: &mut impl Backend, label: &str) {
+        gl.label(GL_TEXTURE, self.id, label);
+    }
+
+    // Not Drop, it needs the backend that made it.
+    pub fn delete(&self, gl: &mut impl Backend) {
+        gl.delete_texture(self.id);
+    }
+}
+
+
+//
+// ─── TEXTURE 2D ARRAY ───────────────────────────────────────────────────────────
+//
+
+// Stack of same sized 2D layers sampled with sampler2DArray, e.g. terrain splat
+// layers or animation frames. Layer is picked by the third texture coordinate.
+#[allow(dead_code)]
+pub struct Texture2DArray {
+    pub id: u32,
+    pub width: u32,
+    pub height: u32,
+    pub layers: u32,
+    pub format: TextureFormat,
+}
+
+#[allow(dead_code)]
+impl Texture2DArray {
+
+    // `data` holds all layers one after another.
+    pub fn new(gl: &mut impl Backend, width: u32, height: u32, layers: u32, format: TextureFormat, data: Option<&[u8]>, sampler: &Sampler) -> Self {
+        check_len(data, (width * height * layers) as usize * format.bytes_per_pixel());
+        let id = create_texture(gl, GL_TEXTURE_2D_ARRAY, sampler);
+        gl.texture_image(GL_TEXTURE_2D_ARRAY, id, format, [width, height, layers], data); // Depth is the number of layers
+
+        let texture = Texture2DArray { id, width, height, layers, format };
+        if data.is_some() && sampler.mipmap_filter.is_some() {
+            texture.generate_mipmaps(gl);
+        }
+        texture
+    }
+
+    // Every image becomes one layer. All of them have to be the same size.
+    pub fn from_images(gl: &mut impl Backend, images: &[Image], sampler: &Sampler) -> Self {
+        assert!(!images.is_empty(), "Texture array needs at least one layer");
+        let (width, height) = (images[0].width, images[0].height);
+        assert!(
+            images.iter().all(|image| image.width == width && image.height == height),
+            "All texture array layers must have the same size"
+        );
+
+        let data: Vec<u8> = images.iter().flat_map(|image| image.data.iter().copied()).collect();
+        Self::new(gl, width, height, images.len() as u32, TextureFormat::Rgba8, Some(&data), sampler)
+    }
+
+    // Replaces one whole layer. Mipmaps have to be regenerated afterwards.
+    pub fn upload_layer(&self, gl: &mut impl Backend, layer: u32, data: &[u8]) {
+        assert!(layer < self.layers, "Layer {} is out of range", layer);
+        check_len(Some(data), (self.width * self.height) as usize * self.format.bytes_per_pixel());
+        gl.texture_sub_image(GL_TEXTURE_2D_ARRAY, self.id, self.format, [0, 0, layer], [self.width, self.height, 1], data);
+    }
+
+    pub fn bind(&self, gl: &mut impl Backend, unit: u32) {
+        gl.bind_texture(unit, GL_TEXTURE_2D_ARRAY, self.id);
+    }
+
+    pub fn set_sampler(&self, gl: &mut impl Backend, sampler: &Sampler) {
+        gl.texture_sampler(GL_TEXTURE_2D_ARRAY, self.id, sampler);
+    }
+
+    // Each layer gets its own chain, layers are never blended together.
+    pub fn generate_mipmaps(&self, gl: &mut impl Backend) {
+        gl.generate_mipmaps(GL_TEXTURE_2D_ARRAY, self.id);
+    }
+
+    // Name shown in GL debug messages and graphics debuggers.
+    pub fn set_label(&self, gl: &mut impl Backend, label: &str) {
+        gl.label(GL_TEXTURE, self.id, label);
+    }
+
+    pub fn delete(&self, gl: &mut impl Backend) {
+        gl.delete_texture(self.id);
+    }
+}
+
+
+//
+// ─── TEXTURE 3D ─────────────────────────────────────────────────────────────────
+//
+
+// Volume texture sampled with sampler3D, e.g. color grading lookup tables.
+// Unlike arrays it is filtered between slices too.
+#[allow(dead_code)]
+pub struct Texture3D {
+    pub id: u32,
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+    pub format: TextureFormat,
+}
+
+#[allow(dead_code)]
+impl Texture3D {
+
+    // `data` holds slices one after another, each slice row by row.
+    pub fn new(gl: &mut impl Backend, width: u32, height: u32, depth: u32, format: TextureFormat, data: Option<&[u8]>, sampler: &Sampler) -> Self {
+        check_len(data, (width * height * depth) as usize * format.bytes_per_pixel());
+        let id = create_texture(gl, GL_TEXTURE_3D, sampler);
+        gl.texture_image(GL_TEXTURE_3D, id, format, [width, height, depth], data);
+
+        let texture = Texture3D { id, width, height, depth, format };
+        if data.is_some() && sampler.mipmap_filter.is_some() {
+            texture.generate_mipmaps(gl);
+        }
+        texture
+    }
+
+    // Replaces a box of texels. Mipmaps have to be regenerated afterwards.
+    pub fn update(&self, gl: &mut impl Backend, offset: [u32; 3], size: [u32; 3], data: &[u8]) {
+        check_len(Some(data), (size[0] * size[1] * size[2]) as usize * self.format.bytes_per_pixel());
+        gl.texture_sub_image(GL_TEXTURE_3D, self.id, self.format, offset, size, data);
+    }
+
+    pub fn bind(&self, gl: &mut impl Backend, unit: u32) {
+        gl.bind_texture(unit, GL_TEXTURE_3D, self.id);
+    }
+
+    pub fn set_sampler(&self, gl: &mut impl Backend, sampler: &Sampler) {
+        gl.texture_sampler(GL_TEXTURE_3D, self.id, sampler);
+    }
+
+    pub fn generate_mipmaps(&self, gl: &mut impl Backend) {
+        gl.generate_mipmaps(GL_TEXTURE_3D, self.id);
+    }
+
+    // Name shown in GL debug messages and graphics debuggers.
+    pub fn set_label(&self, gl: &mut impl Backend, label: &str) {
+        gl.label(GL_TEXTURE, self.id, label);
+    }
+
+    pub fn delete(&self, gl: &mut impl Backend) {
+        gl.delete_texture(self.id);
+    }
+}
+
+
+//
+// ─── TESTS ──────────────────────────────────────────────────────────────────────
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{Call, MockBackend};
+
+    fn layer(rgba: [u8; 4]) -> Image {
+        let mut image = Image::new(2, 2);
+        for pixel in image.data.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&rgba);
+        }
+        image
+    }
+
+    #[test]
+    fn array_layers_are_uploaded_together_and_one_by_one() {
+        let mut gl = MockBackend::new();
+        let array = Texture2DArray::from_images(&mut gl, &[layer([255; 4]), layer([0; 4]), layer([9; 4])], &Sampler::default());
+        assert_eq!((array.width, array.height, array.layers), (2, 2, 3));
+        assert_eq!(gl.take_calls(), vec![
+            Call::CreateTexture(array.id),
+            Call::TextureSampler { target: GL_TEXTURE_2D_ARRAY.0, texture: array.id, sampler: Sampler::default() },
+            Call::TextureImage { target: GL_TEXTURE_2D_ARRAY.0, texture: array.id, size: [2, 2, 3], len: Some(48) },
+            Call::GenerateMipmaps { target: GL_TEXTURE_2D_ARRAY.0, texture: array.id },
+        ]);
+
+        array.upload_layer(&mut gl, 2, &[0; 16]);
+        array.delete(&mut gl);
+        assert_eq!(gl.take_calls(), vec![
+            Call::TextureSubImage { target: GL_TEXTURE_2D_ARRAY.0, texture: array.id, offset: [0, 0, 2], size: [2, 2, 1], len: 16 },
+            Call::DeleteTexture(array.id),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Layer 3 is out of range")]
+    fn array_layers_past_the_end_are_rejected() {
+        let mut gl = MockBackend::new();
+        let array = Texture2DArray::new(&mut gl, 2, 2, 3, TextureFormat::Rgba8, None, &Sampler::nearest());
+        array.upload_layer(&mut gl, 3, &[0; 16]);
+    }
+
+    #[test]
+    #[should_panic(expected = "same size")]
+    fn array_layers_must_match_in_size() {
+        Texture2DArray::from_images(&mut MockBackend::new(), &[layer([0; 4]), Image::new(1, 2)], &Sampler::nearest());
+    }
+
+    #[test]
+    fn volume_is_uploaded_slice_by_slice() {
+        let mut gl = MockBackend::new();
+        let volume = Texture3D::new(&mut gl, 4, 2, 3, TextureFormat::R8, Some(&[0; 24]), &Sampler::nearest());
+        volume.update(&mut gl, [1, 0, 2], [2, 2, 1], &[255; 4]);
+        assert_eq!(gl.take_calls(), vec![
+            Call::CreateTexture(volume.id),
+            Call::TextureSampler { target: GL_TEXTURE_3D.0, texture: volume.id, sampler: Sampler::nearest() },
+            Call::TextureImage { target: GL_TEXTURE_3D.0, texture: volume.id, size: [4, 2, 3], len: Some(24) }, // No mipmaps with nearest
+            Call::TextureSubImage { target: GL_TEXTURE_3D.0, texture: volume.id, offset: [1, 0, 2], size: [2, 2, 1], len: 4 },
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "wrong size")]
+    fn volume_data_has_to_fill_the_box() {
+        let mut gl = MockBackend::new();
+        let volume = Texture3D::new(&mut gl, 2, 2, 2, TextureFormat::Rgba32F, None, &Sampler::nearest());
+        volume.update(&mut gl, [0, 0, 0], [2, 2, 2], &[0; 64]); // 16 bytes per texel, needs 128
+    }
+}
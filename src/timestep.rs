@@ -0,0 +1,102 @@
+// Decides how many fixed simulation ticks each rendered frame runs. Frame
+// time piles up in an accumulator and is spent one `tick` at a time, so the
+// simulation behaves the same at 30 FPS and at 300. Whatever is left over
+// becomes `alpha`, how far the frame is between the last two ticks, which the
+// renderer uses to interpolate.
+pub struct FixedTimestep {
+    pub tick: f32,            // Seconds of simulated time per tick
+    pub max_ticks: u32,       // Per frame. Past this the simulation slows down instead of spiraling
+    pub paused: bool,
+    accumulator: f32,
+    step_requested: bool,
+    pub ticks: u64,           // Run so far
+}
+
+impl FixedTimestep {
+
+    pub fn new(ticks_per_second: f32) -> Self {
+        FixedTimestep {
+            tick: 1.0 / ticks_per_second,
+            max_ticks: 5,
+            paused: false,
+            accumulator: 0.0,
+            step_requested: false,
+            ticks: 0,
+        }
+    }
+
+    // Runs exactly one tick on the next frame, mostly useful while paused.
+    pub fn request_step(&mut self) {
+        self.step_requested = true;
+    }
+
+    // Adds a frame's worth of real time and returns how many ticks to run now.
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        if self.step_requested {
+            self.step_requested = false;
+            self.ticks += 1;
+            return 1;
+        }
+        if self.paused {
+            return 0;
+        }
+
+        self.accumulator += frame_time.max(0.0);
+        let mut count = 0;
+        while self.accumulator >= self.tick && count < self.max_ticks {
+            self.accumulator -= self.tick;
+            count += 1;
+        }
+        if count == self.max_ticks {
+            // Too far behind to ever catch up, forget the backlog
+            self.accumulator = self.accumulator.min(self.tick * 0.999);
+        }
+        self.ticks += count as u64;
+        count
+    }
+
+    // Between 0 (last tick) and 1 (next tick).
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.tick).clamp(0.0, 1.0)
+    }
+}
+
+
+//
+// ─── TESTS ──────────────────────────────────────────────────────────────────────
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_time_is_spent_in_whole_ticks() {
+        let mut timestep = FixedTimestep::new(10.0); // 0.1 s ticks
+        let ticks: Vec<u32> = [0.05, 0.05, 0.25, 0.01].iter().map(|&dt| timestep.advance(dt)).collect();
+        assert_eq!(ticks, vec![0, 1, 2, 0]);
+        assert_eq!(timestep.ticks, 3);
+        assert!((timestep.alpha() - 0.6).abs() < 1e-4);
+    }
+
+    #[test]
+    fn long_frames_are_capped() {
+        let mut timestep = FixedTimestep::new(60.0);
+        assert_eq!(timestep.advance(2.0), 5);
+        assert!(timestep.alpha() < 1.0);
+        assert_eq!(timestep.advance(0.0), 0); // Backlog was dropped
+    }
+
+    #[test]
+    fn paused_only_moves_on_request() {
+        let mut timestep = FixedTimestep::new(60.0);
+        timestep.paused = true;
+        assert_eq!(timestep.advance(1.0), 0);
+        timestep.request_step();
+        assert_eq!(timestep.advance(1.0), 1);
+        assert_eq!(timestep.advance(1.0), 0);
+
+        timestep.paused = false;
+        assert_eq!(timestep.advance(1.0 / 60.0 + 1e-4), 1); // Time spent paused doesn't count
+    }
+}
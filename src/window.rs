@@ -0,0 +1,255 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use glutin::dpi::PhysicalSize;
+use glutin::event_loop::EventLoop;
+use glutin::window::{Fullscreen, WindowBuilder};
+use glutin::{Api, ContextBuilder, CreationError, GlRequest, NotCurrent, WindowedContext};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{self, ConfigError};
+
+fn number<T: FromStr>(option: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("{} needs a number, got '{}'", option, value))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FullscreenMode {
+    Windowed,
+    Borderless, // Covers the monitor without changing its video mode
+    Exclusive,  // Switches the monitor to the closest video mode to width x height
+}
+
+// How the window and its OpenGL context are created. Read from window.ron,
+// command line options override it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    pub width: u32,
+    pub height: u32,
+    pub fullscreen: FullscreenMode,
+    pub msaa: u16,          // Samples per pixel, 0 => off, otherwise a power of two
+    pub vsync: bool,
+    pub debug_context: bool, // Lets the driver report errors and warnings
+    pub depth_bits: u8,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        WindowConfig {
+            width: 1024,
+            height: 768,
+            fullscreen: FullscreenMode::Windowed,
+            msaa: 0,
+            vsync: true,
+            debug_context: false,
+            depth_bits: 24,
+        }
+    }
+}
+
+impl WindowConfig {
+
+    pub const OPTIONS: [&'static str; 7] = [
+        "--width", "--height", "--fullscreen", "--msaa", "--vsync", "--debug-gl", "--depth-bits",
+    ];
+
+    pub fn load_or_default(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        config::load_or_default(path)
+    }
+
+    #[allow(dead_code)]
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        config::save(self, path)
+    }
+
+    // Applies one of `OPTIONS` with its value, e.g. ("--msaa", "4").
+    pub fn set_option(&mut self, option: &str, value: &str) -> Result<(), String> {
+        let on_off = |value: &str| match value {
+            "on" | "true" => Ok(true),
+            "off" | "false" => Ok(false),
+            _ => Err(format!("{} is either on or off, got '{}'", option, value)),
+        };
+
+        match option {
+            "--width" => self.width = number(option, value)?,
+            "--height" => self.height = number(option, value)?,
+            "--fullscreen" => self.fullscreen = match value {
+                "windowed" | "off" => FullscreenMode::Windowed,
+                "borderless" => FullscreenMode::Borderless,
+                "exclusive" => FullscreenMode::Exclusive,
+                _ => return Err(format!("--fullscreen is windowed, borderless or exclusive, got '{}'", value)),
+            },
+            "--msaa" => self.msaa = number(option, value)?,
+            "--vsync" => self.vsync = on_off(value)?,
+            "--debug-gl" => self.debug_context = on_off(value)?,
+            "--depth-bits" => self.depth_bits = number(option, value)?,
+            _ => return Err(format!("Unknown window option {}", option)),
+        }
+        self.validate()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.width == 0 || self.height == 0 {
+            return Err(format!("Window size {}x{} is empty", self.width, self.height));
+        }
+        if self.msaa != 0 && !self.msaa.is_power_of_two() {
+            return Err(format!("MSAA samples must be a power of two, got {}", self.msaa));
+        }
+        Ok(())
+    }
+
+    // Configs to try in order when the requested one can't be created. Each
+    // step gives up a bit more: fewer MSAA samples, then the debug context,
+    // then a 16 bit depth buffer, then vsync.
+    pub fn fallbacks(&self) -> Vec<WindowConfig> {
+        let mut chain = vec![self.clone()];
+        let mut current = self.clone();
+
+        while current.msaa > 0 {
+            current.msaa /= 2;
+            if current.msaa == 1 {
+                current.msaa = 0; // 1 sample is no multisampling
+            }
+            chain.push(current.clone());
+        }
+        if current.debug_context {
+            current.debug_context = false;
+            chain.push(current.clone());
+        }
+        if current.depth_bits > 16 {
+            current.depth_bits = 16;
+            chain.push(current.clone());
+        }
+        if current.vsync {
+            current.vsync = false;
+            chain.push(current.clone());
+        }
+        chain
+    }
+
+    fn fullscreen<T>(&self, event_loop: &EventLoop<T>) -> Option<Fullscreen> {
+        match self.fullscreen {
+            FullscreenMode::Windowed => None,
+            FullscreenMode::Borderless => Some(Fullscreen::Borderless(event_loop.primary_monitor())),
+            FullscreenMode::Exclusive => {
+                // Closest size, then highest refresh rate
+                let monitor = event_loop.primary_monitor()?;
+                let distance = |size: PhysicalSize<u32>| {
+                    (size.width as i64 - self.width as i64).abs() + (size.height as i64 - self.height as i64).abs()
+                };
+                monitor.video_modes()
+                    .min_by_key(|mode| (distance(mode.size()), std::cmp::Reverse(mode.refresh_rate())))
+                    .map(Fullscreen::Exclusive)
+            }
+        }
+    }
+
+    pub fn build<T>(&self, title: &str, event_loop: &EventLoop<T>) -> Result<WindowedContext<NotCurrent>, CreationError> {
+        let window_builder = WindowBuilder::new()
+            .with_title(title)
+            .with_inner_size(PhysicalSize::new(self.width, self.height))
+            .with_fullscreen(self.fullscreen(event_loop));
+
+        ContextBuilder::new()
+            .with_gl(GlRequest::Specific(Api::OpenGl, (3, 3))) // OpenGL 3.3
+            .with_vsync(self.vsync)
+            .with_multisampling(self.msaa)
+            .with_depth_buffer(self.depth_bits)
+            .with_gl_debug_flag(self.debug_context)
+            .build_windowed(window_builder, event_loop)
+    }
+
+    // Tries the fallback chain until something works. Returns the config that
+    // was actually used, or the error of the last attempt.
+    pub fn build_with_fallbacks<T>(&self, title: &str, event_loop: &EventLoop<T>) -> Result<(WindowedContext<NotCurrent>, WindowConfig), CreationError> {
+        let mut last_error = None;
+        for config in self.fallbacks() {
+            match config.build(title, event_loop) {
+                Ok(context) => {
+                    if config != *self {
+                        log::info!("Using fallback window settings {:?}", config);
+                    }
+                    return Ok((context, config));
+                }
+                Err(e) => {
+                    log::warn!("Could not create window with {:?}: {}", config, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.expect("Fallback chain always has the requested config"))
+    }
+}
+
+
+//
+// ─── TESTS ──────────────────────────────────────────────────────────────────────
+//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options_override_config() {
+        let mut config = WindowConfig::default();
+        config.set_option("--width", "1920").unwrap();
+        config.set_option("--fullscreen", "borderless").unwrap();
+        config.set_option("--msaa", "8").unwrap();
+        config.set_option("--vsync", "off").unwrap();
+        config.set_option("--debug-gl", "on").unwrap();
+
+        assert_eq!(config, WindowConfig {
+            width: 1920,
+            fullscreen: FullscreenMode::Borderless,
+            msaa: 8,
+            vsync: false,
+            debug_context: true,
+            ..WindowConfig::default()
+        });
+    }
+
+    #[test]
+    fn bad_options_are_rejected() {
+        let mut config = WindowConfig::default();
+        assert!(config.set_option("--msaa", "3").is_err());
+        assert!(config.set_option("--width", "wide").is_err());
+        assert!(config.set_option("--vsync", "maybe").is_err());
+        assert!(config.set_option("--height", "0").is_err());
+        assert!(config.set_option("--depth-bits", "300").is_err());
+    }
+
+    #[test]
+    fn config_survives_save_and_load() {
+        let path = std::env::temp_dir().join(format!("window_round_trip_{}.ron", std::process::id()));
+        let config = WindowConfig { fullscreen: FullscreenMode::Exclusive, depth_bits: 32, ..WindowConfig::default() };
+        config.save(&path).unwrap();
+        assert_eq!(WindowConfig::load_or_default(&path).unwrap(), config);
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn missing_fields_come_from_defaults() {
+        let config: WindowConfig = ron::de::from_str("(msaa: 4)").unwrap();
+        assert_eq!(config, WindowConfig { msaa: 4, ..WindowConfig::default() });
+    }
+
+    #[test]
+    fn fallbacks_give_up_features_one_at_a_time() {
+        let requested = WindowConfig { msaa: 8, debug_context: true, ..WindowConfig::default() };
+        let chain: Vec<_> = requested.fallbacks().iter()
+            .map(|c| (c.msaa, c.debug_context, c.depth_bits, c.vsync))
+            .collect();
+
+        assert_eq!(chain, vec![
+            (8, true, 24, true),
+            (4, true, 24, true),
+            (2, true, 24, true),
+            (0, true, 24, true),
+            (0, false, 24, true),
+            (0, false, 16, true),
+            (0, false, 16, false),
+        ]);
+    }
+}